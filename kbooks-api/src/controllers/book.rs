@@ -3,24 +3,23 @@ use actix_web::{ test, web, Error, error, HttpResponse, ResponseError, http};
 use chrono::{Duration, Local, NaiveDateTime, Utc };
 use futures::future::{Future, err};
 
-//For tests
-use dotenv::dotenv;
-use actix_web::{ App};
-// use actix_web::{web, test, http, App};
-use actix_i18n::Translations;
-use gettext_macros::include_i18n;
-
 use kbooks_common::khnum::wiring::{DbPool, Config, make_front_url};
 use kbooks_common::khnum::errors::ServiceError;
 use kbooks_common::khnum::users;
+use kbooks_common::{book_filter, isbn, language};
+use kbooks_common::cover::CoverStore;
+use kbooks_common::partial_date::PartialDate;
 
-use kbooks_common::repository::book_handler;
-use kbooks_common::models::{Book, NewBook};
+use kbooks_common::repository::{author_handler, book_handler, tag_handler};
+use kbooks_common::repository::book_handler::{BookFilters, ListOptions, SortKey, SortOrder, TagsMode};
+use kbooks_common::models::{Author, Book, BookDetails, NewBook, BookChanges, ReadingStatus, Role};
 
 use actix_i18n::I18n;
 use gettext::Catalog;
 use gettext_macros::i18n;
 
+use crate::controllers::{clearable, session_user};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CommandResult {
     success: bool,
@@ -29,20 +28,100 @@ pub struct CommandResult {
 
 // ---------------- Create Action------------
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct NewBookForm {
     title: String,
+    // primary author, used to sort books
     author: String,
     isbn: String,
     publicationdate: String,
+    page_count: Option<i32>,
     language_main: String,
     language_secondary: Option<String>,
     language_original: String,
+    // other contributors, names separated by ";"
+    coauthors: Option<String>,
+    translators: Option<String>,
+    editors: Option<String>,
+    illustrators: Option<String>,
+    preface: Option<String>,
+    // comma separated, as in LibraryThing
+    tags: Option<String>,
+}
+
+// Primary author of the book, none when the field is left empty
+fn form_author(pool: DbPool, raw: &str) -> Result<Option<Author>, ServiceError> {
+    match raw.trim() {
+        "" => Ok(None),
+        name => Ok(Some(author_handler::get_or_create(pool, name)?)),
+    }
+}
+
+// The primary author first, then the other contributors in the form order
+fn form_contributors(pool: DbPool, primary: Option<&Author>, book_form: &NewBookForm) -> Result<Vec<(i32, Role)>, ServiceError> {
+    let mut contributors: Vec<(i32, Role)> = primary.iter().map(|primary| (primary.id, Role::Author)).collect();
+    let by_role = vec![
+        (Role::Author, &book_form.coauthors),
+        (Role::Translator, &book_form.translators),
+        (Role::Editor, &book_form.editors),
+        (Role::Illustrator, &book_form.illustrators),
+        (Role::Preface, &book_form.preface),
+    ];
+    for (role, names) in by_role {
+        let names = match names {
+            Some(names) => names,
+            None => continue,
+        };
+        for name in names.split(';').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+            let author = author_handler::get_or_create(pool.clone(), name)?;
+            contributors.push((author.id, role));
+        }
+    }
+    Ok(contributors)
+}
+
+fn form_title(raw: &str) -> Result<String, ServiceError> {
+    match raw.trim() {
+        "" => Err(ServiceError::BadRequest("title: must not be empty".to_string())),
+        title => Ok(title.to_string()),
+    }
+}
+
+// Empty when the book has no ISBN
+fn form_isbn(raw: &str) -> Result<String, ServiceError> {
+    if raw.trim().is_empty() {
+        return Ok(String::new());
+    }
+    isbn::validate(raw).map_err(|error| ServiceError::BadRequest(format!("isbn: {}", error)))
+}
+
+// Canonical form of the publication date, empty when unknown
+fn form_publicationdate(raw: &str) -> Result<String, ServiceError> {
+    if raw.trim().is_empty() {
+        return Ok(String::new());
+    }
+    raw.parse::<PartialDate>()
+        .map(|date| date.to_string())
+        .map_err(|error| ServiceError::BadRequest(format!("publicationdate: {}", error)))
+}
+
+// Language code, empty when unknown
+fn form_language(field: &str, raw: &str) -> Result<String, ServiceError> {
+    language::normalize(raw).map_err(|error| ServiceError::BadRequest(format!("{}: {}", field, error)))
+}
+
+fn form_page_count(raw: Option<i32>) -> Result<Option<i32>, ServiceError> {
+    match raw {
+        Some(count) if count < 1 => Err(ServiceError::BadRequest("page_count: must be greater than 0".to_string())),
+        _ => Ok(raw),
+    }
 }
 
-//TODO
-fn get_or_create_author_code(author: &String) -> String {
-    "ROUBAUD".to_string()
+fn form_secondary_language(raw: &Option<String>) -> Result<Option<String>, ServiceError> {
+    match raw {
+        Some(raw) => form_language("language_secondary", raw).map(|code| Some(code).filter(|code| !code.is_empty())),
+        None => Ok(None),
+    }
 }
 
 pub async fn create(
@@ -51,168 +130,290 @@ pub async fn create(
     config: web::Data<Config>,
     i18n: I18n
 ) -> Result<HttpResponse, ServiceError> {
-    //TODO : bad input data
     let book_form = book_form.into_inner();
+    let user = session_user(&session)?;
+    let title = form_title(&book_form.title)?;
+    let isbn = form_isbn(&book_form.isbn)?;
+    let publicationdate = form_publicationdate(&book_form.publicationdate)?;
+    let page_count = form_page_count(book_form.page_count)?;
+    let language_main = form_language("language_main", &book_form.language_main)?;
+    let language_secondary = form_secondary_language(&book_form.language_secondary)?;
+    let language_original = form_language("language_original", &book_form.language_original)?;
 
-    #[cfg(test)]
-    let opt = Some(users::models::User::testUser());
-
-    #[cfg(not(test))]
-    let opt = session.get::<users::models::User>("user").expect("could not get session user");
-
-    match opt {
-        None => Err(ServiceError::Unauthorized("User not connected".to_string())),
-        Some(user) => {
-            let author_code = get_or_create_author_code(&book_form.author);
-            let book = NewBook {
-                user_id: user.id, 
-                librarything_id: None,
-                title: book_form.title,
-                author_lf: book_form.author,
-                author_code,
-                isbn: book_form.isbn,
-                publicationdate: book_form.publicationdate,
-                rating: None,
-                language_main: book_form.language_main,
-                language_secondary: book_form.language_secondary,
-                language_original: book_form.language_original,
-                review: None,
-                cover: "".to_string(),
-                created_at: Utc::now().naive_utc(),
-                dateacquired_stamp: None,
-                started_stamp: None,
-                finished_stamp: None
-            };
-
-            //TODO : db error
-            let _book = book_handler::add(config.pool.clone(), book).expect("error when inserting new book");
-            let res = CommandResult {success: true, error: None};
-            Ok(HttpResponse::Ok().json(res))
-        },
-    }
+    let author = form_author(config.pool.clone(), &book_form.author)?;
+    let contributors = form_contributors(config.pool.clone(), author.as_ref(), &book_form)?;
+    let tags = book_form.tags.clone();
+    let book = NewBook {
+        user_id: user.id, 
+        librarything_id: None,
+        title,
+        author_lf: author.as_ref().map(|author| author.name.clone()).unwrap_or_default(),
+        author_code: author.as_ref().map(|author| author.code.clone()).unwrap_or_default(),
+        isbn,
+        publicationdate,
+        rating: None,
+        language_main,
+        language_secondary,
+        language_original,
+        review: None,
+        cover: "".to_string(),
+        created_at: Utc::now().naive_utc(),
+        dateacquired_stamp: None,
+        started_stamp: None,
+        finished_stamp: None,
+        author_id: author.as_ref().map(|author| author.id),
+        page_count,
+    };
+
+    let tags = tags.map(|tags| tag_handler::split(&tags)).unwrap_or_else(Vec::new);
+    book_handler::create(config.pool.clone(), book, &contributors, &tags)?;
+    let res = CommandResult {success: true, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- List Action------------
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ListQuery {
+    page: Option<i64>,
+    limit: Option<i64>,
+    sort: Option<SortKey>,
+    order: Option<SortOrder>,
+    language_main: Option<String>,
+    rated: Option<bool>,
+    finished: Option<bool>,
+    // to-read, reading, read or abandoned
+    status: Option<ReadingStatus>,
+    // ISBN-10 or ISBN-13, whatever the form recorded
+    isbn: Option<String>,
+    // comma separated
+    tags: Option<String>,
+    tags_mode: Option<TagsMode>,
+    // id of a shelf of the user
+    shelf: Option<i32>,
+    // filter expression, see book_filter
+    query: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BooksListCommandResult {
     success: bool,
-    books: Vec<Book>,
+    books: Vec<BookDetails>,
+    total: i64,
+    page: i64,
+    limit: i64,
+    next_page: Option<i64>,
     error: Option<String>
 }
 
 pub async fn list(
     session: Session,
+    query: web::Query<ListQuery>,
     config: web::Data<Config>,
     i18n: I18n
 ) -> Result<HttpResponse, ServiceError> {
-    //TODO : bad input data
+    let user = session_user(&session)?;
+    let query = query.into_inner();
 
-    #[cfg(test)]
-    let opt = Some(users::models::User::testUser());
+    let page = query.page.unwrap_or(1);
+    if page < 1 {
+        return Err(ServiceError::BadRequest("page: must be greater than 0".to_string()));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit < 1 || limit > MAX_PAGE_SIZE {
+        return Err(ServiceError::BadRequest(format!("limit: must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+    let offset = (page - 1).checked_mul(limit)
+        .ok_or_else(|| ServiceError::BadRequest("page: out of range".to_string()))?;
+    let filter_query = match query.query {
+        Some(ref raw) if !raw.trim().is_empty() => Some(book_filter::parse(raw).map_err(|error| ServiceError::BadRequest(format!("query: {}", error)))?),
+        _ => None,
+    };
+    let options = ListOptions {
+        filters: BookFilters {
+            language_main: query.language_main.map(|language| language::normalize_or_keep(&language)),
+            rated: query.rated,
+            finished: query.finished,
+            status: query.status,
+            isbn: query.isbn,
+            tags: query.tags.map(|tags| tag_handler::split(&tags)).unwrap_or_else(Vec::new),
+            tags_mode: query.tags_mode.unwrap_or_default(),
+            shelf: query.shelf,
+            query: filter_query,
+        },
+        sort: query.sort.unwrap_or(SortKey::CreatedAt),
+        order: query.order.unwrap_or(SortOrder::Asc),
+        offset,
+        limit,
+    };
 
-    #[cfg(not(test))]
-    let opt = session.get::<users::models::User>("user").expect("could not get session user");
+    let (books, total) = book_handler::list_page(config.pool.clone(), user.id, &options)?;
+    let next_page = if options.offset + (books.len() as i64) < total { Some(page + 1) } else { None };
+    let books = book_handler::with_details(config.pool.clone(), books)?;
+    let res = BooksListCommandResult {success: true, books, total, page, limit, next_page, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
 
-    match opt {
-        None => Err(ServiceError::Unauthorized("User not connected".to_string())),
-        Some(user) => {
-            //TODO : db error
-            let books = book_handler::list(config.pool.clone()).expect("error getting books list");
-            let res = BooksListCommandResult {success: true, books, error: None};
-            Ok(HttpResponse::Ok().json(res))
-        },
+// ---------------- Search Action------------
+
+const SEARCH_LIMIT: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchQuery {
+    q: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BooksSearchCommandResult {
+    success: bool,
+    books: Vec<BookDetails>,
+    error: Option<String>
+}
+
+pub async fn search(
+    session: Session,
+    query: web::Query<SearchQuery>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let terms = query.into_inner().q;
+    if terms.trim().is_empty() {
+        return Err(ServiceError::BadRequest("q: search terms are missing".to_string()));
     }
+    let books = book_handler::search(config.pool.clone(), user.id, terms.trim(), SEARCH_LIMIT)?;
+    let books = book_handler::with_details(config.pool.clone(), books)?;
+    let res = BooksSearchCommandResult {success: true, books, error: None};
+    Ok(HttpResponse::Ok().json(res))
 }
 
-// #[cfg(test)]
-// mod tests;
-//// #[path = "./book_test.rs"] // avoid creating a /register folder
-//// mod book_test;
-
-
-pub fn managed_state() -> Translations {
-    include_i18n!()
-}
-
-#[actix_rt::test]
-async fn test_create() {
-    dotenv().ok();
-    let srv = test::start( || {
-        let pool = kbooks_common::khnum::wiring::test_conn_init();
-        let conn = &pool.get().unwrap();
-        App::new()
-            .app_data(managed_state())
-            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")}).service(
-                                                                                                web::scope("/book")
-                                                                                                .service( web::resource("/create").route(
-                                                                                                        web::post().to(create)
-                                                                                                )
-                                                                                                )
-            )
-    });
-
-    let form = NewBookForm { 
-        title: "Le grand incendie de Londres".to_string(),
-        author: "Roubaud, Jacques".to_string(),
-        isbn: "2020104725".to_string(),
-        publicationdate: "1989-01".to_string(),
-        language_main: "FR".to_string(),
-        language_secondary: None,
-        language_original: "FR".to_string(),
+// ---------------- Get Action------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BookCommandResult {
+    success: bool,
+    book: BookDetails,
+    error: Option<String>
+}
+
+fn details(pool: DbPool, book: Book) -> Result<BookDetails, ServiceError> {
+    let mut books = book_handler::with_details(pool, vec![book])?;
+    Ok(books.remove(0))
+}
+
+pub async fn get(
+    session: Session,
+    book_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book = book_handler::get(config.pool.clone(), user.id, book_id.into_inner())?;
+    let res = BookCommandResult {success: true, book: details(config.pool.clone(), book)?, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Update Actions------------
+
+// Replaces the book descriptive data (PUT)
+pub async fn update(
+    session: Session,
+    book_id: web::Path<i32>,
+    book_form: web::Form<NewBookForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_form = book_form.into_inner();
+    let title = form_title(&book_form.title)?;
+    let isbn = form_isbn(&book_form.isbn)?;
+    let publicationdate = form_publicationdate(&book_form.publicationdate)?;
+    let page_count = form_page_count(book_form.page_count)?;
+    let language_main = form_language("language_main", &book_form.language_main)?;
+    let language_secondary = form_secondary_language(&book_form.language_secondary)?;
+    let language_original = form_language("language_original", &book_form.language_original)?;
+    let book_id = book_id.into_inner();
+    // before creating the authors of the form
+    if !book_handler::is_owner(config.pool.clone(), user.id, book_id)? {
+        return Err(ServiceError::NotFound("book: unknown book".to_string()));
+    }
+    let author = form_author(config.pool.clone(), &book_form.author)?;
+    let contributors = form_contributors(config.pool.clone(), author.as_ref(), &book_form)?;
+    let tags = book_form.tags.clone();
+    let changes = BookChanges {
+        title: Some(title),
+        author_lf: Some(author.as_ref().map(|author| author.name.clone()).unwrap_or_default()),
+        author_code: Some(author.as_ref().map(|author| author.code.clone()).unwrap_or_default()),
+        author_id: Some(author.as_ref().map(|author| author.id)),
+        isbn: Some(isbn),
+        publicationdate: Some(publicationdate),
+        // cleared when not given
+        page_count: Some(page_count),
+        language_main: Some(language_main),
+        language_secondary: Some(language_secondary),
+        language_original: Some(language_original),
+        ..BookChanges::default()
     };
+    let tags = tags.map(|tags| tag_handler::split(&tags));
+    let book = book_handler::replace(config.pool.clone(), user.id, book_id, &changes, &contributors, tags.as_deref())?;
+    let res = BookCommandResult {success: true, book: details(config.pool.clone(), book)?, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
 
-    let req = srv.post("/book/create")
-        .timeout(std::time::Duration::new(15, 0));
-        // .header( http::header::CONTENT_TYPE, http::header::HeaderValue::from_static("application/json"),);
-
-    let mut response = req.send_form(&form).await.unwrap();
-    assert!(response.status().is_success());
-    let result: CommandResult = response.json().await.expect("Could not parse json"); 
-    assert!(result.success);
-}
-
-use diesel::prelude::*;
-use kbooks_common::schema::books::dsl;
-#[actix_rt::test]
-async fn test_list() {
-    dotenv().ok();
-    let srv = test::start( || {
-        let pool = kbooks_common::khnum::wiring::test_conn_init();
-        let conn = &pool.get().unwrap();
-        let book = NewBook {
-            user_id: 1,
-            librarything_id: None,
-            title: "a title".to_string(),
-            author_lf: "Authorlf".to_string(),
-            author_code: "AUT".to_string(),
-            isbn: "1234564654654654645".to_string(),
-            publicationdate: "2019-03-02".to_string(),
-            language_original: "FR".to_string(),
-            language_main: "FR".to_string(),
-            language_secondary: None,
-            review: None,
-            rating: None,
-            cover: "".to_string(),
-            created_at: Utc::now().naive_utc(),
-            dateacquired_stamp: None,
-            started_stamp: None,
-            finished_stamp: None,
-        };
-        diesel::insert_into(dsl::books).values(&book)
-            .execute(conn).expect("Error populating test database");
-        App::new()
-            .app_data(managed_state())
-            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")})
-            .service( web::resource("/book")
-                      .route( web::get().to(list))
-            )
-    });
-
-    let req = srv.get("/book")
-        .timeout(std::time::Duration::new(15, 0));
-        // .header( http::header::CONTENT_TYPE, http::header::HeaderValue::from_static("application/json"),);
-
-    let mut response = req.send().await.unwrap();
-    assert!(response.status().is_success());
-    let result: BooksListCommandResult = response.json().await.expect("Could not parse json"); 
-    assert!(result.books.len() == 1);
+// Fields not sent are left unchanged, fields sent empty are cleared
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct UpdateBookForm {
+    #[serde(default, deserialize_with = "clearable")]
+    rating: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "clearable")]
+    review: Option<Option<String>>,
+    #[serde(default, deserialize_with = "clearable")]
+    dateacquired_stamp: Option<Option<NaiveDateTime>>,
+}
+
+// Partial update of the reading data (PATCH), the reading dates are set through the readings
+pub async fn patch(
+    session: Session,
+    book_id: web::Path<i32>,
+    book_form: web::Form<UpdateBookForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_form = book_form.into_inner();
+    if let Some(Some(rating)) = book_form.rating {
+        if rating < 0 || rating > 5 {
+            return Err(ServiceError::BadRequest("rating: must be between 0 and 5".to_string()));
+        }
+    }
+    let changes = BookChanges {
+        rating: book_form.rating,
+        review: book_form.review,
+        dateacquired_stamp: book_form.dateacquired_stamp,
+        ..BookChanges::default()
+    };
+    let book = book_handler::update(config.pool.clone(), user.id, book_id.into_inner(), &changes)?;
+    let res = BookCommandResult {success: true, book: details(config.pool.clone(), book)?, error: None};
+    Ok(HttpResponse::Ok().json(res))
 }
+
+// ---------------- Delete Action------------
+
+// The cover files are removed with the book
+pub async fn delete(
+    session: Session,
+    book_id: web::Path<i32>,
+    config: web::Data<Config>,
+    covers: web::Data<CoverStore>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book = book_handler::get(config.pool.clone(), user.id, book_id.into_inner())?;
+    book_handler::delete(config.pool.clone(), user.id, book.id)?;
+    if !book.cover.is_empty() {
+        covers.delete(&book.cover).map_err(|_| ServiceError::InternalServerError)?;
+    }
+    let res = CommandResult {success: true, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
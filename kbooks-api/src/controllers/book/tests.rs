@@ -0,0 +1,431 @@
+use actix_web::{web, test, http, App};
+use chrono::Utc;
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::models::Role;
+use kbooks_common::repository::{author_handler, book_handler};
+
+use crate::controllers::fixtures::{managed_state, test_book, test_cover_store, insert_books, populate_two_users};
+
+use super::{CommandResult, BooksListCommandResult, BooksSearchCommandResult, BookCommandResult, NewBookForm, UpdateBookForm};
+
+#[actix_rt::test]
+async fn test_create() {
+    dotenv().ok();
+    let srv = test::start( || {
+        let pool = kbooks_common::khnum::wiring::test_conn_init();
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::scope("/book")
+                      .service( web::resource("/create").route( web::post().to(super::create)))
+            )
+    });
+
+    let form = NewBookForm { 
+        title: "Le grand incendie de Londres".to_string(),
+        author: "Roubaud, Jacques".to_string(),
+        isbn: "2020104725".to_string(),
+        publicationdate: "1989-01".to_string(),
+        language_main: "FR".to_string(),
+        language_secondary: None,
+        language_original: "FR".to_string(),
+        ..NewBookForm::default()
+    };
+
+    let req = srv.post("/book/create")
+        .timeout(Duration::new(15, 0));
+
+    let mut response = req.send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: CommandResult = response.json().await.expect("Could not parse json"); 
+    assert!(result.success);
+}
+
+#[actix_rt::test]
+async fn test_isbn() {
+    dotenv().ok();
+    let srv = test::start( || {
+        let pool = kbooks_common::khnum::wiring::test_conn_init();
+        populate_two_users(&pool.get().unwrap());
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book")
+                      .route( web::post().to(super::create))
+                      .route( web::get().to(super::list))
+            )
+    });
+
+    let mut form = NewBookForm {
+        title: "Exercices de style".to_string(),
+        author: "Queneau, Raymond".to_string(),
+        isbn: "1234564654654654645".to_string(),
+        publicationdate: "1947".to_string(),
+        language_main: "FR".to_string(),
+        language_original: "FR".to_string(),
+        ..NewBookForm::default()
+    };
+    let response = srv.post("/book").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    // Wrong check digit
+    form.isbn = "2-07-036822-8".to_string();
+    let response = srv.post("/book").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    form.isbn = "2-07-036822-X".to_string();
+    let response = srv.post("/book").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+
+    // Found with the ISBN-13 form
+    let mut response = srv.get("/book?isbn=978-2-07-036822-8").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json");
+    // the fixture book has the same ISBN-13
+    assert_eq!(result.total, 2);
+    let isbns: Vec<String> = result.books.into_iter().map(|details| details.book.isbn).collect();
+    assert!(isbns.contains(&String::from("207036822X")));
+}
+
+#[actix_rt::test]
+async fn test_publicationdate() {
+    dotenv().ok();
+    let srv = test::start( || {
+        let pool = kbooks_common::khnum::wiring::test_conn_init();
+        populate_two_users(&pool.get().unwrap());
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book")
+                      .route( web::post().to(super::create))
+                      .route( web::get().to(super::list))
+            )
+    });
+
+    let mut form = NewBookForm {
+        title: " ".to_string(),
+        author: "Platon".to_string(),
+        publicationdate: "ca. 375 BC".to_string(),
+        ..NewBookForm::default()
+    };
+    let response = srv.post("/book").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    form = NewBookForm {
+        title: "La République".to_string(),
+        author: "Platon".to_string(),
+        publicationdate: "2019-02-30".to_string(),
+        language_main: "FR".to_string(),
+        language_original: "GRC".to_string(),
+        ..NewBookForm::default()
+    };
+    let response = srv.post("/book").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    form.publicationdate = "ca. 375 BC".to_string();
+    let response = srv.post("/book").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+
+    // Stored in canonical form, sorted before the fixture book of 2019
+    let mut response = srv.get("/book?sort=publicationdate").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json");
+    let dates: Vec<String> = result.books.into_iter().map(|details| details.book.publicationdate).collect();
+    assert_eq!(dates, vec![String::from("c. 375 BCE"), String::from("2019-03-02")]);
+}
+
+#[test]
+fn test_author_codes() {
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+
+    let roubaud = author_handler::get_or_create(pool.clone(), "Roubaud, Jacques").unwrap();
+    assert_eq!(roubaud.name, "Roubaud, Jacques");
+    assert_eq!(roubaud.code, "roubaudjacques");
+
+    // same author written differently
+    let same = author_handler::get_or_create(pool.clone(), "Jacques  Roubaud").unwrap();
+    assert_eq!(same.id, roubaud.id);
+    assert_eq!(same.code, "roubaudjacques");
+
+    // different names with the same base code
+    let first = author_handler::get_or_create(pool.clone(), "Dupont, Jean-Marie").unwrap();
+    let second = author_handler::get_or_create(pool.clone(), "Dupont, Jean Marie").unwrap();
+    assert_eq!(first.code, "dupontjeanmarie");
+    assert_eq!(second.code, "dupontjeanmarie-2");
+    assert_eq!(author_handler::find_by_code(pool.clone(), "dupontjeanmarie-2").unwrap(), Some(second));
+}
+
+#[actix_rt::test]
+async fn test_list() {
+    dotenv().ok();
+    let srv = test::start( || {
+        let pool = kbooks_common::khnum::wiring::test_conn_init();
+        populate_two_users(&pool.get().unwrap());
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book")
+                      .route( web::get().to(super::list))
+            )
+    });
+
+    let req = srv.get("/book")
+        .timeout(Duration::new(15, 0));
+
+    let mut response = req.send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json"); 
+    // Only the books of the connected user are listed
+    assert!(result.books.len() == 1);
+    assert_eq!(result.books[0].book.user_id, 1);
+    assert_eq!(result.books[0].book.title, "a title");
+    assert_eq!(result.total, 1);
+    assert_eq!(result.next_page, None);
+}
+
+#[actix_rt::test]
+async fn test_list_pages() {
+    dotenv().ok();
+    let srv = test::start( || {
+        let pool = kbooks_common::khnum::wiring::test_conn_init();
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        let mut books = vec![
+            test_book(1, "c title"),
+            test_book(1, "b title"),
+            test_book(1, "d title"),
+        ];
+        books[0].rating = Some(3);
+        books[1].rating = Some(5);
+        books[1].language_main = "en".to_string();
+        books[2].finished_stamp = Some(Utc::now().naive_utc());
+        insert_books(conn, books);
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book")
+                      .route( web::get().to(super::list))
+            )
+    });
+
+    //==== Sorted pages
+    let mut response = srv.get("/book?sort=title&order=desc&limit=3").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.total, 4);
+    assert_eq!(result.next_page, Some(2));
+    let titles: Vec<&str> = result.books.iter().map(|b| b.book.title.as_str()).collect();
+    assert_eq!(titles, vec!["d title", "c title", "b title"]);
+
+    let mut response = srv.get("/book?sort=title&order=desc&limit=3&page=2").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.books.len(), 1);
+    assert_eq!(result.books[0].book.title, "a title");
+    assert_eq!(result.next_page, None);
+
+    // unrated books last in both orders
+    for (order, expected) in vec![("desc", ["b title", "c title"]), ("asc", ["c title", "b title"])] {
+        let mut response = srv.get(format!("/book?sort=rating&order={}", order)).timeout(Duration::new(15, 0)).send().await.unwrap();
+        let result: BooksListCommandResult = response.json().await.expect("Could not parse json");
+        let titles: Vec<&str> = result.books.iter().map(|b| b.book.title.as_str()).collect();
+        assert_eq!(titles[..2], expected, "{}", order);
+    }
+
+    //==== Filters
+    let mut response = srv.get("/book?rated=true&sort=rating&order=desc").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json"); 
+    let titles: Vec<&str> = result.books.iter().map(|b| b.book.title.as_str()).collect();
+    assert_eq!(titles, vec!["b title", "c title"]);
+
+    // Language names and codes are accepted
+    let mut response = srv.get("/book?language_main=English").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.total, 1);
+
+    let mut response = srv.get("/book?finished=true").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksListCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.books[0].book.title, "d title");
+
+    //==== Bad parameters
+    for query in &["limit=0", "page=0", "page=9223372036854775807"] {
+        let response = srv.get(format!("/book?{}", query)).timeout(Duration::new(15, 0)).send().await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST, "{}", query);
+    }
+}
+
+#[actix_rt::test]
+async fn test_search() {
+    dotenv().ok();
+    let srv = test::start( || {
+        let pool = kbooks_common::khnum::wiring::test_conn_init();
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        let mut books = vec![
+            test_book(1, "Le grand incendie de Londres"),
+            test_book(1, "Éloge de l'ombre"),
+            test_book(2, "Londres, incendie"),
+        ];
+        books[1].review = Some("Un essai sur l'esthétique japonaise".to_string());
+        insert_books(conn, books);
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/search")
+                      .route( web::get().to(super::search))
+            )
+    });
+
+    let mut response = srv.get("/book/search?q=incendie%20londres").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: BooksSearchCommandResult = response.json().await.expect("Could not parse json"); 
+    // the other user's book is not found
+    assert_eq!(result.books.len(), 1);
+    assert_eq!(result.books[0].book.title, "Le grand incendie de Londres");
+
+    // accents are ignored, in the title as well as in the review
+    let mut response = srv.get("/book/search?q=eloge").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksSearchCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.books.len(), 1);
+    let mut response = srv.get("/book/search?q=esthetique").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BooksSearchCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.books[0].book.title, "Éloge de l'ombre");
+
+    let response = srv.get("/book/search?q=").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_is_owner() {
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    populate_two_users(&pool.get().unwrap());
+
+    assert!(book_handler::is_owner(pool.clone(), 1, 1).unwrap());
+    assert!(!book_handler::is_owner(pool.clone(), 1, 2).unwrap());
+    assert!(book_handler::is_owner(pool.clone(), 2, 2).unwrap());
+    assert!(!book_handler::is_owner(pool.clone(), 2, 42).unwrap());
+    assert_eq!(book_handler::list(pool.clone(), 2).unwrap().len(), 2);
+}
+
+#[actix_rt::test]
+async fn test_crud() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    populate_two_users(&pool.get().unwrap());
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .data(test_cover_store("crud"))
+            .service( web::resource("/book/{id}")
+                      .route( web::get().to(super::get))
+                      .route( web::put().to(super::update))
+                      .route( web::patch().to(super::patch))
+                      .route( web::delete().to(super::delete))
+            )
+    });
+
+    //==== Get
+    let mut response = srv.get("/book/1").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: BookCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.book.book.title, "a title");
+
+    // Book of another user
+    let response = srv.get("/book/2").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    //==== Put
+    let put_form = NewBookForm { 
+        title: "La Boucle".to_string(),
+        author: "Roubaud, Jacques".to_string(),
+        isbn: "2020104725".to_string(),
+        publicationdate: "1993".to_string(),
+        language_main: "FR".to_string(),
+        language_secondary: Some("EN".to_string()),
+        language_original: "FR".to_string(),
+        translators: Some("Waldrop, Rosmarie".to_string()),
+        illustrators: Some("Jacques Roubaud; Doré, Gustave".to_string()),
+        ..NewBookForm::default()
+    };
+    let mut response = srv.put("/book/1").timeout(Duration::new(15, 0)).send_form(&put_form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BookCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.book.book.title, "La Boucle");
+    assert_eq!(result.book.book.author_code, "roubaudjacques");
+    assert!(result.book.book.author_id.is_some());
+    let contributors: Vec<(&str, Role)> = result.book.contributors.iter().map(|c| (c.name.as_str(), c.role)).collect();
+    assert_eq!(contributors, vec![
+               ("Roubaud, Jacques", Role::Author),
+               ("Waldrop, Rosmarie", Role::Translator),
+               ("Roubaud, Jacques", Role::Illustrator),
+               ("Doré, Gustave", Role::Illustrator),
+    ]);
+    assert_eq!(result.book.book.publicationdate, "1993");
+    assert_eq!(result.book.book.language_secondary, Some("en".to_string()));
+
+    // the authors of the form are not created for the book of another user
+    let other_form = NewBookForm {
+        title: "Inconnu".to_string(),
+        author: "Inconnu, Auteur".to_string(),
+        language_main: "fr".to_string(),
+        language_original: "fr".to_string(),
+        ..NewBookForm::default()
+    };
+    let response = srv.put("/book/2").timeout(Duration::new(15, 0)).send_form(&other_form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    let blank_title = NewBookForm { title: String::new(), ..other_form };
+    let response = srv.put("/book/1").timeout(Duration::new(15, 0)).send_form(&blank_title).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    // no author
+    let anonymous_form = NewBookForm { title: "Beowulf".to_string(), author: " ".to_string(), ..NewBookForm::default() };
+    let mut response = srv.put("/book/1").timeout(Duration::new(15, 0)).send_form(&anonymous_form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BookCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!((result.book.book.author_lf.as_str(), result.book.book.author_id), ("", None));
+    assert!(result.book.contributors.is_empty());
+    assert!(author_handler::find_by_code(pool.clone(), "").unwrap().is_none());
+    let response = srv.put("/book/1").timeout(Duration::new(15, 0)).send_form(&put_form).await.unwrap();
+    assert!(response.status().is_success());
+    assert!(author_handler::find_by_code(pool.clone(), "inconnuauteur").unwrap().is_none());
+
+    //==== Patch
+    let form = UpdateBookForm { rating: Some(Some(4)), review: Some(Some("Great".to_string())), ..UpdateBookForm::default() };
+    let mut response = srv.patch("/book/1").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BookCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.book.book.rating, Some(4));
+    assert_eq!(result.book.book.review, Some("Great".to_string()));
+    // unchanged
+    assert_eq!(result.book.book.title, "La Boucle");
+
+    let form = UpdateBookForm { rating: Some(Some(12)), ..UpdateBookForm::default() };
+    let response = srv.patch("/book/1").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    // a field sent empty is cleared
+    let mut response = srv.patch("/book/1").timeout(Duration::new(15, 0)).send_form(&[("rating", "")]).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BookCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.book.book.rating, None);
+    assert_eq!(result.book.book.review, Some("Great".to_string()));
+
+    // the full replace clears the secondary language, the reading data is kept
+    let form = NewBookForm { language_secondary: None, ..put_form };
+    let mut response = srv.put("/book/1").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BookCommandResult = response.json().await.expect("Could not parse json"); 
+    assert_eq!(result.book.book.language_secondary, None);
+    assert_eq!(result.book.book.review, Some("Great".to_string()));
+
+    //==== Delete
+    let response = srv.delete("/book/2").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let response = srv.delete("/book/1").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let response = srv.get("/book/1").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,248 @@
+use actix_i18n::I18n;
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+use gettext::Catalog;
+use gettext_macros::i18n;
+
+use kbooks_common::khnum::wiring::{Config, DbPool};
+use kbooks_common::khnum::errors::ServiceError;
+use kbooks_common::khnum::users::models::User;
+use kbooks_common::khnum::users::repository::user_handler;
+
+use kbooks_common::borrow::{self, BorrowAction};
+use kbooks_common::repository::{borrow_handler, loan_handler};
+use kbooks_common::models::{BorrowRequest, BorrowRequestDetails, BorrowState};
+
+use crate::controllers::{non_empty, session_user};
+use crate::controllers::loan::already_lent;
+use crate::khnum::mail::{escape, send_mail};
+
+// ---------------- Requests of the user ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BorrowRequestsCommandResult {
+    success: bool,
+    // requests for the books of the user, most recent first
+    received: Vec<BorrowRequestDetails>,
+    // requests made by the user, most recent first
+    sent: Vec<BorrowRequestDetails>,
+    error: Option<String>
+}
+
+pub async fn list(
+    session: Session,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let received = borrow_handler::received(config.pool.clone(), user.id)?;
+    let sent = borrow_handler::sent(config.pool.clone(), user.id)?;
+    let res = BorrowRequestsCommandResult {success: true, received, sent, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Notifications ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BorrowCommandResult {
+    success: bool,
+    request: BorrowRequest,
+    // false when a mail could not be sent, the error tells why
+    notified: bool,
+    error: Option<String>
+}
+
+fn book_title(pool: DbPool, request: &BorrowRequest) -> Result<String, ServiceError> {
+    let details = borrow_handler::get_details(pool, request.owner_id, request.id)?;
+    Ok(details.book.title)
+}
+
+// Mails are sent to the given users, the request is answered even when they fail
+fn notify(request: BorrowRequest, mails: Vec<(&User, String, String)>) -> HttpResponse {
+    let errors: Vec<String> = mails.into_iter()
+        .filter_map(|(recipient, subject, body)| send_mail(&recipient.email, &subject, body).get_error())
+        .collect();
+    let error = if errors.is_empty() { None } else { Some(errors.join("\n")) };
+    let res = BorrowCommandResult {success: true, request, notified: error.is_none(), error};
+    HttpResponse::Ok().json(res)
+}
+
+fn users(pool: DbPool, request: &BorrowRequest) -> Result<(User, User), ServiceError> {
+    let owner = user_handler::get(pool.clone(), request.owner_id)?;
+    let requester = user_handler::get(pool, request.requester_id)?;
+    Ok((owner, requester))
+}
+
+fn request_mail(catalog: &Catalog, requester: &User, title: &str, message: &Option<String>) -> String {
+    format!(
+        "<strong>{login}</strong> {msg_request} <em>{title}</em>.<br/>
+         {message}",
+        login = escape(&requester.login),
+        msg_request = i18n!(catalog, "would like to borrow"),
+        title = escape(title),
+        message = message.as_ref().map(|message| escape(message)).unwrap_or_default(),
+    )
+}
+
+fn answer_mail(catalog: &Catalog, owner: &User, title: &str, state: BorrowState, due: Option<NaiveDateTime>) -> String {
+    let answer = match state {
+        BorrowState::Accepted => i18n!(catalog, "accepted to lend you"),
+        BorrowState::Declined => i18n!(catalog, "declined to lend you"),
+        _ => i18n!(catalog, "recorded the return of"),
+    };
+    let due = due
+        .map(|due| format!("<br/>{} <strong>{}</strong>", i18n!(catalog, "The book is due on"), due.format("%A, %-d %B, %C%y")))
+        .unwrap_or_default();
+    format!(
+        "<strong>{login}</strong> ({email}) {answer} <em>{title}</em>.{due}",
+        login = escape(&owner.login),
+        email = escape(&owner.email),
+        answer = answer,
+        title = escape(title),
+        due = due,
+    )
+}
+
+fn lent_mail(catalog: &Catalog, requester: &User, title: &str) -> String {
+    format!(
+        "<em>{title}</em> {msg_lent} <strong>{login}</strong> ({email}).",
+        title = escape(title),
+        msg_lent = i18n!(catalog, "is now lent to"),
+        login = escape(&requester.login),
+        email = escape(&requester.email),
+    )
+}
+
+// ---------------- Request to borrow a book ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct BorrowForm {
+    book_id: i32,
+    message: Option<String>,
+}
+
+// Only the books visible to the user can be requested, see friend_handler. The owner of the book is notified
+pub async fn create(
+    session: Session,
+    borrow_form: web::Form<BorrowForm>,
+    config: web::Data<Config>,
+    i18n: I18n
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_id = borrow_form.book_id;
+    if borrow_handler::owner_of(config.pool.clone(), user.id, book_id)? == user.id {
+        return Err(ServiceError::BadRequest("book_id: the book is already yours".to_string()));
+    }
+    if borrow_handler::pending(config.pool.clone(), user.id, book_id)?.is_some() {
+        return Err(ServiceError::BadRequest("book_id: the book is already requested".to_string()));
+    }
+    let request = borrow_handler::request(config.pool.clone(), user.id, book_id, non_empty(&borrow_form.message))?;
+
+    let (owner, requester) = users(config.pool.clone(), &request)?;
+    let title = book_title(config.pool.clone(), &request)?;
+    let body = request_mail(&i18n.catalog, &requester, &title, &request.message);
+    Ok(notify(request, vec![(&owner, i18n!(i18n.catalog, "Borrow request"), body)]))
+}
+
+// ---------------- Answer a request ------------
+
+// Only the owner of the book answers, in the order allowed by borrow::next
+fn owned_request(pool: DbPool, owner_id: i32, request_id: i32, action: BorrowAction) -> Result<BorrowRequest, ServiceError> {
+    let request = borrow_handler::get(pool, owner_id, request_id)?;
+    if request.owner_id != owner_id {
+        return Err(ServiceError::NotFound("request: unknown request".to_string()));
+    }
+    let state = request.state.parse::<BorrowState>().map_err(|_| ServiceError::InternalServerError)?;
+    borrow::next(state, action).map_err(|error| ServiceError::BadRequest(format!("state: {}", error)))?;
+    Ok(request)
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct AcceptForm {
+    // now when not given
+    date: Option<NaiveDateTime>,
+    due: Option<NaiveDateTime>,
+}
+
+// The book is lent to the requester : both users are notified
+pub async fn accept(
+    session: Session,
+    request_id: web::Path<i32>,
+    accept_form: web::Form<AcceptForm>,
+    config: web::Data<Config>,
+    i18n: I18n
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let request = owned_request(config.pool.clone(), user.id, request_id.into_inner(), BorrowAction::Accept)?;
+    let date = accept_form.date.unwrap_or_else(|| Utc::now().naive_utc());
+    if accept_form.due.map(|due| due < date).unwrap_or(false) {
+        return Err(ServiceError::BadRequest("due: the book is due before it is lent".to_string()));
+    }
+    if loan_handler::current(config.pool.clone(), user.id, request.book_id)?.is_some() {
+        return Err(already_lent());
+    }
+    let (owner, requester) = users(config.pool.clone(), &request)?;
+    let request = match borrow_handler::accept(config.pool.clone(), user.id, request.id, &requester.login, date, accept_form.due) {
+        // lent concurrently, see loans_in_progress_idx
+        Err(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => return Err(already_lent()),
+        result => result?,
+    };
+
+    let title = book_title(config.pool.clone(), &request)?;
+    let subject = i18n!(i18n.catalog, "Borrow request accepted");
+    let mails = vec![
+        (&requester, subject.clone(), answer_mail(&i18n.catalog, &owner, &title, BorrowState::Accepted, accept_form.due)),
+        (&owner, subject, lent_mail(&i18n.catalog, &requester, &title)),
+    ];
+    Ok(notify(request, mails))
+}
+
+pub async fn decline(
+    session: Session,
+    request_id: web::Path<i32>,
+    config: web::Data<Config>,
+    i18n: I18n
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let request = owned_request(config.pool.clone(), user.id, request_id.into_inner(), BorrowAction::Decline)?;
+    let request = borrow_handler::decline(config.pool.clone(), user.id, request.id)?;
+
+    let (owner, requester) = users(config.pool.clone(), &request)?;
+    let title = book_title(config.pool.clone(), &request)?;
+    let body = answer_mail(&i18n.catalog, &owner, &title, BorrowState::Declined, None);
+    Ok(notify(request, vec![(&requester, i18n!(i18n.catalog, "Borrow request declined"), body)]))
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReturnForm {
+    // now when not given
+    date: Option<NaiveDateTime>,
+}
+
+// Ends the loan of an accepted request, the requester is notified
+pub async fn give_back(
+    session: Session,
+    request_id: web::Path<i32>,
+    return_form: web::Form<ReturnForm>,
+    config: web::Data<Config>,
+    i18n: I18n
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let request = owned_request(config.pool.clone(), user.id, request_id.into_inner(), BorrowAction::Return)?;
+    let date = return_form.date.unwrap_or_else(|| Utc::now().naive_utc());
+    if let Some(loan) = loan_handler::current(config.pool.clone(), user.id, request.book_id)? {
+        if loan.lent_at > date {
+            return Err(ServiceError::BadRequest("date: the book is returned before it was lent".to_string()));
+        }
+    }
+    let request = borrow_handler::give_back(config.pool.clone(), user.id, request.id, date)?;
+
+    let (owner, requester) = users(config.pool.clone(), &request)?;
+    let title = book_title(config.pool.clone(), &request)?;
+    let body = answer_mail(&i18n.catalog, &owner, &title, BorrowState::Returned, None);
+    Ok(notify(request, vec![(&requester, i18n!(i18n.catalog, "Borrowed book returned"), body)]))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,116 @@
+use actix_web::{web, test, http, App};
+use chrono::{NaiveDate, NaiveDateTime};
+use dotenv::dotenv;
+use gettext::Catalog;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::khnum::users::models::User;
+use kbooks_common::repository::{borrow_handler, friend_handler, loan_handler};
+
+use crate::controllers::fixtures::{managed_state, populate_two_users};
+
+use super::{request_mail, AcceptForm, BorrowCommandResult, BorrowForm, BorrowRequestsCommandResult, ReturnForm};
+
+fn day(day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd(2019, 1, day).and_hms(0, 0, 0)
+}
+
+#[actix_rt::test]
+async fn test_borrow_requests() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/borrow")
+                .route( web::get().to(super::list))
+                .route( web::post().to(super::create)))
+            .service( web::resource("/borrow/{id}/accept").route( web::post().to(super::accept)))
+            .service( web::resource("/borrow/{id}/decline").route( web::post().to(super::decline)))
+            .service( web::resource("/borrow/{id}/return").route( web::post().to(super::give_back)))
+    });
+
+    //==== Request a book of the other user
+    let form = BorrowForm { book_id: 2, message: None };
+    let response = srv.post("/borrow").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    friend_handler::add(pool.clone(), 2, 1).unwrap();
+    friend_handler::add(pool.clone(), 1, 2).unwrap();
+
+    let form = BorrowForm { book_id: 2, message: Some(" for the holidays ".to_string()) };
+    let mut response = srv.post("/borrow").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BorrowCommandResult = response.json().await.unwrap();
+    assert!(result.notified);
+    let sent = result.request;
+    assert_eq!((sent.owner_id, sent.state.as_str(), sent.message.as_deref()), (2, "pending", Some("for the holidays")));
+
+    for book_id in vec![1, 2] {
+        let form = BorrowForm { book_id, message: None };
+        let response = srv.post("/borrow").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+    let response = srv.post("/borrow").timeout(Duration::new(15, 0)).send_form(&BorrowForm { book_id: 99, message: None }).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    // only the owner answers
+    let url = format!("/borrow/{}/accept", sent.id);
+    let response = srv.post(&url).timeout(Duration::new(15, 0)).send_form(&AcceptForm::default()).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    //==== Answer the requests of the other user
+    let received = borrow_handler::request(pool.clone(), 2, 1, None).expect("request not created");
+    let mut response = srv.get("/borrow").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: BorrowRequestsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.received.iter().map(|details| details.book.id).collect::<Vec<i32>>(), vec![1]);
+    assert_eq!(result.sent.iter().map(|details| details.request.id).collect::<Vec<i32>>(), vec![sent.id]);
+
+    let url = format!("/borrow/{}/return", received.id);
+    let response = srv.post(&url).timeout(Duration::new(15, 0)).send_form(&ReturnForm::default()).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let url = format!("/borrow/{}/accept", received.id);
+    let form = AcceptForm { date: Some(day(10)), due: Some(day(1)) };
+    let response = srv.post(&url).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let form = AcceptForm { date: Some(day(1)), due: Some(day(20)) };
+    let mut response = srv.post(&url).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BorrowCommandResult = response.json().await.unwrap();
+    assert_eq!(result.request.state, "accepted");
+    let loan = loan_handler::current(pool.clone(), 1, 1).unwrap().expect("book not lent");
+    assert_eq!((Some(loan.id), loan.borrower_id, loan.borrower_name.as_str(), loan.due_at), (result.request.loan_id, Some(2), "other", Some(day(20))));
+
+    let url = format!("/borrow/{}/decline", received.id);
+    let response = srv.post(&url).timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let url = format!("/borrow/{}/return", received.id);
+    let form = ReturnForm { date: Some(day(15)) };
+    let mut response = srv.post(&url).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: BorrowCommandResult = response.json().await.unwrap();
+    assert_eq!(result.request.state, "returned");
+    assert!(loan_handler::current(pool.clone(), 1, 1).unwrap().is_none());
+
+    let declined = borrow_handler::request(pool.clone(), 2, 1, None).expect("request not created");
+    let url = format!("/borrow/{}/decline", declined.id);
+    let mut response = srv.post(&url).timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: BorrowCommandResult = response.json().await.unwrap();
+    assert_eq!(result.request.state, "declined");
+}
+
+#[test]
+fn test_request_mail() {
+    let body = request_mail(&Catalog::empty(), &User::testUser(), "Tintin & Milou", &Some("<a href=\"x\">soon</a>".to_string()));
+    assert!(body.contains("Tintin &amp; Milou"));
+    assert!(body.contains("&lt;a href=&quot;x&quot;&gt;soon&lt;/a&gt;"));
+}
@@ -0,0 +1,144 @@
+use actix_multipart::Multipart;
+use actix_session::{Session};
+use actix_web::{http, web, error::BlockingError, HttpResponse};
+use std::io;
+
+use kbooks_common::khnum::wiring::{Config, DbPool};
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::cover::{self, CoverError, CoverSize, CoverStore, MAX_COVER_SIZE};
+use kbooks_common::placeholder;
+use kbooks_common::repository::book_handler;
+use kbooks_common::models::{Book, BookChanges};
+
+use crate::controllers::{read_file, session_user};
+
+// Where the files of the local storage are served
+pub const COVERS_PATH: &str = "/covers";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CoverUrls {
+    large: String,
+    medium: String,
+    small: String,
+}
+
+pub fn cover_urls(key: &str) -> Option<CoverUrls> {
+    if key.is_empty() {
+        return None;
+    }
+    let url = |size| format!("{}/{}", COVERS_PATH, cover::file_name(key, size));
+    Some(CoverUrls {
+        large: url(CoverSize::Large),
+        medium: url(CoverSize::Medium),
+        small: url(CoverSize::Small),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CoverCommandResult {
+    success: bool,
+    // none when the book has no cover
+    cover: Option<CoverUrls>,
+    error: Option<String>
+}
+
+fn cover_error(error: CoverError) -> ServiceError {
+    match error {
+        CoverError::Storage(_) => ServiceError::InternalServerError,
+        error => ServiceError::BadRequest(format!("file: {}", error)),
+    }
+}
+
+// Decoding and resizing a large image takes a while : it is done out of the server threads
+pub async fn process(data: Vec<u8>) -> Result<Vec<(CoverSize, Vec<u8>)>, CoverError> {
+    web::block(move || cover::process(&data)).await.map_err(|error| match error {
+        BlockingError::Error(error) => error,
+        BlockingError::Canceled => CoverError::Storage(io::Error::new(io::ErrorKind::Other, "image processing canceled")),
+    })
+}
+
+// Records the new cover of the book : its files are removed when the book can not be updated
+fn set_book_cover(pool: DbPool, covers: &CoverStore, user_id: i32, book_id: i32, key: &str) -> Result<Book, ServiceError> {
+    let changes = BookChanges { cover: Some(key.to_string()), ..BookChanges::default() };
+    book_handler::update(pool, user_id, book_id, &changes).map_err(|error| {
+        let _ = covers.delete(key);
+        error.into()
+    })
+}
+
+// ---------------- Cover of a book ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CoverQuery {
+    // large, medium or small, medium when not given
+    size: Option<String>,
+}
+
+// Redirects to the uploaded image, or generates a placeholder with the title and the author
+pub async fn get(
+    session: Session,
+    book_id: web::Path<i32>,
+    query: web::Query<CoverQuery>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book = book_handler::get(config.pool.clone(), user.id, book_id.into_inner())?;
+    let size = match &query.size {
+        Some(size) => size.parse::<CoverSize>().map_err(|error| ServiceError::BadRequest(format!("size: {}", error)))?,
+        None => CoverSize::Medium,
+    };
+    if !book.cover.is_empty() {
+        let url = format!("{}/{}", COVERS_PATH, cover::file_name(&book.cover, size));
+        return Ok(HttpResponse::Found().header(http::header::LOCATION, url).finish());
+    }
+    let svg = placeholder::svg(&book.title, &book.author_lf, &book.author_code);
+    Ok(HttpResponse::Ok()
+        .content_type("image/svg+xml")
+        // the title or the author may change
+        .header(http::header::CACHE_CONTROL, "no-cache")
+        .body(svg))
+}
+
+// ---------------- Upload / remove the cover of a book ------------
+
+// The image is sent in the "file" field of a multipart form, it replaces the previous cover
+pub async fn upload(
+    session: Session,
+    book_id: web::Path<i32>,
+    payload: Multipart,
+    config: web::Data<Config>,
+    covers: web::Data<CoverStore>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book = book_handler::get(config.pool.clone(), user.id, book_id.into_inner())?;
+    let data = read_file(payload, MAX_COVER_SIZE).await?;
+    let sizes = process(data).await.map_err(cover_error)?;
+    let key = covers.save(book.id, &sizes).map_err(cover_error)?;
+    set_book_cover(config.pool.clone(), &covers, user.id, book.id, &key)?;
+    if !book.cover.is_empty() {
+        covers.delete(&book.cover).map_err(cover_error)?;
+    }
+    let res = CoverCommandResult {success: true, cover: cover_urls(&key), error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn delete(
+    session: Session,
+    book_id: web::Path<i32>,
+    config: web::Data<Config>,
+    covers: web::Data<CoverStore>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book = book_handler::get(config.pool.clone(), user.id, book_id.into_inner())?;
+    if !book.cover.is_empty() {
+        let changes = BookChanges { cover: Some(String::new()), ..BookChanges::default() };
+        book_handler::update(config.pool.clone(), user.id, book.id, &changes)?;
+        covers.delete(&book.cover).map_err(cover_error)?;
+    }
+    let res = CoverCommandResult {success: true, cover: None, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
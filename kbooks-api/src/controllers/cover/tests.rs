@@ -0,0 +1,106 @@
+use actix_web::{web, test, http, App};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::cover::CoverSize;
+use kbooks_common::repository::book_handler;
+
+use crate::controllers::fixtures::{managed_state, populate_two_users, test_cover_store, GIF};
+
+use super::{cover_urls, CoverCommandResult};
+
+const BOUNDARY: &str = "kbooksboundary";
+
+fn multipart_body(content: &[u8]) -> Vec<u8> {
+    let mut body = format!("--{boundary}\r\n\
+                            Content-Disposition: form-data; name=\"file\"; filename=\"cover.gif\"\r\n\
+                            Content-Type: image/gif\r\n\r\n", boundary = BOUNDARY).into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+#[test]
+fn test_cover_urls() {
+    assert_eq!(cover_urls(""), None);
+    let urls = cover_urls("1-abc").unwrap();
+    assert_eq!(urls.small, "/covers/1-abc-small.jpg");
+}
+
+#[actix_rt::test]
+async fn test_upload() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    populate_two_users(&pool.get().unwrap());
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .data(test_cover_store("cover"))
+            .service( web::resource("/book/{id}/cover")
+                .route( web::get().to(super::get))
+                .route( web::post().to(super::upload))
+                .route( web::delete().to(super::delete)))
+    });
+    let covers = test_cover_store("cover");
+    let content_type = format!("multipart/form-data; boundary={}", BOUNDARY);
+
+    //==== Placeholder
+    let mut response = srv.get("/book/1/cover").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(http::header::CONTENT_TYPE).unwrap(), "image/svg+xml");
+    let svg = String::from_utf8(response.body().await.unwrap().to_vec()).unwrap();
+    assert!(svg.contains(">a title</text>"));
+    let response = srv.get("/book/1/cover?size=huge").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let response = srv.get("/book/2/cover").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    //==== Upload
+    for (book_id, content, status) in vec![
+        (1, b"not an image".to_vec(), http::StatusCode::BAD_REQUEST),
+        (2, GIF.to_vec(), http::StatusCode::NOT_FOUND),
+    ] {
+        let response = srv.post(format!("/book/{}/cover", book_id))
+            .header(http::header::CONTENT_TYPE, content_type.clone())
+            .timeout(Duration::new(15, 0))
+            .send_body(multipart_body(&content)).await.unwrap();
+        assert_eq!(response.status(), status);
+    }
+
+    let mut response = srv.post("/book/1/cover")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(GIF)).await.unwrap();
+    assert!(response.status().is_success());
+    let result: CoverCommandResult = response.json().await.expect("Could not parse json");
+    let first = book_handler::get(pool.clone(), 1, 1).unwrap().cover;
+    assert_eq!(result.cover, cover_urls(&first));
+    assert!(covers.get(&first, CoverSize::Small).is_ok());
+
+    // a new upload replaces the cover
+    let response = srv.post("/book/1/cover")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(GIF)).await.unwrap();
+    assert!(response.status().is_success());
+    let second = book_handler::get(pool.clone(), 1, 1).unwrap().cover;
+    assert_ne!(first, second);
+    assert!(covers.get(&first, CoverSize::Large).is_err());
+
+    // the uploaded image replaces the placeholder
+    let response = srv.get("/book/1/cover?size=small").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::FOUND);
+    let location = format!("/covers/{}-small.jpg", second);
+    assert_eq!(response.headers().get(http::header::LOCATION).unwrap(), location.as_str());
+
+    //==== Delete
+    let mut response = srv.delete("/book/1/cover").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: CoverCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.cover, None);
+    assert_eq!(book_handler::get(pool.clone(), 1, 1).unwrap().cover, "");
+    assert!(covers.get(&second, CoverSize::Medium).is_err());
+}
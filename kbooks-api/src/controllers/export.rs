@@ -0,0 +1,71 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse, http};
+use futures::stream;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::export::{ExportFormat, Exporter};
+use kbooks_common::repository::book_handler;
+
+use crate::controllers::session_user;
+
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportQuery {
+    format: String,
+}
+
+enum Step {
+    Header,
+    Books(i64),
+    Footer,
+    Done,
+}
+
+// The books are read and sent a page at a time
+pub async fn export(
+    session: Session,
+    query: web::Query<ExportQuery>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let format: ExportFormat = query.format.parse()
+        .map_err(|error: String| ServiceError::BadRequest(format!("format: {}", error)))?;
+    let pool = config.pool.clone();
+    let user_id = user.id;
+
+    let chunks = stream::unfold((Step::Header, Exporter::new(format)), move |(step, mut exporter)| {
+        let pool = pool.clone();
+        async move {
+            let (chunk, next) = match step {
+                Step::Header => (exporter.header(), Step::Books(0)),
+                Step::Books(offset) => {
+                    let books = match book_handler::export_page(pool, user_id, offset, EXPORT_PAGE_SIZE) {
+                        Ok(books) => books,
+                        Err(error) => return Some((Err(ServiceError::from(error)), (Step::Done, exporter))),
+                    };
+                    let next = if (books.len() as i64) < EXPORT_PAGE_SIZE {
+                        Step::Footer
+                    } else {
+                        Step::Books(offset + EXPORT_PAGE_SIZE)
+                    };
+                    (exporter.books(&books), next)
+                }
+                Step::Footer => (Ok(exporter.footer()), Step::Done),
+                Step::Done => return None,
+            };
+            let chunk = chunk.map(web::Bytes::from).map_err(|_| ServiceError::InternalServerError);
+            Some((chunk, (next, exporter)))
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .header(http::header::CONTENT_DISPOSITION, format!("attachment; filename=\"kbooks.{}\"", format.extension()))
+        .streaming(Box::pin(chunks)))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,51 @@
+use actix_web::{web, test, http, App};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::import::{self, ImportFormat, ImportStatus};
+use kbooks_common::repository::{book_handler, import_handler};
+
+use crate::controllers::fixtures::{managed_state, test_book, insert_books, populate_two_users};
+
+#[actix_rt::test]
+async fn test_export() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        insert_books(conn, vec![test_book(1, "Zazie dans le métro")]);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/export").route( web::get().to(super::export)))
+    });
+
+    // Each export is read back by the matching importer
+    let formats = vec![("json", ImportFormat::LibraryThing), ("tsv", ImportFormat::LibraryThing), ("csv", ImportFormat::Goodreads)];
+    for (format, import_format) in formats {
+        let mut response = srv.get(format!("/book/export?format={}", format)).timeout(Duration::new(15, 0)).send().await.unwrap();
+        assert!(response.status().is_success());
+        let body = response.body().await.unwrap();
+        let titles: Vec<String> = import::parse(import_format, &body).unwrap().into_iter()
+            .map(|row| row.book.expect("invalid exported book").title)
+            .collect();
+        assert_eq!(titles, vec![String::from("a title"), String::from("Zazie dans le métro")]);
+    }
+
+    // The CSV and JSON exports are imported again without duplicating the books
+    for (format, import_format) in vec![("csv", ImportFormat::Goodreads), ("json", ImportFormat::LibraryThing)] {
+        let mut response = srv.get(format!("/book/export?format={}", format)).timeout(Duration::new(15, 0)).send().await.unwrap();
+        let body = response.body().await.unwrap();
+        let report = import_handler::import(pool.clone(), 1, import::parse(import_format, &body).unwrap()).unwrap();
+        assert_eq!(report.count(ImportStatus::Created), 0);
+        assert_eq!(book_handler::list(pool.clone(), 1).unwrap().len(), 2);
+    }
+
+    let response = srv.get("/book/export?format=pdf").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+}
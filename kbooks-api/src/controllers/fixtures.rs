@@ -0,0 +1,73 @@
+// Test data shared by the controllers tests
+use chrono::Utc;
+use diesel::prelude::*;
+
+use actix_i18n::Translations;
+use gettext_macros::include_i18n;
+
+use kbooks_common::cover::CoverStore;
+use kbooks_common::schema::books::dsl;
+use kbooks_common::khnum::schema::users::dsl as users_dsl;
+use kbooks_common::khnum::users::models::NewUser;
+use kbooks_common::khnum::wiring::MyConnection;
+use kbooks_common::models::NewBook;
+use kbooks_common::storage::LocalStorage;
+
+// 1x1 pixel
+pub const GIF: &[u8] = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\xff\xff\xff\x00\x00\x00!\xf9\x04\x01\x00\x00\x00\x00,\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02D\x01\x00;";
+
+pub fn managed_state() -> Translations {
+    include_i18n!()
+}
+
+// Covers stored in a temporary directory of the test
+pub fn test_cover_store(test_name: &str) -> CoverStore {
+    let root = std::env::temp_dir().join(format!("kbooks-{}-{}", test_name, std::process::id()));
+    CoverStore::new(Box::new(LocalStorage::new(root)))
+}
+
+pub fn test_book(user_id: i32, title: &str) -> NewBook {
+    NewBook {
+        user_id,
+        librarything_id: None,
+        title: title.to_string(),
+        author_lf: "Authorlf".to_string(),
+        author_code: "AUT".to_string(),
+        isbn: "9782070368228".to_string(),
+        publicationdate: "2019-03-02".to_string(),
+        language_original: "fr".to_string(),
+        language_main: "fr".to_string(),
+        language_secondary: None,
+        review: None,
+        rating: None,
+        cover: "".to_string(),
+        created_at: Utc::now().naive_utc(),
+        dateacquired_stamp: None,
+        started_stamp: None,
+        finished_stamp: None,
+        author_id: None,
+        page_count: None,
+    }
+}
+
+pub fn insert_books(conn: &MyConnection, books: Vec<NewBook>) {
+    for book in books {
+        diesel::insert_into(dsl::books).values(&book)
+            .execute(conn).expect("Error populating test database");
+    }
+}
+
+// Two users with their own books : the first one is the session test user
+pub fn populate_two_users(conn: &MyConnection) {
+    let users = vec![
+        NewUser::with_details(String::from("login"), String::from("email@test.fr"), String::from("password"), String::from("fr_FR")),
+        NewUser::with_details(String::from("other"), String::from("other@test.fr"), String::from("password"), String::from("fr_FR")),
+    ];
+    diesel::insert_into(users_dsl::users).values(&users)
+        .execute(conn).expect("Error populating test database");
+    insert_books(conn, vec![
+        test_book(1, "a title"),
+        test_book(2, "another user title"),
+        test_book(2, "yet another user title"),
+    ]);
+}
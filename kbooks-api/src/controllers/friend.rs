@@ -0,0 +1,80 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+
+use kbooks_common::khnum::wiring::{Config, DbPool};
+use kbooks_common::khnum::errors::ServiceError;
+use kbooks_common::khnum::users::repository::user_handler;
+
+use kbooks_common::repository::friend_handler;
+use kbooks_common::models::Friend;
+
+use crate::controllers::session_user;
+
+// ---------------- Friends of the user ------------
+
+// The library of the user is visible to the friends, who can ask to borrow its books
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FriendsCommandResult {
+    success: bool,
+    // by login
+    friends: Vec<Friend>,
+    error: Option<String>
+}
+
+fn friends_result(pool: DbPool, user_id: i32) -> Result<HttpResponse, ServiceError> {
+    let friends = friend_handler::list(pool, user_id)?;
+    let res = FriendsCommandResult {success: true, friends, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn list(
+    session: Session,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    friends_result(config.pool.clone(), user.id)
+}
+
+// ---------------- Add / remove a friend ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct FriendForm {
+    login: String,
+}
+
+pub async fn add(
+    session: Session,
+    friend_form: web::Form<FriendForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let friend = match user_handler::get_by_login(config.pool.clone(), friend_form.login.trim()) {
+        Err(DBError::NotFound) => return Err(ServiceError::BadRequest("login: unknown user".to_string())),
+        result => result?,
+    };
+    if friend.id == user.id {
+        return Err(ServiceError::BadRequest("login: the library is already visible to its owner".to_string()));
+    }
+    match friend_handler::add(config.pool.clone(), user.id, friend.id) {
+        Err(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            return Err(ServiceError::BadRequest("login: already a friend".to_string()));
+        }
+        result => result?,
+    };
+    friends_result(config.pool.clone(), user.id)
+}
+
+// The requests already made by the former friend are kept, but hidden from them
+pub async fn remove(
+    session: Session,
+    friend_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    friend_handler::remove(config.pool.clone(), user.id, friend_id.into_inner())?;
+    friends_result(config.pool.clone(), user.id)
+}
+
+#[cfg(test)]
+mod tests;
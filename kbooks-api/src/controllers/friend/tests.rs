@@ -0,0 +1,56 @@
+use actix_web::{web, test, http, App};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::repository::borrow_handler;
+
+use crate::controllers::fixtures::{managed_state, populate_two_users};
+
+use super::{FriendForm, FriendsCommandResult};
+
+fn friend_form(login: &str) -> FriendForm {
+    FriendForm { login: login.to_string() }
+}
+
+#[actix_rt::test]
+async fn test_friends() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/friend")
+                .route( web::get().to(super::list))
+                .route( web::post().to(super::add)))
+            .service( web::resource("/friend/{id}").route( web::delete().to(super::remove)))
+    });
+
+    // the books of the test user are not visible to the other one
+    assert!(borrow_handler::owner_of(pool.clone(), 2, 1).is_err());
+
+    let mut response = srv.post("/friend").timeout(Duration::new(15, 0)).send_form(&friend_form(" other ")).await.unwrap();
+    assert!(response.status().is_success());
+    let result: FriendsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.friends.iter().map(|friend| friend.login.as_str()).collect::<Vec<&str>>(), vec!["other"]);
+    assert_eq!(borrow_handler::owner_of(pool.clone(), 2, 1).unwrap(), 1);
+
+    for login in vec!["other", "login", "nobody"] {
+        let response = srv.post("/friend").timeout(Duration::new(15, 0)).send_form(&friend_form(login)).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST, "{}", login);
+    }
+
+    let mut response = srv.delete("/friend/2").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: FriendsCommandResult = response.json().await.unwrap();
+    assert!(result.friends.is_empty());
+    assert!(borrow_handler::owner_of(pool.clone(), 2, 1).is_err());
+    let response = srv.delete("/friend/2").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+}
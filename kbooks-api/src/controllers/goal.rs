@@ -0,0 +1,97 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+use chrono::{Datelike, Utc};
+
+use kbooks_common::khnum::wiring::{Config, CommandResult};
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::goals::GoalProgress;
+use kbooks_common::language;
+use kbooks_common::repository::goal_handler;
+use kbooks_common::models::{GoalKind, NewGoal};
+
+use crate::controllers::session_user;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoalsCommandResult {
+    success: bool,
+    goals: Vec<GoalProgress>,
+    error: Option<String>
+}
+
+// ---------------- Goals and their progress ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct GoalsQuery {
+    // all the years when not given
+    year: Option<i32>,
+}
+
+pub async fn list(
+    session: Session,
+    query: web::Query<GoalsQuery>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let goals = goal_handler::list(config.pool.clone(), user.id, query.year)?;
+    let goals = goal_handler::with_progress(config.pool.clone(), user.id, goals, Utc::now().naive_utc().date())?;
+    let res = GoalsCommandResult {success: true, goals, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Set a goal ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoalForm {
+    // the current year when not given
+    year: Option<i32>,
+    kind: GoalKind,
+    target: i32,
+    // books read in any language count when not given
+    language: Option<String>,
+}
+
+// The target of an existing goal with the same year, kind and language is replaced
+pub async fn set(
+    session: Session,
+    goal_form: web::Form<GoalForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    if goal_form.target <= 0 {
+        return Err(ServiceError::BadRequest("target: must be positive".to_string()));
+    }
+    let language = match goal_form.language {
+        Some(ref raw) => language::normalize(raw)
+            .map_err(|error| ServiceError::BadRequest(format!("language: {}", error)))?,
+        None => String::new(),
+    };
+    let now = Utc::now().naive_utc();
+    let goal = NewGoal {
+        user_id: user.id,
+        year: goal_form.year.unwrap_or_else(|| now.year()),
+        kind: goal_form.kind.as_str().to_string(),
+        target: goal_form.target,
+        language: Some(language).filter(|code| !code.is_empty()),
+        created_at: now,
+    };
+    let goal = goal_handler::set(config.pool.clone(), goal)?;
+    let goals = goal_handler::with_progress(config.pool.clone(), user.id, vec![goal], now.date())?;
+    let res = GoalsCommandResult {success: true, goals, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Delete a goal ------------
+
+pub async fn delete(
+    session: Session,
+    goal_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    goal_handler::delete(config.pool.clone(), user.id, goal_id.into_inner())?;
+    Ok(HttpResponse::Ok().json(CommandResult::success()))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,87 @@
+use actix_web::{web, test, http, App};
+use chrono::{NaiveDate, Utc};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::models::{GoalKind, NewGoal};
+use kbooks_common::repository::{goal_handler, reading_handler};
+
+use crate::controllers::fixtures::{managed_state, populate_two_users};
+
+use super::{GoalForm, GoalsCommandResult};
+
+fn goal_form(kind: GoalKind, target: i32, language: Option<&str>) -> GoalForm {
+    GoalForm { year: Some(2019), kind, target, language: language.map(String::from) }
+}
+
+#[actix_rt::test]
+async fn test_goals() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+    }
+    reading_handler::finish(pool.clone(), 1, 1, NaiveDate::from_ymd(2019, 3, 1).and_hms(0, 0, 0), None).unwrap();
+    reading_handler::finish(pool.clone(), 2, 2, NaiveDate::from_ymd(2019, 3, 1).and_hms(0, 0, 0), None).unwrap();
+
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/goal")
+                .route( web::get().to(super::list))
+                .route( web::post().to(super::set)))
+            .service( web::resource("/goal/{id}").route( web::delete().to(super::delete)))
+    });
+
+    //==== Set goals
+    let mut response = srv.post("/goal").timeout(Duration::new(15, 0)).send_form(&goal_form(GoalKind::Books, 10, None)).await.unwrap();
+    assert!(response.status().is_success());
+    let result: GoalsCommandResult = response.json().await.unwrap();
+    // the year is over
+    let progress = &result.goals[0];
+    assert_eq!((progress.done, progress.expected, progress.on_track, progress.behind_by), (1, 10, false, 9));
+    let goal_id = progress.goal.id;
+
+    // same goal with another target
+    let mut response = srv.post("/goal").timeout(Duration::new(15, 0)).send_form(&goal_form(GoalKind::Books, 1, None)).await.unwrap();
+    let result: GoalsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.goals[0].goal.id, goal_id);
+    assert!(result.goals[0].on_track);
+
+    let mut response = srv.post("/goal").timeout(Duration::new(15, 0)).send_form(&goal_form(GoalKind::Books, 5, Some("German"))).await.unwrap();
+    let result: GoalsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.goals[0].goal.language, Some(String::from("de")));
+    assert_eq!(result.goals[0].done, 0);
+
+    let response = srv.post("/goal").timeout(Duration::new(15, 0)).send_form(&goal_form(GoalKind::Pages, 0, None)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let response = srv.post("/goal").timeout(Duration::new(15, 0)).send_form(&goal_form(GoalKind::Pages, 100, Some("Elvish"))).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    //==== List
+    let other_user_goal = goal_handler::set(pool.clone(), NewGoal {
+        user_id: 2,
+        year: 2019,
+        kind: GoalKind::Books.as_str().to_string(),
+        target: 3,
+        language: None,
+        created_at: Utc::now().naive_utc(),
+    }).unwrap();
+    let mut response = srv.get("/goal?year=2019").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: GoalsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.goals.len(), 2);
+    let mut response = srv.get("/goal?year=2020").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: GoalsCommandResult = response.json().await.unwrap();
+    assert!(result.goals.is_empty());
+
+    //==== Delete
+    let response = srv.delete(format!("/goal/{}", other_user_goal.id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    let response = srv.delete(format!("/goal/{}", goal_id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(goal_handler::list(pool.clone(), 1, None).unwrap().len(), 1);
+}
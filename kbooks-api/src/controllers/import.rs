@@ -0,0 +1,46 @@
+use actix_multipart::Multipart;
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::import::{self, ImportFormat, ImportReport};
+use kbooks_common::repository::import_handler;
+
+use crate::controllers::{read_file, session_user};
+
+// LibraryThing exports of large libraries are a few megabytes
+const MAX_IMPORT_SIZE: usize = 20 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportQuery {
+    format: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportCommandResult {
+    success: bool,
+    report: ImportReport,
+    error: Option<String>
+}
+
+pub async fn import(
+    session: Session,
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let format: ImportFormat = query.format.parse()
+        .map_err(|error: String| ServiceError::BadRequest(format!("format: {}", error)))?;
+    let data = read_file(payload, MAX_IMPORT_SIZE).await?;
+    let rows = import::parse(format, &data)
+        .map_err(|error| ServiceError::BadRequest(format!("file: {}", error)))?;
+    let report = import_handler::import(config.pool.clone(), user.id, rows)?;
+    let res = ImportCommandResult {success: true, report, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
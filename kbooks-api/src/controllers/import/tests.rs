@@ -0,0 +1,157 @@
+use actix_web::{web, test, http, App};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::import::ImportStatus;
+use kbooks_common::models::Role;
+use kbooks_common::repository::{book_handler, contributor_handler, tag_handler};
+
+use crate::controllers::fixtures::{managed_state, populate_two_users};
+
+use super::ImportCommandResult;
+
+const BOUNDARY: &str = "kbooksboundary";
+
+const EXPORT: &str = "Book Id\tTitle\tPrimary Author\tSecondary Author\tSecondary Author Roles\tDate\tRating\tTags\tLanguages\tOriginal Languages\tISBNs\tDate Read\n\
+                      162415042\tLa Vie mode d'emploi\tPerec, Georges\tBellos, David\tTranslator\t1978\t5\tnovel, oulipo\tEnglish\tFrench\t[0879237511, 9780879237516]\t2020-03-04\n\
+                      162415043\t\tQueneau, Raymond\t\t\t\t\t\t\t\t\t\n";
+
+const GOODREADS_EXPORT: &str = "Book Id,Title,Author l-f,ISBN,ISBN13,My Rating,Date Read,Date Added,Bookshelves,Exclusive Shelf,My Review\n\
+                               2474,Exercices de style,\"Queneau, Raymond\",\"=\"\"0811220117\"\"\",\"=\"\"9780811220118\"\"\",4,2019/03/12,2019/01/15,oulipo,read,\n";
+
+const LIBRARYTHING_WITH_DATES: &str = "Book Id\tTitle\tPrimary Author\tSecondary Author\tSecondary Author Roles\tDate\tRating\tTags\tLanguages\tOriginal Languages\tISBNs\tDate Started\tDate Read\n\
+                                       162415042\tLa Vie mode d'emploi\tPerec, Georges\tBellos, David\tTranslator\t1978\t5\tnovel, oulipo\tEnglish, French\tFrench\t[9780879237516]\t2020-02-01\t2020-03-04\n";
+
+// The columns of a real Goodreads export : no languages, no start date, no contributor roles
+const GOODREADS_SAME_BOOK: &str = "Book Id,Title,Author,Author l-f,Additional Authors,ISBN,ISBN13,My Rating,Average Rating,Publisher,Binding,Number of Pages,Year Published,Original Publication Year,Date Read,Date Added,Bookshelves,Bookshelves with positions,Exclusive Shelf,My Review,Spoiler,Private Notes,Read Count,Owned Copies\n\
+                                   1234,Life: A User's Manual,Georges Perec,\"Perec, Georges\",,\"=\"\"0879237511\"\"\",\"=\"\"9780879237516\"\"\",4,4.2,Godine,Paperback,580,1987,1978,2020/03/04,2019/01/15,favorites,favorites (#1),read,,,,1,1\n";
+
+fn multipart_body(content: &str) -> String {
+    format!("--{boundary}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"export.tsv\"\r\n\
+             Content-Type: text/tab-separated-values\r\n\r\n\
+             {content}\r\n\
+             --{boundary}--\r\n", boundary = BOUNDARY, content = content)
+}
+
+#[actix_rt::test]
+async fn test_import() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    populate_two_users(&pool.get().unwrap());
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/import").route( web::post().to(super::import)))
+    });
+    let content_type = format!("multipart/form-data; boundary={}", BOUNDARY);
+
+    let mut response = srv.post("/book/import?format=librarything")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(EXPORT)).await.unwrap();
+    assert!(response.status().is_success());
+    let result: ImportCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.report.count(ImportStatus::Created), 1);
+    assert_eq!(result.report.count(ImportStatus::Skipped), 1);
+    assert_eq!(result.report.rows[1].message, Some(String::from("title is missing")));
+    let book = book_handler::find_by_librarything_id(pool.clone(), 1, "162415042").unwrap().expect("book not imported");
+    assert_eq!(book.title, "La Vie mode d'emploi");
+
+    // Importing the same file again changes nothing
+    let mut response = srv.post("/book/import?format=librarything")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(EXPORT)).await.unwrap();
+    let result: ImportCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.report.count(ImportStatus::Skipped), 2);
+
+    // An emptied field clears the stored value
+    let mut response = srv.post("/book/import?format=librarything")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(&EXPORT.replace("\t5\t", "\t\t"))).await.unwrap();
+    let result: ImportCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.report.count(ImportStatus::Updated), 1);
+    let book = book_handler::find_by_librarything_id(pool.clone(), 1, "162415042").unwrap().expect("book not imported");
+    assert_eq!(book.rating, None);
+
+    // Goodreads
+    let mut response = srv.post("/book/import?format=goodreads")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(GOODREADS_EXPORT)).await.unwrap();
+    let result: ImportCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.report.count(ImportStatus::Created), 1);
+    let book_id = result.report.rows[0].book_id.unwrap();
+    let book = book_handler::get(pool.clone(), 1, book_id).unwrap();
+    assert_eq!(book.isbn, "9780811220118");
+    assert_eq!(book.rating, Some(4));
+    assert!(book.finished_stamp.is_some());
+    // no language column : the language of the user
+    assert_eq!(book.language_main, "fr");
+
+    // Goodreads rows have no LibraryThing id : the book is found by its ISBN
+    let mut response = srv.post("/book/import?format=goodreads")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(GOODREADS_EXPORT)).await.unwrap();
+    let result: ImportCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.report.count(ImportStatus::Skipped), 1);
+    assert_eq!(result.report.rows[0].book_id, Some(book_id));
+
+    // Unknown format
+    let response = srv.post("/book/import?format=calibre")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(EXPORT)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn test_goodreads_over_librarything() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    populate_two_users(&pool.get().unwrap());
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/import").route( web::post().to(super::import)))
+    });
+    let content_type = format!("multipart/form-data; boundary={}", BOUNDARY);
+
+    let mut response = srv.post("/book/import?format=librarything")
+        .header(http::header::CONTENT_TYPE, content_type.clone())
+        .timeout(Duration::new(15, 0))
+        .send_body(multipart_body(LIBRARYTHING_WITH_DATES)).await.unwrap();
+    let result: ImportCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.report.count(ImportStatus::Created), 1);
+    let book_id = result.report.rows[0].book_id.unwrap();
+
+    for status in vec![ImportStatus::Updated, ImportStatus::Skipped] {
+        let mut response = srv.post("/book/import?format=goodreads")
+            .header(http::header::CONTENT_TYPE, content_type.clone())
+            .timeout(Duration::new(15, 0))
+            .send_body(multipart_body(GOODREADS_SAME_BOOK)).await.unwrap();
+        let result: ImportCommandResult = response.json().await.expect("Could not parse json");
+        assert_eq!((result.report.rows[0].status, result.report.rows[0].book_id), (status, Some(book_id)));
+    }
+
+    let book = book_handler::get(pool.clone(), 1, book_id).unwrap();
+    // the columns of the Goodreads export are written
+    assert_eq!((book.title.as_str(), book.rating), ("Life: A User's Manual", Some(4)));
+    // the others are kept
+    assert_eq!((book.language_main.as_str(), book.language_secondary.as_deref(), book.language_original.as_str()), ("en", Some("fr"), "fr"));
+    assert_eq!(book.started_stamp.map(|started| started.to_string()), Some(String::from("2020-02-01 00:00:00")));
+    let tags = tag_handler::list_for_books(pool.clone(), &[book_id]).unwrap().remove(&book_id).unwrap();
+    assert_eq!(tags, vec!["favorites", "novel", "oulipo"]);
+    let contributors: Vec<(String, Role)> = contributor_handler::list_for_books(pool.clone(), &[book_id]).unwrap()
+        .remove(&book_id).unwrap()
+        .into_iter().map(|contributor| (contributor.name, contributor.role)).collect();
+    assert_eq!(contributors, vec![(String::from("Perec, Georges"), Role::Author), (String::from("Bellos, David"), Role::Translator)]);
+}
@@ -0,0 +1,114 @@
+use actix_i18n::I18n;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use gettext::Catalog;
+use gettext_macros::i18n;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::language;
+use kbooks_common::repository::book_handler;
+
+use crate::controllers::session_user;
+
+// ---------------- Languages of the library ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LanguageCount {
+    code: String,
+    name: String,
+    count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LanguagesCommandResult {
+    success: bool,
+    languages: Vec<LanguageCount>,
+    error: Option<String>
+}
+
+// The English names are the keys of the translation catalogs
+fn language_name(catalog: &Catalog, code: &str) -> String {
+    match language::name(code) {
+        Some(name) => catalog.gettext(name).to_string(),
+        None => code.to_string(),
+    }
+}
+
+// Never called : the names of kbooks_common::language::LANGUAGES, written here to be extracted to the translation catalogs
+#[allow(dead_code)]
+fn catalog_names(catalog: &Catalog) -> Vec<String> {
+    vec![
+        i18n!(catalog, "Afar"), i18n!(catalog, "Abkhazian"), i18n!(catalog, "Avestan"), i18n!(catalog, "Afrikaans"),
+        i18n!(catalog, "Akan"), i18n!(catalog, "Amharic"), i18n!(catalog, "Aragonese"), i18n!(catalog, "Arabic"),
+        i18n!(catalog, "Assamese"), i18n!(catalog, "Avaric"), i18n!(catalog, "Aymara"), i18n!(catalog, "Azerbaijani"),
+        i18n!(catalog, "Bashkir"), i18n!(catalog, "Belarusian"), i18n!(catalog, "Bulgarian"),
+        i18n!(catalog, "Bihari languages"), i18n!(catalog, "Bislama"), i18n!(catalog, "Bambara"),
+        i18n!(catalog, "Bengali"), i18n!(catalog, "Tibetan"), i18n!(catalog, "Breton"), i18n!(catalog, "Bosnian"),
+        i18n!(catalog, "Catalan"), i18n!(catalog, "Chechen"), i18n!(catalog, "Chamorro"), i18n!(catalog, "Corsican"),
+        i18n!(catalog, "Cree"), i18n!(catalog, "Czech"), i18n!(catalog, "Church Slavonic"), i18n!(catalog, "Chuvash"),
+        i18n!(catalog, "Welsh"), i18n!(catalog, "Danish"), i18n!(catalog, "German"), i18n!(catalog, "Divehi"),
+        i18n!(catalog, "Dzongkha"), i18n!(catalog, "Ewe"), i18n!(catalog, "Greek"), i18n!(catalog, "English"),
+        i18n!(catalog, "Esperanto"), i18n!(catalog, "Spanish"), i18n!(catalog, "Estonian"), i18n!(catalog, "Basque"),
+        i18n!(catalog, "Persian"), i18n!(catalog, "Fulah"), i18n!(catalog, "Finnish"), i18n!(catalog, "Fijian"),
+        i18n!(catalog, "Faroese"), i18n!(catalog, "French"), i18n!(catalog, "Western Frisian"),
+        i18n!(catalog, "Irish"), i18n!(catalog, "Scottish Gaelic"), i18n!(catalog, "Galician"),
+        i18n!(catalog, "Guarani"), i18n!(catalog, "Gujarati"), i18n!(catalog, "Manx"), i18n!(catalog, "Hausa"),
+        i18n!(catalog, "Hebrew"), i18n!(catalog, "Hindi"), i18n!(catalog, "Hiri Motu"), i18n!(catalog, "Croatian"),
+        i18n!(catalog, "Haitian Creole"), i18n!(catalog, "Hungarian"), i18n!(catalog, "Armenian"),
+        i18n!(catalog, "Herero"), i18n!(catalog, "Interlingua"), i18n!(catalog, "Indonesian"),
+        i18n!(catalog, "Interlingue"), i18n!(catalog, "Igbo"), i18n!(catalog, "Sichuan Yi"),
+        i18n!(catalog, "Inupiaq"), i18n!(catalog, "Ido"), i18n!(catalog, "Icelandic"), i18n!(catalog, "Italian"),
+        i18n!(catalog, "Inuktitut"), i18n!(catalog, "Japanese"), i18n!(catalog, "Javanese"),
+        i18n!(catalog, "Georgian"), i18n!(catalog, "Kongo"), i18n!(catalog, "Kikuyu"), i18n!(catalog, "Kuanyama"),
+        i18n!(catalog, "Kazakh"), i18n!(catalog, "Kalaallisut"), i18n!(catalog, "Khmer"), i18n!(catalog, "Kannada"),
+        i18n!(catalog, "Korean"), i18n!(catalog, "Kanuri"), i18n!(catalog, "Kashmiri"), i18n!(catalog, "Kurdish"),
+        i18n!(catalog, "Komi"), i18n!(catalog, "Cornish"), i18n!(catalog, "Kyrgyz"), i18n!(catalog, "Latin"),
+        i18n!(catalog, "Luxembourgish"), i18n!(catalog, "Ganda"), i18n!(catalog, "Limburgish"),
+        i18n!(catalog, "Lingala"), i18n!(catalog, "Lao"), i18n!(catalog, "Lithuanian"),
+        i18n!(catalog, "Luba-Katanga"), i18n!(catalog, "Latvian"), i18n!(catalog, "Malagasy"),
+        i18n!(catalog, "Marshallese"), i18n!(catalog, "Maori"), i18n!(catalog, "Macedonian"),
+        i18n!(catalog, "Malayalam"), i18n!(catalog, "Mongolian"), i18n!(catalog, "Marathi"), i18n!(catalog, "Malay"),
+        i18n!(catalog, "Maltese"), i18n!(catalog, "Burmese"), i18n!(catalog, "Nauru"),
+        i18n!(catalog, "Norwegian Bokmål"), i18n!(catalog, "North Ndebele"), i18n!(catalog, "Nepali"),
+        i18n!(catalog, "Ndonga"), i18n!(catalog, "Dutch"), i18n!(catalog, "Norwegian Nynorsk"),
+        i18n!(catalog, "Norwegian"), i18n!(catalog, "South Ndebele"), i18n!(catalog, "Navajo"),
+        i18n!(catalog, "Chichewa"), i18n!(catalog, "Occitan"), i18n!(catalog, "Ojibwa"), i18n!(catalog, "Oromo"),
+        i18n!(catalog, "Oriya"), i18n!(catalog, "Ossetian"), i18n!(catalog, "Punjabi"), i18n!(catalog, "Pali"),
+        i18n!(catalog, "Polish"), i18n!(catalog, "Pashto"), i18n!(catalog, "Portuguese"), i18n!(catalog, "Quechua"),
+        i18n!(catalog, "Romansh"), i18n!(catalog, "Rundi"), i18n!(catalog, "Romanian"), i18n!(catalog, "Russian"),
+        i18n!(catalog, "Kinyarwanda"), i18n!(catalog, "Sanskrit"), i18n!(catalog, "Sardinian"),
+        i18n!(catalog, "Sindhi"), i18n!(catalog, "Northern Sami"), i18n!(catalog, "Sango"), i18n!(catalog, "Sinhala"),
+        i18n!(catalog, "Slovak"), i18n!(catalog, "Slovenian"), i18n!(catalog, "Samoan"), i18n!(catalog, "Shona"),
+        i18n!(catalog, "Somali"), i18n!(catalog, "Albanian"), i18n!(catalog, "Serbian"), i18n!(catalog, "Swati"),
+        i18n!(catalog, "Southern Sotho"), i18n!(catalog, "Sundanese"), i18n!(catalog, "Swedish"),
+        i18n!(catalog, "Swahili"), i18n!(catalog, "Tamil"), i18n!(catalog, "Telugu"), i18n!(catalog, "Tajik"),
+        i18n!(catalog, "Thai"), i18n!(catalog, "Tigrinya"), i18n!(catalog, "Turkmen"), i18n!(catalog, "Tagalog"),
+        i18n!(catalog, "Tswana"), i18n!(catalog, "Tongan"), i18n!(catalog, "Turkish"), i18n!(catalog, "Tsonga"),
+        i18n!(catalog, "Tatar"), i18n!(catalog, "Twi"), i18n!(catalog, "Tahitian"), i18n!(catalog, "Uyghur"),
+        i18n!(catalog, "Ukrainian"), i18n!(catalog, "Urdu"), i18n!(catalog, "Uzbek"), i18n!(catalog, "Venda"),
+        i18n!(catalog, "Vietnamese"), i18n!(catalog, "Volapük"), i18n!(catalog, "Walloon"), i18n!(catalog, "Wolof"),
+        i18n!(catalog, "Xhosa"), i18n!(catalog, "Yiddish"), i18n!(catalog, "Yoruba"), i18n!(catalog, "Zhuang"),
+        i18n!(catalog, "Chinese"), i18n!(catalog, "Zulu"), i18n!(catalog, "Old English"),
+        i18n!(catalog, "Middle English"), i18n!(catalog, "Old French"), i18n!(catalog, "Middle French"),
+        i18n!(catalog, "Ancient Greek"), i18n!(catalog, "Old Norse"),
+    ]
+}
+
+pub async fn list(
+    session: Session,
+    config: web::Data<Config>,
+    i18n: I18n,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let languages = book_handler::language_counts(config.pool.clone(), user.id)?
+        .into_iter()
+        .map(|(code, count)| LanguageCount { name: language_name(&i18n.catalog, &code), code, count })
+        .collect();
+    let res = LanguagesCommandResult {success: true, languages, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
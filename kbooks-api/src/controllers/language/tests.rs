@@ -0,0 +1,56 @@
+use actix_web::{web, test, App};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::models::NewBook;
+
+use crate::controllers::fixtures::{managed_state, test_book, insert_books, populate_two_users};
+
+use super::LanguagesCommandResult;
+
+fn book_in(user_id: i32, title: &str, language: &str) -> NewBook {
+    NewBook {
+        language_main: language.to_string(),
+        ..test_book(user_id, title)
+    }
+}
+
+#[actix_rt::test]
+async fn test_languages() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        insert_books(conn, vec![
+            book_in(1, "Zazie dans le métro", "fr"),
+            book_in(1, "The Hobbit", "en"),
+            book_in(1, "Der Process", "de"),
+            book_in(1, "Das Schloss", "de"),
+            book_in(1, "Unknown", "tlh"),
+            book_in(2, "Il nome della rosa", "it"),
+        ]);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/language").route( web::get().to(super::list)))
+    });
+
+    // Most used languages first, names translated, only the books of the connected user
+    let mut response = srv.get("/language").header("Accept-Language", "fr").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: LanguagesCommandResult = response.json().await.unwrap();
+    let languages: Vec<(String, String, i64)> = result.languages.into_iter()
+        .map(|language| (language.code, language.name, language.count))
+        .collect();
+    assert_eq!(languages, vec![
+        (String::from("fr"), String::from("Français"), 3),
+        (String::from("de"), String::from("Allemand"), 2),
+        (String::from("en"), String::from("Anglais"), 1),
+        (String::from("tlh"), String::from("tlh"), 1),
+    ]);
+}
@@ -0,0 +1,164 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+
+use kbooks_common::khnum::wiring::{Config, DbPool};
+use kbooks_common::khnum::errors::ServiceError;
+use kbooks_common::khnum::users::repository::user_handler;
+
+use kbooks_common::repository::loan_handler::{self, Borrower};
+use kbooks_common::models::{LentBook, Loan};
+
+use crate::controllers::{non_empty, session_user};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoansCommandResult {
+    success: bool,
+    // the loan in progress
+    current: Option<Loan>,
+    // oldest first
+    loans: Vec<Loan>,
+    error: Option<String>
+}
+
+fn loans_result(pool: DbPool, user_id: i32, book_id: i32) -> Result<HttpResponse, ServiceError> {
+    let loans = loan_handler::list_for_book(pool, user_id, book_id)?;
+    let current = loans.iter().find(|loan| loan.returned_at.is_none()).cloned();
+    let res = LoansCommandResult {success: true, current, loans, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Loan history of a book ------------
+
+pub async fn list(
+    session: Session,
+    book_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    loans_result(config.pool.clone(), user.id, book_id.into_inner())
+}
+
+// ---------------- Lend / return a book ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct LendForm {
+    // the name of the borrower, or the login of a registered user
+    borrower: Option<String>,
+    borrower_login: Option<String>,
+    // now when not given
+    date: Option<NaiveDateTime>,
+    due: Option<NaiveDateTime>,
+    notes: Option<String>,
+}
+
+fn form_borrower(pool: DbPool, user_id: i32, lend_form: &LendForm) -> Result<Borrower, ServiceError> {
+    if let Some(login) = non_empty(&lend_form.borrower_login) {
+        let borrower = match user_handler::get_by_login(pool, &login) {
+            Err(DBError::NotFound) => return Err(ServiceError::BadRequest("borrower_login: unknown user".to_string())),
+            result => result?,
+        };
+        if borrower.id == user_id {
+            return Err(ServiceError::BadRequest("borrower_login: a book cannot be lent to its owner".to_string()));
+        }
+        return Ok(Borrower::User { id: borrower.id, login: borrower.login });
+    }
+    non_empty(&lend_form.borrower)
+        .map(Borrower::Name)
+        .ok_or_else(|| ServiceError::BadRequest("borrower: borrower is missing".to_string()))
+}
+
+pub fn already_lent() -> ServiceError {
+    ServiceError::BadRequest("book: the book is already lent".to_string())
+}
+
+pub async fn lend(
+    session: Session,
+    book_id: web::Path<i32>,
+    lend_form: web::Form<LendForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_id = book_id.into_inner();
+    let borrower = form_borrower(config.pool.clone(), user.id, &lend_form)?;
+    let date = lend_form.date.unwrap_or_else(|| Utc::now().naive_utc());
+    if lend_form.due.map(|due| due < date).unwrap_or(false) {
+        return Err(ServiceError::BadRequest("due: the book is due before it is lent".to_string()));
+    }
+    if loan_handler::current(config.pool.clone(), user.id, book_id)?.is_some() {
+        return Err(already_lent());
+    }
+    match loan_handler::lend(config.pool.clone(), user.id, book_id, borrower, date, lend_form.due, non_empty(&lend_form.notes)) {
+        // lent concurrently, see loans_in_progress_idx
+        Err(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => return Err(already_lent()),
+        result => result?,
+    };
+    loans_result(config.pool.clone(), user.id, book_id)
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReturnForm {
+    // now when not given
+    date: Option<NaiveDateTime>,
+    notes: Option<String>,
+}
+
+pub async fn give_back(
+    session: Session,
+    book_id: web::Path<i32>,
+    return_form: web::Form<ReturnForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_id = book_id.into_inner();
+    let date = return_form.date.unwrap_or_else(|| Utc::now().naive_utc());
+    match loan_handler::current(config.pool.clone(), user.id, book_id)? {
+        Some(loan) if loan.lent_at > date => return Err(ServiceError::BadRequest("date: the book is returned before it was lent".to_string())),
+        Some(_) => (),
+        None => return Err(ServiceError::BadRequest("book: the book is not lent".to_string())),
+    }
+    loan_handler::give_back(config.pool.clone(), user.id, book_id, date, non_empty(&return_form.notes))?;
+    loans_result(config.pool.clone(), user.id, book_id)
+}
+
+// ---------------- Lent books ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LentBooksCommandResult {
+    success: bool,
+    // the longest lent first
+    loans: Vec<LentBook>,
+    error: Option<String>
+}
+
+pub async fn lent(
+    session: Session,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let loans = loan_handler::lent(config.pool.clone(), user.id)?;
+    let res = LentBooksCommandResult {success: true, loans, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct OverdueQuery {
+    // now when not given
+    date: Option<NaiveDateTime>,
+}
+
+pub async fn overdue(
+    session: Session,
+    query: web::Query<OverdueQuery>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let date = query.date.unwrap_or_else(|| Utc::now().naive_utc());
+    let loans = loan_handler::overdue(config.pool.clone(), user.id, date)?;
+    let res = LentBooksCommandResult {success: true, loans, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
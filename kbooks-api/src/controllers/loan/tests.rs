@@ -0,0 +1,118 @@
+use actix_web::{web, test, http, App};
+use chrono::{NaiveDate, NaiveDateTime};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::repository::book_handler;
+
+use crate::controllers::fixtures::{managed_state, populate_two_users};
+
+use super::{LendForm, LentBooksCommandResult, LoansCommandResult, ReturnForm};
+
+fn day(day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd(2019, 1, day).and_hms(0, 0, 0)
+}
+
+fn lend_to(borrower: &str, date: u32, due: u32) -> LendForm {
+    LendForm {
+        borrower: Some(borrower.to_string()),
+        date: Some(day(date)),
+        due: Some(day(due)),
+        ..LendForm::default()
+    }
+}
+
+#[actix_rt::test]
+async fn test_loans() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/{id}/loan").route( web::get().to(super::list)))
+            .service( web::resource("/book/{id}/loan/lend").route( web::post().to(super::lend)))
+            .service( web::resource("/book/{id}/loan/return").route( web::post().to(super::give_back)))
+            .service( web::resource("/loan").route( web::get().to(super::lent)))
+            .service( web::resource("/loan/overdue").route( web::get().to(super::overdue)))
+    });
+
+    let mut response = srv.get("/book/1/loan").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: LoansCommandResult = response.json().await.unwrap();
+    assert_eq!((result.current, result.loans.len()), (None, 0));
+
+    //==== Lend a book
+    for form in vec![
+        lend_to(" ", 1, 10),
+        lend_to("Alice", 10, 1),
+        LendForm { borrower_login: Some("nobody".to_string()), ..lend_to("Alice", 1, 10) },
+        LendForm { borrower_login: Some("login".to_string()), ..lend_to("Alice", 1, 10) },
+    ] {
+        let response = srv.post("/book/1/loan/lend").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+    // book of the other user
+    let response = srv.post("/book/2/loan/lend").timeout(Duration::new(15, 0)).send_form(&lend_to("Alice", 1, 10)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    let response = srv.post("/book/1/loan/return").timeout(Duration::new(15, 0)).send_form(&ReturnForm::default()).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let form = LendForm { notes: Some(" take care ".to_string()), ..lend_to(" Alice ", 1, 10) };
+    let mut response = srv.post("/book/1/loan/lend").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: LoansCommandResult = response.json().await.unwrap();
+    let loan = result.current.unwrap();
+    assert_eq!((loan.borrower_name.as_str(), loan.borrower_id, loan.notes.as_deref()), ("Alice", None, Some("take care")));
+
+    let response = srv.post("/book/1/loan/lend").timeout(Duration::new(15, 0)).send_form(&lend_to("Bob", 2, 10)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    //==== Currently lent and overdue books
+    let mut response = srv.get("/loan").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: LentBooksCommandResult = response.json().await.unwrap();
+    assert_eq!(result.loans.iter().map(|lent| lent.book.id).collect::<Vec<i32>>(), vec![1]);
+
+    let mut response = srv.get("/loan/overdue?date=2019-01-09T00:00:00").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: LentBooksCommandResult = response.json().await.unwrap();
+    assert!(result.loans.is_empty());
+    let mut response = srv.get("/loan/overdue?date=2019-01-11T00:00:00").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: LentBooksCommandResult = response.json().await.unwrap();
+    assert_eq!(result.loans.len(), 1);
+
+    //==== Return the book
+    let form = ReturnForm { date: Some(NaiveDate::from_ymd(2018, 12, 31).and_hms(0, 0, 0)), notes: None };
+    let response = srv.post("/book/1/loan/return").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let form = ReturnForm { date: Some(day(12)), notes: Some("coffee stain".to_string()) };
+    let mut response = srv.post("/book/1/loan/return").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: LoansCommandResult = response.json().await.unwrap();
+    assert_eq!(result.current, None);
+    assert_eq!((result.loans[0].returned_at, result.loans[0].notes.as_deref()), (Some(day(12)), Some("take care\ncoffee stain")));
+
+    //==== Lend to a registered user, the history is kept
+    let form = LendForm { borrower_login: Some("other".to_string()), ..lend_to("", 20, 25) };
+    let mut response = srv.post("/book/1/loan/lend").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: LoansCommandResult = response.json().await.unwrap();
+    let loan = result.current.unwrap();
+    assert_eq!((loan.borrower_name.as_str(), loan.borrower_id), ("other", Some(2)));
+    assert_eq!(result.loans.len(), 2);
+
+    let mut response = srv.get("/loan/overdue?date=2019-01-30T00:00:00").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: LentBooksCommandResult = response.json().await.unwrap();
+    assert_eq!(result.loans.iter().map(|lent| lent.loan.id).collect::<Vec<i32>>(), vec![loan.id]);
+
+    //==== Loans are removed with the book
+    book_handler::delete(pool.clone(), 1, 1).expect("book not deleted");
+    let mut response = srv.get("/loan").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: LentBooksCommandResult = response.json().await.unwrap();
+    assert!(result.loans.is_empty());
+}
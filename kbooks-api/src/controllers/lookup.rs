@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use actix_session::{Session};
+use actix_web::{http, web, HttpResponse};
+use url::Url;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::isbn;
+use kbooks_common::cover::{CoverSize, CoverStore, MAX_COVER_SIZE};
+use kbooks_common::metadata::{BookMetadata, MetadataProviders};
+use kbooks_common::repository::{book_handler, metadata_handler};
+use kbooks_common::models::BookDetails;
+
+use crate::controllers::cover::{cover_urls, process as process_cover, CoverUrls};
+use crate::controllers::session_user;
+
+const TIMEOUT_SECONDS: u64 = 10;
+// The providers answer with a few kilobytes of JSON
+const MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+
+// The providers redirect the covers to their storage
+const MAX_REDIRECTS: usize = 5;
+
+// The openssl feature of awc gives the connector the TLS support needed by the https URLs of the providers
+fn client() -> awc::Client {
+    let connector = awc::Connector::new()
+        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
+        .finish();
+    awc::Client::build()
+        .connector(connector)
+        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
+        .finish()
+}
+
+// None when the resource does not exist. The redirections are followed.
+async fn fetch(url: &str, max_size: usize) -> Result<Option<Vec<u8>>, String> {
+    let client = client();
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let mut response = client.get(url.as_str())
+            .send().await
+            .map_err(|error| format!("{} : {}", url, error))?;
+        if response.status().is_redirection() {
+            let location = response.headers().get(http::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| format!("{} : redirection without location", url))?;
+            // the location may be relative to the requested URL
+            let next = Url::parse(&url).and_then(|base| base.join(location))
+                .map_err(|error| format!("{} : invalid location {} : {}", url, location, error))?;
+            url = next.as_str().to_string();
+            continue;
+        }
+        if response.status() == http::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("{} : unexpected status {}", url, response.status()));
+        }
+        let body = response.body().limit(max_size).await.map_err(|error| format!("{} : {}", url, error))?;
+        return Ok(Some(body.to_vec()));
+    }
+    Err(format!("{} : more than {} redirections", url, MAX_REDIRECTS))
+}
+
+// Metadata of the first provider knowing the ISBN.
+// The errors of the providers are returned only when no other provider found the book.
+async fn find_metadata(providers: &MetadataProviders, isbn: &str) -> Result<Option<BookMetadata>, String> {
+    let mut errors: Vec<String> = Vec::new();
+    for provider in &providers.providers {
+        let found = fetch(&provider.url(isbn), MAX_RESPONSE_SIZE).await
+            .and_then(|body| match body {
+                Some(body) => provider.parse(isbn, &body),
+                None => Ok(None),
+            });
+        match found {
+            Ok(Some(metadata)) => return Ok(Some(metadata)),
+            Ok(None) => (),
+            Err(error) => errors.push(format!("{} : {}", provider.name(), error)),
+        }
+    }
+    if errors.is_empty() { Ok(None) } else { Err(errors.join("\n")) }
+}
+
+fn path_isbn(raw: &str) -> Result<String, ServiceError> {
+    isbn::validate(raw).map_err(|error| ServiceError::BadRequest(format!("isbn: {}", error)))
+}
+
+fn unknown_isbn() -> ServiceError {
+    ServiceError::NotFound("isbn: no book found for this ISBN".to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LookupCommandResult {
+    success: bool,
+    // none when the providers could not be queried
+    metadata: Option<BookMetadata>,
+    error: Option<String>
+}
+
+fn providers_error(error: String) -> HttpResponse {
+    let res = LookupCommandResult {success: false, metadata: None, error: Some(error)};
+    HttpResponse::BadGateway().json(res)
+}
+
+// ---------------- Lookup an ISBN ------------
+
+// Fields of the new book form, as found by the providers
+pub async fn isbn(
+    session: Session,
+    raw_isbn: web::Path<String>,
+    providers: web::Data<MetadataProviders>,
+) -> Result<HttpResponse, ServiceError> {
+    session_user(&session)?;
+    let isbn = path_isbn(&raw_isbn)?;
+    let metadata = match find_metadata(&providers, &isbn).await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return Err(unknown_isbn()),
+        Err(error) => return Ok(providers_error(error)),
+    };
+    let res = LookupCommandResult {success: true, metadata: Some(metadata), error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Create a book from its ISBN ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct IsbnForm {
+    isbn: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IsbnBookCommandResult {
+    success: bool,
+    book: BookDetails,
+    // none when the provider has no cover, or when it could not be fetched : the error tells why
+    cover: Option<CoverUrls>,
+    error: Option<String>
+}
+
+// The content of each size of the cover : the book is created even when it can not be fetched
+async fn download_cover(url: &str) -> Result<Option<Vec<(CoverSize, Vec<u8>)>>, String> {
+    let data = match fetch(url, MAX_COVER_SIZE).await? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    process_cover(data).await.map(Some).map_err(|error| format!("{} : {}", url, error))
+}
+
+pub async fn create(
+    session: Session,
+    isbn_form: web::Form<IsbnForm>,
+    config: web::Data<Config>,
+    providers: web::Data<MetadataProviders>,
+    covers: web::Data<CoverStore>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let isbn = path_isbn(&isbn_form.isbn)?;
+    let metadata = match find_metadata(&providers, &isbn).await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return Err(unknown_isbn()),
+        Err(error) => return Ok(providers_error(error)),
+    };
+    let (sizes, mut error) = match &metadata.cover_url {
+        None => (None, None),
+        Some(url) => match download_cover(url).await {
+            Ok(sizes) => (sizes, None),
+            Err(error) => (None, Some(format!("cover: {}", error))),
+        },
+    };
+
+    // the cover is stored once the book id is known, and recorded with the book
+    let mut stored = None;
+    let created = metadata_handler::create_book(config.pool.clone(), user.id, &metadata, |book_id| {
+        let sizes = sizes?;
+        match covers.save(book_id, &sizes) {
+            Ok(key) => {
+                stored = Some(key.clone());
+                Some(key)
+            }
+            Err(cover_error) => {
+                error = Some(format!("cover: {}", cover_error));
+                None
+            }
+        }
+    });
+    let book = match created {
+        Ok(book) => book,
+        Err(create_error) => {
+            if let Some(key) = stored {
+                let _ = covers.delete(&key);
+            }
+            return Err(create_error);
+        }
+    };
+    let cover = cover_urls(&book.cover);
+    let book = book_handler::with_details(config.pool.clone(), vec![book])?.remove(0);
+    let res = IsbnBookCommandResult {success: true, book, cover, error};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,124 @@
+use actix_web::{web, test, http, App, HttpResponse};
+use dotenv::dotenv;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::cover::CoverSize;
+use kbooks_common::metadata::{MetadataProviders, OpenLibrary};
+use kbooks_common::repository::book_handler;
+
+use crate::controllers::fixtures::{managed_state, populate_two_users, test_cover_store, GIF};
+
+use super::{IsbnBookCommandResult, IsbnForm, LookupCommandResult};
+
+const RESPONSE: &str = r#"{"ISBN:9782070368228": {"details": {
+    "title": "W ou le souvenir d'enfance",
+    "authors": [{"key": "/authors/OL1A", "name": "Georges Perec"}],
+    "publish_date": "1993",
+    "number_of_pages": 220,
+    "languages": [{"key": "/languages/fre"}],
+    "covers": [1234]
+}}}"#;
+
+// Several languages : the code is not one of a language
+const MULTILINGUAL_RESPONSE: &str = r#"{"ISBN:9780306406157": {"details": {
+    "title": "Dictionnaire des synonymes",
+    "languages": [{"key": "/languages/mul"}]
+}}}"#;
+
+// Open Library stand-in : knows two ISBNs, and fails for 0441478123
+async fn stub_books(query: web::Query<HashMap<String, String>>) -> HttpResponse {
+    match query.get("bibkeys").map(String::as_str) {
+        Some("ISBN:9782070368228") => HttpResponse::Ok().content_type("application/json").body(RESPONSE),
+        Some("ISBN:9780306406157") => HttpResponse::Ok().content_type("application/json").body(MULTILINGUAL_RESPONSE),
+        Some("ISBN:0441478123") => HttpResponse::ServiceUnavailable().finish(),
+        _ => HttpResponse::Ok().content_type("application/json").body("{}"),
+    }
+}
+
+// Open Library redirects the covers to their storage
+async fn stub_cover_redirect(file: web::Path<String>) -> HttpResponse {
+    HttpResponse::Found().header(http::header::LOCATION, format!("/storage/{}", file)).finish()
+}
+
+async fn stub_cover() -> HttpResponse {
+    HttpResponse::Ok().content_type("image/gif").body(GIF)
+}
+
+#[actix_rt::test]
+async fn test_lookup() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    populate_two_users(&pool.get().unwrap());
+    let stub = test::start(|| {
+        App::new()
+            .service( web::resource("/api/books").route( web::get().to(stub_books)))
+            .service( web::resource("/b/id/{file}").route( web::get().to(stub_cover_redirect)))
+            .service( web::resource("/storage/{file}").route( web::get().to(stub_cover)))
+    });
+    let stub_url = stub.url("");
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .data(MetadataProviders::new(vec![Box::new(OpenLibrary::new(&stub_url, &stub_url))]))
+            .data(test_cover_store("lookup"))
+            .service( web::resource("/book/isbn").route( web::post().to(super::create)))
+            .service( web::resource("/lookup/isbn/{isbn}").route( web::get().to(super::isbn)))
+    });
+
+    //==== Lookup
+    let mut response = srv.get("/lookup/isbn/978-2-07-036822-8").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: LookupCommandResult = response.json().await.expect("Could not parse json");
+    let metadata = result.metadata.unwrap();
+    assert_eq!(metadata.title, "W ou le souvenir d'enfance");
+    assert_eq!(metadata.authors, vec!["Perec, Georges"]);
+    assert_eq!((metadata.publicationdate.as_str(), metadata.language_main.as_str(), metadata.page_count), ("1993", "fr", Some(220)));
+    assert_eq!(metadata.cover_url, Some(format!("{}b/id/1234-L.jpg", stub.url(""))));
+
+    for (isbn, status) in vec![
+        ("978-2-07-036822-9", http::StatusCode::BAD_REQUEST),
+        ("207036822X", http::StatusCode::NOT_FOUND),
+        ("0441478123", http::StatusCode::BAD_GATEWAY),
+    ] {
+        let response = srv.get(format!("/lookup/isbn/{}", isbn)).timeout(Duration::new(15, 0)).send().await.unwrap();
+        assert_eq!(response.status(), status, "{}", isbn);
+    }
+
+    //==== Create from ISBN
+    let response = srv.post("/book/isbn").timeout(Duration::new(15, 0))
+        .send_form(&IsbnForm {isbn: "207036822X".to_string()}).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    let mut response = srv.post("/book/isbn").timeout(Duration::new(15, 0))
+        .send_form(&IsbnForm {isbn: "9782070368228".to_string()}).await.unwrap();
+    assert!(response.status().is_success());
+    let result: IsbnBookCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.error, None);
+    assert!(result.cover.is_some());
+
+    let book = book_handler::list(pool.clone(), 1).unwrap().into_iter()
+        .find(|book| book.title == "W ou le souvenir d'enfance").unwrap();
+    assert_eq!((book.author_lf.as_str(), book.isbn.as_str(), book.page_count), ("Perec, Georges", "9782070368228", Some(220)));
+    assert!(test_cover_store("lookup").get(&book.cover, CoverSize::Small).is_ok());
+
+    // the book is already in the library
+    let response = srv.post("/book/isbn").timeout(Duration::new(15, 0))
+        .send_form(&IsbnForm {isbn: "978-2-07-036822-8".to_string()}).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let count = book_handler::list(pool.clone(), 1).unwrap().into_iter()
+        .filter(|book| book.isbn == "9782070368228").count();
+    assert_eq!(count, 1);
+
+    // the unknown language codes of the provider are left out
+    let mut response = srv.post("/book/isbn").timeout(Duration::new(15, 0))
+        .send_form(&IsbnForm {isbn: "9780306406157".to_string()}).await.unwrap();
+    assert!(response.status().is_success());
+    let result: IsbnBookCommandResult = response.json().await.expect("Could not parse json");
+    assert_eq!(result.error, None);
+    let book = book_handler::list(pool.clone(), 1).unwrap().into_iter()
+        .find(|book| book.isbn == "9780306406157").unwrap();
+    assert_eq!((book.title.as_str(), book.language_main.as_str()), ("Dictionnaire des synonymes", ""));
+}
@@ -1 +1,86 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use actix_multipart::Multipart;
+use actix_session::Session;
+use futures::StreamExt;
+use serde::{de, Deserialize, Deserializer};
+
+use kbooks_common::khnum::errors::ServiceError;
+use kbooks_common::khnum::users::models::User;
+
 pub mod book;
+pub mod borrow;
+pub mod cover;
+pub mod export;
+pub mod friend;
+pub mod goal;
+pub mod import;
+pub mod language;
+pub mod loan;
+pub mod lookup;
+pub mod reading;
+pub mod shelf;
+pub mod stats;
+pub mod tag;
+pub mod wish;
+
+#[cfg(test)]
+mod fixtures;
+
+// The test user is always connected in test environment
+pub fn session_user(session: &Session) -> Result<User, ServiceError> {
+    #[cfg(test)]
+    let opt = Some(User::testUser());
+
+    #[cfg(not(test))]
+    let opt = session.get::<User>("user").expect("could not get session user");
+
+    opt.ok_or_else(|| ServiceError::Unauthorized("User not connected".to_string()))
+}
+
+// Optional form field, trimmed : none when empty
+pub fn non_empty(text: &Option<String>) -> Option<String> {
+    text.as_ref().map(|text| text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+// Form field of a partial update : absent leaves the value unchanged, empty clears it.
+// To be used with #[serde(default, deserialize_with = "clearable")]
+pub fn clearable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let raw = String::deserialize(deserializer)?;
+    if raw.trim().is_empty() {
+        return Ok(Some(None));
+    }
+    raw.trim().parse::<T>().map(|value| Some(Some(value))).map_err(de::Error::custom)
+}
+
+// Content of the "file" field of the form
+pub async fn read_file(mut payload: Multipart, max_size: usize) -> Result<Vec<u8>, ServiceError> {
+    let invalid = |_| ServiceError::BadRequest("file: invalid multipart content".to_string());
+    let mut data: Vec<u8> = Vec::new();
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(invalid)?;
+        let is_file = field.content_disposition()
+            .and_then(|disposition| disposition.get_name().map(|name| name == "file"))
+            .unwrap_or(false);
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(invalid)?;
+            if !is_file {
+                continue;
+            }
+            if data.len() + chunk.len() > max_size {
+                return Err(ServiceError::BadRequest("file: the file is too large".to_string()));
+            }
+            data.extend_from_slice(&chunk);
+        }
+    }
+    if data.is_empty() {
+        return Err(ServiceError::BadRequest("file: no file sent".to_string()));
+    }
+    Ok(data)
+}
@@ -0,0 +1,180 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+
+use kbooks_common::khnum::wiring::{Config, DbPool};
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::repository::{book_handler, reading_handler};
+use kbooks_common::models::{Reading, ReadingDetails, ReadingStatus};
+
+use crate::controllers::session_user;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadingsCommandResult {
+    success: bool,
+    status: ReadingStatus,
+    // oldest first
+    readings: Vec<ReadingDetails>,
+    error: Option<String>
+}
+
+fn readings_result(pool: DbPool, user_id: i32, book_id: i32) -> Result<HttpResponse, ServiceError> {
+    let readings = reading_handler::list_for_book(pool.clone(), user_id, book_id)?;
+    let status = reading_handler::status(readings.last());
+    let readings = reading_handler::with_progress(pool, readings)?;
+    let res = ReadingsCommandResult {success: true, status, readings, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Reading history of a book ------------
+
+pub async fn list(
+    session: Session,
+    book_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    readings_result(config.pool.clone(), user.id, book_id.into_inner())
+}
+
+// ---------------- Start / finish / abandon a reading ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReadingForm {
+    // now when not given
+    date: Option<NaiveDateTime>,
+    notes: Option<String>,
+}
+
+impl ReadingForm {
+    fn date(&self) -> NaiveDateTime {
+        self.date.unwrap_or_else(|| Utc::now().naive_utc())
+    }
+
+    fn notes(&self) -> Option<String> {
+        self.notes.as_ref().map(|notes| notes.trim().to_string()).filter(|notes| !notes.is_empty())
+    }
+}
+
+fn check_end(reading: &Reading, date: NaiveDateTime) -> Result<(), ServiceError> {
+    match reading.started_at {
+        Some(started) if started > date => Err(ServiceError::BadRequest("date: the reading ends before it started".to_string())),
+        _ => Ok(()),
+    }
+}
+
+pub async fn start(
+    session: Session,
+    book_id: web::Path<i32>,
+    reading_form: web::Form<ReadingForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_id = book_id.into_inner();
+    let in_progress = || ServiceError::BadRequest("book: a reading is already in progress".to_string());
+    if reading_handler::current(config.pool.clone(), user.id, book_id)?.is_some() {
+        return Err(in_progress());
+    }
+    match reading_handler::start(config.pool.clone(), user.id, book_id, reading_form.date(), reading_form.notes()) {
+        // started concurrently
+        Err(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => return Err(in_progress()),
+        result => result?,
+    };
+    readings_result(config.pool.clone(), user.id, book_id)
+}
+
+// Without a reading in progress, the book is recorded as read at the given date
+pub async fn finish(
+    session: Session,
+    book_id: web::Path<i32>,
+    reading_form: web::Form<ReadingForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_id = book_id.into_inner();
+    let date = reading_form.date();
+    if let Some(reading) = reading_handler::current(config.pool.clone(), user.id, book_id)? {
+        check_end(&reading, date)?;
+    }
+    reading_handler::finish(config.pool.clone(), user.id, book_id, date, reading_form.notes())?;
+    readings_result(config.pool.clone(), user.id, book_id)
+}
+
+pub async fn abandon(
+    session: Session,
+    book_id: web::Path<i32>,
+    reading_form: web::Form<ReadingForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book_id = book_id.into_inner();
+    let date = reading_form.date();
+    match reading_handler::current(config.pool.clone(), user.id, book_id)? {
+        Some(reading) => check_end(&reading, date)?,
+        None => return Err(ServiceError::BadRequest("book: no reading in progress".to_string())),
+    }
+    reading_handler::abandon(config.pool.clone(), user.id, book_id, date, reading_form.notes())?;
+    readings_result(config.pool.clone(), user.id, book_id)
+}
+
+// ---------------- Progress of the reading in progress ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ProgressForm {
+    // the percentage is computed from the page when the page count of the book is known
+    page: Option<i32>,
+    percent: Option<i32>,
+    // now when not given
+    date: Option<NaiveDateTime>,
+}
+
+// Page and percentage read
+fn form_progress(progress_form: &ProgressForm, page_count: Option<i32>) -> Result<(Option<i32>, i32), ServiceError> {
+    match (progress_form.page, progress_form.percent, page_count) {
+        (Some(page), _, Some(page_count)) if page < 0 || page > page_count => {
+            Err(ServiceError::BadRequest(format!("page: must be between 0 and {}", page_count)))
+        }
+        (Some(page), _, Some(page_count)) => Ok((Some(page), reading_handler::percent(page, page_count))),
+        (Some(page), _, None) if page < 0 => Err(ServiceError::BadRequest("page: must be positive".to_string())),
+        (_, Some(percent), _) if percent < 0 || percent > 100 => {
+            Err(ServiceError::BadRequest("percent: must be between 0 and 100".to_string()))
+        }
+        (page, Some(percent), _) => Ok((page, percent)),
+        (Some(_), None, None) => Err(ServiceError::BadRequest("page: the page count of the book is unknown".to_string())),
+        (None, None, _) => Err(ServiceError::BadRequest("page: the page or the percentage is missing".to_string())),
+    }
+}
+
+pub async fn progress(
+    session: Session,
+    book_id: web::Path<i32>,
+    progress_form: web::Form<ProgressForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let book = book_handler::get(config.pool.clone(), user.id, book_id.into_inner())?;
+    let (page, percent) = form_progress(&progress_form, book.page_count)?;
+    if reading_handler::current(config.pool.clone(), user.id, book.id)?.is_none() {
+        return Err(ServiceError::BadRequest("book: no reading in progress".to_string()));
+    }
+    let date = progress_form.date.unwrap_or_else(|| Utc::now().naive_utc());
+    reading_handler::add_progress(config.pool.clone(), user.id, book.id, page, percent, date)?;
+    readings_result(config.pool.clone(), user.id, book.id)
+}
+
+// ---------------- Delete a reading ------------
+
+pub async fn delete(
+    session: Session,
+    data: web::Path<(i32, i32)>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    reading_handler::delete(config.pool.clone(), user.id, data.0, data.1)?;
+    readings_result(config.pool.clone(), user.id, data.0)
+}
+
+#[cfg(test)]
+mod tests;
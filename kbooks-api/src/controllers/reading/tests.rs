@@ -0,0 +1,156 @@
+use actix_web::{web, test, http, App};
+use chrono::NaiveDate;
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::models::{BookChanges, ReadingStatus};
+use kbooks_common::repository::book_handler::{self, BookFilters, ListOptions};
+
+use crate::controllers::fixtures::{managed_state, populate_two_users};
+
+use super::{ProgressForm, ReadingForm, ReadingsCommandResult};
+
+fn on(day: u32) -> ReadingForm {
+    ReadingForm { date: Some(NaiveDate::from_ymd(2019, 1, day).and_hms(0, 0, 0)), ..ReadingForm::default() }
+}
+
+#[actix_rt::test]
+async fn test_readings() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/{id}/reading").route( web::get().to(super::list)))
+            .service( web::resource("/book/{id}/reading/start").route( web::post().to(super::start)))
+            .service( web::resource("/book/{id}/reading/finish").route( web::post().to(super::finish)))
+            .service( web::resource("/book/{id}/reading/abandon").route( web::post().to(super::abandon)))
+            .service( web::resource("/book/{id}/reading/{reading_id}").route( web::delete().to(super::delete)))
+    });
+
+    let mut response = srv.get("/book/1/reading").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: ReadingsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.status, ReadingStatus::ToRead);
+
+    //==== First reading
+    let form = ReadingForm { notes: Some("slow start".to_string()), ..on(1) };
+    let mut response = srv.post("/book/1/reading/start").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: ReadingsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.status, ReadingStatus::Reading);
+
+    let response = srv.post("/book/1/reading/start").timeout(Duration::new(15, 0)).send_form(&on(2)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    // ends before it started
+    let form = ReadingForm { date: Some(NaiveDate::from_ymd(2018, 12, 31).and_hms(0, 0, 0)), notes: None };
+    let response = srv.post("/book/1/reading/finish").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let mut response = srv.post("/book/1/reading/finish").timeout(Duration::new(15, 0)).send_form(&on(9)).await.unwrap();
+    assert!(response.status().is_success());
+    let result: ReadingsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.status, ReadingStatus::Read);
+    assert_eq!(result.readings.len(), 1);
+    assert_eq!(result.readings[0].reading.notes, Some("slow start".to_string()));
+    let book = book_handler::get(pool.clone(), 1, 1).unwrap();
+    assert_eq!(book.finished_stamp, on(9).date);
+
+    //==== Second reading, abandoned
+    let response = srv.post("/book/1/reading/abandon").timeout(Duration::new(15, 0)).send_form(&on(10)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    srv.post("/book/1/reading/start").timeout(Duration::new(15, 0)).send_form(&on(20)).await.unwrap();
+    // still found among the books read
+    let book = book_handler::get(pool.clone(), 1, 1).unwrap();
+    assert_eq!((book.started_stamp, book.finished_stamp), (on(20).date, on(9).date));
+    let mut response = srv.post("/book/1/reading/abandon").timeout(Duration::new(15, 0)).send_form(&on(25)).await.unwrap();
+    let result: ReadingsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.status, ReadingStatus::Abandoned);
+    assert_eq!(result.readings.len(), 2);
+
+    let options = ListOptions {
+        filters: BookFilters { status: Some(ReadingStatus::Abandoned), ..BookFilters::default() },
+        ..ListOptions::default()
+    };
+    let (books, _total) = book_handler::list_page(pool.clone(), 1, &options).unwrap();
+    assert_eq!(books.len(), 1);
+
+    //==== Delete the abandoned reading
+    let abandoned_id = result.readings[1].reading.id;
+    let mut response = srv.delete(format!("/book/1/reading/{}", abandoned_id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: ReadingsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.status, ReadingStatus::Read);
+
+    //==== Books of other users
+    let response = srv.get("/book/2/reading").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    let response = srv.post("/book/2/reading/start").timeout(Duration::new(15, 0)).send_form(&on(1)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn test_progress() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/{id}/reading/start").route( web::post().to(super::start)))
+            .service( web::resource("/book/{id}/progress").route( web::post().to(super::progress)))
+    });
+
+    let page = |page: i32| ProgressForm { page: Some(page), ..ProgressForm::default() };
+
+    // no reading in progress
+    let form = ProgressForm { percent: Some(10), ..ProgressForm::default() };
+    let response = srv.post("/book/1/progress").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    srv.post("/book/1/reading/start").timeout(Duration::new(15, 0)).send_form(&on(1)).await.unwrap();
+    let mut response = srv.post("/book/1/progress").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: ReadingsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.readings[0].progress[0].percent, 10);
+
+    // the page is checked even with a percentage
+    let form_page = ProgressForm { page: Some(-1), percent: Some(10), ..ProgressForm::default() };
+    let response = srv.post("/book/1/progress").timeout(Duration::new(15, 0)).send_form(&form_page).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    // pages need the page count
+    let response = srv.post("/book/1/progress").timeout(Duration::new(15, 0)).send_form(&page(142)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let changes = BookChanges { page_count: Some(Some(380)), ..BookChanges::default() };
+    book_handler::update(pool.clone(), 1, 1, &changes).unwrap();
+    let response = srv.post("/book/1/progress").timeout(Duration::new(15, 0)).send_form(&page(400)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let form_page = ProgressForm { page: Some(400), percent: Some(10), ..ProgressForm::default() };
+    let response = srv.post("/book/1/progress").timeout(Duration::new(15, 0)).send_form(&form_page).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let mut response = srv.post("/book/1/progress").timeout(Duration::new(15, 0)).send_form(&page(142)).await.unwrap();
+    let result: ReadingsCommandResult = response.json().await.unwrap();
+    let progress: Vec<(Option<i32>, i32)> = result.readings[0].progress.iter().map(|progress| (progress.page, progress.percent)).collect();
+    assert_eq!(progress, vec![(None, 10), (Some(142), 37)]);
+
+    // shown in the books list
+    let book = book_handler::get(pool.clone(), 1, 1).unwrap();
+    let books = book_handler::with_details(pool.clone(), vec![book]).unwrap();
+    assert_eq!(books[0].progress, Some(37));
+
+    let response = srv.post("/book/2/progress").timeout(Duration::new(15, 0)).send_form(&page(1)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,199 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+
+use kbooks_common::khnum::wiring::{Config, CommandResult, DbPool};
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::book_filter::{self, Expr};
+use kbooks_common::repository::{book_handler, shelf_handler};
+use kbooks_common::models::{BookDetails, Shelf, ShelfCount};
+
+use crate::controllers::session_user;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShelfCommandResult {
+    success: bool,
+    shelf: Shelf,
+    // in the shelf order
+    books: Vec<BookDetails>,
+    error: Option<String>
+}
+
+fn shelf_result(pool: DbPool, user_id: i32, shelf_id: i32) -> Result<HttpResponse, ServiceError> {
+    let shelf = shelf_handler::get(pool.clone(), user_id, shelf_id)?;
+    let books = shelf_handler::books(pool.clone(), user_id, shelf_id)?;
+    let books = book_handler::with_details(pool, books)?;
+    let res = ShelfCommandResult {success: true, shelf, books, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Shelves of the user ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShelvesCommandResult {
+    success: bool,
+    shelves: Vec<ShelfCount>,
+    error: Option<String>
+}
+
+pub async fn list(
+    session: Session,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let shelves = shelf_handler::list(config.pool.clone(), user.id)?;
+    let res = ShelvesCommandResult {success: true, shelves, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn get(
+    session: Session,
+    shelf_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    shelf_result(config.pool.clone(), user.id, shelf_id.into_inner())
+}
+
+// ---------------- Create / update / delete a shelf ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ShelfForm {
+    name: String,
+    description: Option<String>,
+    // filter expression of a smart shelf, see book_filter
+    query: Option<String>,
+}
+
+// Trimmed name, which must be unique among the user shelves
+fn form_name(pool: DbPool, user_id: i32, shelf_id: Option<i32>, raw: &str) -> Result<String, ServiceError> {
+    let name = raw.trim().to_string();
+    if name.is_empty() {
+        return Err(ServiceError::BadRequest("name: shelf name is missing".to_string()));
+    }
+    match shelf_handler::find_by_name(pool, user_id, &name)? {
+        Some(shelf) if Some(shelf.id) != shelf_id => Err(ServiceError::BadRequest("name: a shelf with this name already exists".to_string())),
+        _ => Ok(name),
+    }
+}
+
+fn form_description(shelf_form: &ShelfForm) -> String {
+    shelf_form.description.as_ref().map(|description| description.trim().to_string()).unwrap_or_default()
+}
+
+fn form_query(shelf_form: &ShelfForm) -> Result<Option<Expr>, ServiceError> {
+    match shelf_form.query {
+        Some(ref raw) if !raw.trim().is_empty() => book_filter::parse(raw)
+            .map(Some)
+            .map_err(|error| ServiceError::BadRequest(format!("query: {}", error))),
+        _ => Ok(None),
+    }
+}
+
+// The books of a smart shelf are the ones matching its query
+fn manual_shelf(pool: DbPool, user_id: i32, shelf_id: i32) -> Result<(), ServiceError> {
+    let shelf = shelf_handler::get(pool, user_id, shelf_id)?;
+    if shelf.query.is_some() {
+        return Err(ServiceError::BadRequest("shelf: the books of a smart shelf can not be changed".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn create(
+    session: Session,
+    shelf_form: web::Form<ShelfForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let name = form_name(config.pool.clone(), user.id, None, &shelf_form.name)?;
+    let query = form_query(&shelf_form)?;
+    let shelf = shelf_handler::create(config.pool.clone(), user.id, &name, &form_description(&shelf_form), query.as_ref())?;
+    shelf_result(config.pool.clone(), user.id, shelf.id)
+}
+
+pub async fn update(
+    session: Session,
+    shelf_id: web::Path<i32>,
+    shelf_form: web::Form<ShelfForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let shelf_id = shelf_id.into_inner();
+    let name = form_name(config.pool.clone(), user.id, Some(shelf_id), &shelf_form.name)?;
+    let query = form_query(&shelf_form)?;
+    shelf_handler::update(config.pool.clone(), user.id, shelf_id, &name, &form_description(&shelf_form), query.as_ref())?;
+    shelf_result(config.pool.clone(), user.id, shelf_id)
+}
+
+// The books of the shelf are kept
+pub async fn delete(
+    session: Session,
+    shelf_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    shelf_handler::delete(config.pool.clone(), user.id, shelf_id.into_inner())?;
+    Ok(HttpResponse::Ok().json(CommandResult::success()))
+}
+
+// ---------------- Books of a shelf ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ShelfBookForm {
+    book_id: i32,
+    // from 0, at the end when not given
+    position: Option<usize>,
+}
+
+// A book already on the shelf is moved to the given position
+pub async fn add_book(
+    session: Session,
+    shelf_id: web::Path<i32>,
+    book_form: web::Form<ShelfBookForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let shelf_id = shelf_id.into_inner();
+    manual_shelf(config.pool.clone(), user.id, shelf_id)?;
+    shelf_handler::add_book(config.pool.clone(), user.id, shelf_id, book_form.book_id, book_form.position)?;
+    shelf_result(config.pool.clone(), user.id, shelf_id)
+}
+
+pub async fn remove_book(
+    session: Session,
+    data: web::Path<(i32, i32)>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    manual_shelf(config.pool.clone(), user.id, data.0)?;
+    shelf_handler::remove_book(config.pool.clone(), user.id, data.0, data.1)?;
+    shelf_result(config.pool.clone(), user.id, data.0)
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct OrderForm {
+    // comma separated, the books not listed keep their relative order after the listed ones
+    book_ids: String,
+}
+
+pub async fn reorder(
+    session: Session,
+    shelf_id: web::Path<i32>,
+    order_form: web::Form<OrderForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let shelf_id = shelf_id.into_inner();
+    let book_ids = order_form.book_ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse::<i32>())
+        .collect::<Result<Vec<i32>, _>>()
+        .map_err(|_| ServiceError::BadRequest("book_ids: must be a comma separated list of book ids".to_string()))?;
+    manual_shelf(config.pool.clone(), user.id, shelf_id)?;
+    shelf_handler::reorder(config.pool.clone(), user.id, shelf_id, &book_ids)?;
+    shelf_result(config.pool.clone(), user.id, shelf_id)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,183 @@
+use actix_web::{web, test, http, App};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::models::{BookChanges, NewBook};
+use kbooks_common::repository::tag_handler;
+use kbooks_common::repository::book_handler::{self, BookFilters, ListOptions};
+
+use crate::controllers::fixtures::{managed_state, test_book, insert_books, populate_two_users};
+
+use super::{OrderForm, ShelfBookForm, ShelfCommandResult, ShelfForm, ShelvesCommandResult};
+
+fn shelf_form(name: &str) -> ShelfForm {
+    ShelfForm { name: name.to_string(), description: Some(" Potential literature ".to_string()), query: None }
+}
+
+fn book_ids(result: &ShelfCommandResult) -> Vec<i32> {
+    result.books.iter().map(|details| details.book.id).collect()
+}
+
+#[actix_rt::test]
+async fn test_shelves() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        insert_books(conn, vec![test_book(1, "La Disparition"), test_book(1, "Les Revenentes")]);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/shelf")
+                .route( web::get().to(super::list))
+                .route( web::post().to(super::create)))
+            .service( web::resource("/shelf/{id}")
+                .route( web::get().to(super::get))
+                .route( web::put().to(super::update))
+                .route( web::delete().to(super::delete)))
+            .service( web::resource("/shelf/{id}/book").route( web::post().to(super::add_book)))
+            .service( web::resource("/shelf/{id}/book/{book_id}").route( web::delete().to(super::remove_book)))
+            .service( web::resource("/shelf/{id}/order").route( web::put().to(super::reorder)))
+    });
+
+    //==== Create
+    let mut response = srv.post("/shelf").timeout(Duration::new(15, 0)).send_form(&shelf_form(" Oulipo ")).await.unwrap();
+    assert!(response.status().is_success());
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    assert_eq!((result.shelf.name.as_str(), result.shelf.description.as_str()), ("Oulipo", "Potential literature"));
+    let shelf_id = result.shelf.id;
+
+    let response = srv.post("/shelf").timeout(Duration::new(15, 0)).send_form(&shelf_form("Oulipo")).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let response = srv.post("/shelf").timeout(Duration::new(15, 0)).send_form(&shelf_form(" ")).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    //==== Books and their order
+    for book_id in &[1, 4, 5] {
+        let form = ShelfBookForm { book_id: *book_id, position: None };
+        let response = srv.post(format!("/shelf/{}/book", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+        assert!(response.status().is_success());
+    }
+    // book of another user
+    let form = ShelfBookForm { book_id: 2, position: None };
+    let response = srv.post(format!("/shelf/{}/book", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let form = ShelfBookForm { book_id: 5, position: Some(0) };
+    let mut response = srv.post(format!("/shelf/{}/book", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    // by title
+    assert_eq!(book_ids(&result), vec![5, 4, 1]);
+
+    let form = OrderForm { book_ids: "4, 5".to_string() };
+    let mut response = srv.put(format!("/shelf/{}/order", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    assert_eq!(book_ids(&result), vec![4, 5, 1]);
+    let form = OrderForm { book_ids: "4,five".to_string() };
+    let response = srv.put(format!("/shelf/{}/order", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let mut response = srv.delete(format!("/shelf/{}/book/5", shelf_id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    assert_eq!(book_ids(&result), vec![4, 1]);
+
+    // books list filter
+    let options = ListOptions {
+        filters: BookFilters { shelf: Some(shelf_id), ..BookFilters::default() },
+        ..ListOptions::default()
+    };
+    let (_books, total) = book_handler::list_page(pool.clone(), 1, &options).unwrap();
+    assert_eq!(total, 2);
+
+    //==== List, update, delete
+    srv.post("/shelf").timeout(Duration::new(15, 0)).send_form(&shelf_form("Bedside")).await.unwrap();
+    let mut response = srv.get("/shelf").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: ShelvesCommandResult = response.json().await.unwrap();
+    let counts: Vec<(String, i64)> = result.shelves.into_iter().map(|count| (count.shelf.name, count.count)).collect();
+    assert_eq!(counts, vec![(String::from("Bedside"), 0), (String::from("Oulipo"), 2)]);
+
+    let response = srv.put(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send_form(&shelf_form("Bedside")).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let mut response = srv.put(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send_form(&shelf_form("OuLiPo")).await.unwrap();
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    assert_eq!(result.shelf.name, "OuLiPo");
+
+    let response = srv.delete(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let response = srv.get(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    assert!(book_handler::get(pool.clone(), 1, 4).is_ok());
+}
+
+#[actix_rt::test]
+async fn test_smart_shelves() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        insert_books(conn, vec![
+            NewBook { rating: Some(5), language_original: "de".to_string(), ..test_book(1, "Duineser Elegien") },
+            NewBook { rating: Some(4), ..test_book(1, "Alcools") },
+        ]);
+    }
+    tag_handler::add_to_book(pool.clone(), 1, 4, "Poetry").unwrap();
+    tag_handler::add_to_book(pool.clone(), 1, 5, "poetry").unwrap();
+
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/shelf")
+                .route( web::get().to(super::list))
+                .route( web::post().to(super::create)))
+            .service( web::resource("/shelf/{id}")
+                .route( web::get().to(super::get))
+                .route( web::put().to(super::update)))
+            .service( web::resource("/shelf/{id}/book").route( web::post().to(super::add_book)))
+    });
+
+    let form = ShelfForm { query: Some("language_original != language_main AND rating >= 4 AND tag:Poetry".to_string()), ..shelf_form("Translated poetry") };
+    let mut response = srv.post("/shelf").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    assert_eq!(result.shelf.query, Some(String::from("language_original != language_main AND rating >= 4 AND tag:\"poetry\"")));
+    assert_eq!(book_ids(&result), vec![4]);
+    let shelf_id = result.shelf.id;
+
+    // evaluated on request
+    tag_handler::add_to_book(pool.clone(), 1, 1, "poetry").unwrap();
+    book_handler::update(pool.clone(), 1, 1, &BookChanges { rating: Some(Some(4)), language_original: Some("en".to_string()), ..BookChanges::default() }).unwrap();
+    let mut response = srv.get(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    assert_eq!(book_ids(&result), vec![4, 1]);
+    let mut response = srv.get("/shelf").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: ShelvesCommandResult = response.json().await.unwrap();
+    assert_eq!(result.shelves[0].count, 2);
+
+    // the books of a smart shelf come from its query
+    let form = ShelfBookForm { book_id: 5, position: None };
+    let response = srv.post(format!("/shelf/{}/book", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let form = ShelfForm { query: Some("rating >= 4 AND".to_string()), ..shelf_form("Translated poetry") };
+    let response = srv.put(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    let form = ShelfForm { query: Some("tag:poetry AND NOT rating = null".to_string()), ..shelf_form("Translated poetry") };
+    let mut response = srv.put(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    // by title
+    assert_eq!(book_ids(&result), vec![5, 4, 1]);
+
+    // the negation of a comparison with a missing value is true
+    let form = ShelfForm { query: Some("tag:poetry AND NOT page_count > 100".to_string()), ..shelf_form("Translated poetry") };
+    let mut response = srv.put(format!("/shelf/{}", shelf_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    let result: ShelfCommandResult = response.json().await.unwrap();
+    assert_eq!(book_ids(&result), vec![5, 4, 1]);
+}
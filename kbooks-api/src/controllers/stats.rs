@@ -0,0 +1,39 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::repository::stats_handler;
+use kbooks_common::stats::Stats;
+
+use crate::controllers::session_user;
+
+// ---------------- Reading statistics ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct StatsQuery {
+    // all the readings when not given
+    year: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsCommandResult {
+    success: bool,
+    stats: Stats,
+    error: Option<String>
+}
+
+pub async fn stats(
+    session: Session,
+    query: web::Query<StatsQuery>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let stats = stats_handler::stats(config.pool.clone(), user.id, query.year)?;
+    let res = StatsCommandResult {success: true, stats, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,75 @@
+use actix_web::{web, test, App};
+use chrono::NaiveDate;
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::models::NewBook;
+use kbooks_common::repository::reading_handler;
+
+use crate::controllers::fixtures::{managed_state, test_book, insert_books, populate_two_users};
+
+use super::StatsCommandResult;
+
+fn translated_book(title: &str, pages: i32) -> NewBook {
+    NewBook {
+        author_lf: "Calvino, Italo".to_string(),
+        author_code: "calvinoitalo".to_string(),
+        language_original: "it".to_string(),
+        page_count: Some(pages),
+        dateacquired_stamp: Some(NaiveDate::from_ymd(2019, 1, 1).and_hms(0, 0, 0)),
+        ..test_book(1, title)
+    }
+}
+
+#[actix_rt::test]
+async fn test_stats() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        insert_books(conn, vec![translated_book("Le Baron perché", 300), translated_book("Si par une nuit d'hiver un voyageur", 250)]);
+    }
+    let day = |year, month, day| NaiveDate::from_ymd(year, month, day).and_hms(0, 0, 0);
+    reading_handler::finish(pool.clone(), 1, 1, day(2018, 6, 1), None).unwrap();
+    reading_handler::finish(pool.clone(), 1, 4, day(2019, 1, 11), None).unwrap();
+    reading_handler::finish(pool.clone(), 1, 5, day(2019, 2, 1), None).unwrap();
+    // abandoned readings and the readings of other users are not counted
+    reading_handler::start(pool.clone(), 1, 4, day(2019, 3, 1), None).unwrap();
+    reading_handler::abandon(pool.clone(), 1, 4, day(2019, 3, 2), None).unwrap();
+    reading_handler::finish(pool.clone(), 2, 2, day(2019, 1, 1), None).unwrap();
+    // read again
+    reading_handler::finish(pool.clone(), 1, 5, day(2020, 3, 1), None).unwrap();
+
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/stats").route( web::get().to(super::stats)))
+    });
+
+    let mut response = srv.get("/stats").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    let result: StatsCommandResult = response.json().await.unwrap();
+    assert_eq!(result.stats.books_read, 4);
+    assert_eq!(result.stats.by_year.len(), 3);
+
+    let mut response = srv.get("/stats?year=2019").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: StatsCommandResult = response.json().await.unwrap();
+    let stats = result.stats;
+    assert_eq!((stats.books_read, stats.pages_read), (2, 550));
+    assert_eq!(stats.by_month.iter().map(|period| period.month).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+    assert_eq!(stats.languages.translated, 2);
+    assert_eq!(stats.top_authors[0].code, "calvinoitalo");
+    assert_eq!(stats.top_authors[0].books, 2);
+    let delay = stats.acquisition_delay.unwrap();
+    assert_eq!((delay.books, delay.median_days), (2, 31));
+
+    // the first reading of the book was the year before
+    let mut response = srv.get("/stats?year=2020").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: StatsCommandResult = response.json().await.unwrap();
+    assert_eq!((result.stats.books_read, result.stats.pages_read), (1, 250));
+    assert_eq!(result.stats.acquisition_delay, None);
+}
@@ -0,0 +1,64 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+
+use kbooks_common::khnum::wiring::{Config, CommandResult};
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::repository::tag_handler;
+use kbooks_common::models::TagCount;
+
+use crate::controllers::session_user;
+
+// ---------------- Add / remove tags of a book ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagForm {
+    name: String,
+}
+
+pub async fn add(
+    session: Session,
+    book_id: web::Path<i32>,
+    tag_form: web::Form<TagForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let name = tag_handler::normalize(&tag_form.into_inner().name);
+    if name.is_empty() {
+        return Err(ServiceError::BadRequest("name: tag name is missing".to_string()));
+    }
+    tag_handler::add_to_book(config.pool.clone(), user.id, book_id.into_inner(), &name)?;
+    Ok(HttpResponse::Ok().json(CommandResult::success()))
+}
+
+pub async fn remove(
+    session: Session,
+    data: web::Path<(i32, String)>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    tag_handler::remove_from_book(config.pool.clone(), user.id, data.0, &data.1)?;
+    Ok(HttpResponse::Ok().json(CommandResult::success()))
+}
+
+// ---------------- Tag cloud ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagsCommandResult {
+    success: bool,
+    tags: Vec<TagCount>,
+    error: Option<String>
+}
+
+pub async fn cloud(
+    session: Session,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let tags = tag_handler::cloud(config.pool.clone(), user.id)?;
+    let res = TagsCommandResult {success: true, tags, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,72 @@
+use actix_web::{web, test, http, App};
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::{Config, DbPool};
+use kbooks_common::repository::book_handler::{self, BookFilters, ListOptions, SortKey, TagsMode};
+
+use crate::controllers::fixtures::{managed_state, test_book, insert_books, populate_two_users};
+
+use super::{TagForm, TagsCommandResult};
+
+fn tagged_titles(pool: DbPool, tags: &[&str], tags_mode: TagsMode) -> Vec<String> {
+    let options = ListOptions {
+        filters: BookFilters {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            tags_mode,
+            ..BookFilters::default()
+        },
+        sort: SortKey::Title,
+        ..ListOptions::default()
+    };
+    let (books, _total) = book_handler::list_page(pool, 1, &options).unwrap();
+    books.into_iter().map(|book| book.title).collect()
+}
+
+#[actix_rt::test]
+async fn test_tags() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+        insert_books(conn, vec![test_book(1, "Zazie dans le métro"), test_book(1, "La Disparition")]);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/book/{id}/tag").route( web::post().to(super::add)))
+            .service( web::resource("/book/{id}/tag/{name}").route( web::delete().to(super::remove)))
+            .service( web::resource("/tag").route( web::get().to(super::cloud)))
+    });
+
+    // books 4 and 5 are "Zazie dans le métro" and "La Disparition"
+    let tagging = vec![(1, "Novel"), (4, "novel "), (4, "Oulipo"), (5, "oulipo"), (5, "lipogram")];
+    for (book_id, name) in tagging {
+        let form = TagForm { name: name.to_string() };
+        let response = srv.post(format!("/book/{}/tag", book_id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+        assert!(response.status().is_success());
+    }
+
+    // Book of another user
+    let form = TagForm { name: "novel".to_string() };
+    let response = srv.post("/book/2/tag").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    //==== Tag cloud
+    let mut response = srv.get("/tag").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: TagsCommandResult = response.json().await.expect("Could not parse json"); 
+    let cloud: Vec<(&str, i64)> = result.tags.iter().map(|t| (t.name.as_str(), t.count)).collect();
+    assert_eq!(cloud, vec![("lipogram", 1), ("novel", 2), ("oulipo", 2)]);
+
+    //==== Filters
+    assert_eq!(tagged_titles(pool.clone(), &["oulipo", "novel"], TagsMode::Any), vec!["La Disparition", "Zazie dans le métro", "a title"]);
+    assert_eq!(tagged_titles(pool.clone(), &["oulipo", "novel"], TagsMode::All), vec!["Zazie dans le métro"]);
+
+    //==== Remove
+    let response = srv.delete("/book/4/tag/oulipo").timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(tagged_titles(pool.clone(), &["oulipo"], TagsMode::Any), vec!["La Disparition"]);
+}
@@ -0,0 +1,171 @@
+use actix_session::{Session};
+use actix_web::{web, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
+
+use kbooks_common::khnum::wiring::{Config, CommandResult};
+use kbooks_common::khnum::errors::ServiceError;
+
+use kbooks_common::isbn;
+use kbooks_common::repository::{book_handler, wish_handler};
+use kbooks_common::models::{BookDetails, NewWish, Wish, HIGHEST_PRIORITY, LOWEST_PRIORITY};
+
+use crate::controllers::{non_empty, session_user};
+
+const DEFAULT_PRIORITY: i32 = 3;
+
+// ---------------- Wishlist ------------
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WishesCommandResult {
+    success: bool,
+    // most wanted first
+    wishes: Vec<Wish>,
+    error: Option<String>
+}
+
+pub async fn list(
+    session: Session,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let wishes = wish_handler::list(config.pool.clone(), user.id)?;
+    let res = WishesCommandResult {success: true, wishes, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// ---------------- Add / update / delete a wish ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct WishForm {
+    title: String,
+    author: Option<String>,
+    isbn: Option<String>,
+    // from 1 (most wanted) to 5, 3 when not given
+    priority: Option<i32>,
+    notes: Option<String>,
+    // "12.50" or "12,50"
+    target_price: Option<String>,
+    where_to_buy: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WishCommandResult {
+    success: bool,
+    wish: Wish,
+    error: Option<String>
+}
+
+// Price in cents
+fn form_price(raw: &Option<String>) -> Result<Option<i32>, ServiceError> {
+    let raw = match non_empty(raw) {
+        Some(raw) => raw.replace(',', "."),
+        None => return Ok(None),
+    };
+    let invalid = || ServiceError::BadRequest("target_price: must be an amount such as 12.50".to_string());
+    // "-0.50" would parse as 0 units and 50 cents
+    if !raw.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return Err(invalid());
+    }
+    let mut parts = raw.splitn(2, '.');
+    let units: i32 = parts.next().unwrap_or("").parse().map_err(|_| invalid())?;
+    let cents = match parts.next() {
+        None => 0,
+        Some(cents) if cents.len() == 1 => cents.parse::<i32>().map_err(|_| invalid())? * 10,
+        Some(cents) if cents.len() == 2 => cents.parse::<i32>().map_err(|_| invalid())?,
+        Some(_) => return Err(invalid()),
+    };
+    units.checked_mul(100).and_then(|price| price.checked_add(cents)).map(Some).ok_or_else(invalid)
+}
+
+fn form_wish(user_id: i32, wish_form: &WishForm) -> Result<NewWish, ServiceError> {
+    let title = wish_form.title.trim().to_string();
+    if title.is_empty() {
+        return Err(ServiceError::BadRequest("title: title is missing".to_string()));
+    }
+    let isbn = match non_empty(&wish_form.isbn) {
+        Some(raw) => isbn::validate(&raw).map_err(|error| ServiceError::BadRequest(format!("isbn: {}", error)))?,
+        None => String::new(),
+    };
+    let priority = wish_form.priority.unwrap_or(DEFAULT_PRIORITY);
+    if priority < HIGHEST_PRIORITY || priority > LOWEST_PRIORITY {
+        return Err(ServiceError::BadRequest(format!("priority: must be between {} and {}", HIGHEST_PRIORITY, LOWEST_PRIORITY)));
+    }
+    Ok(NewWish {
+        user_id,
+        title,
+        author: non_empty(&wish_form.author).unwrap_or_default(),
+        isbn,
+        priority,
+        notes: non_empty(&wish_form.notes),
+        target_price: form_price(&wish_form.target_price)?,
+        where_to_buy: non_empty(&wish_form.where_to_buy),
+        created_at: Utc::now().naive_utc(),
+    })
+}
+
+pub async fn create(
+    session: Session,
+    wish_form: web::Form<WishForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let wish = wish_handler::add(config.pool.clone(), form_wish(user.id, &wish_form)?)?;
+    let res = WishCommandResult {success: true, wish, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn update(
+    session: Session,
+    wish_id: web::Path<i32>,
+    wish_form: web::Form<WishForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let wish = form_wish(user.id, &wish_form)?;
+    let wish = wish_handler::update(config.pool.clone(), user.id, wish_id.into_inner(), &wish)?;
+    let res = WishCommandResult {success: true, wish, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn delete(
+    session: Session,
+    wish_id: web::Path<i32>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    wish_handler::delete(config.pool.clone(), user.id, wish_id.into_inner())?;
+    Ok(HttpResponse::Ok().json(CommandResult::success()))
+}
+
+// ---------------- Mark a wish acquired ------------
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct AcquireForm {
+    // now when not given
+    date: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AcquireCommandResult {
+    success: bool,
+    // the new book of the library
+    book: BookDetails,
+    error: Option<String>
+}
+
+pub async fn acquire(
+    session: Session,
+    wish_id: web::Path<i32>,
+    acquire_form: web::Form<AcquireForm>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ServiceError> {
+    let user = session_user(&session)?;
+    let date = acquire_form.date.unwrap_or_else(|| Utc::now().naive_utc());
+    let book = wish_handler::acquire(config.pool.clone(), user.id, wish_id.into_inner(), date)?;
+    let book = book_handler::with_details(config.pool.clone(), vec![book])?.remove(0);
+    let res = AcquireCommandResult {success: true, book, error: None};
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,108 @@
+use actix_web::{web, test, http, App};
+use chrono::NaiveDate;
+use dotenv::dotenv;
+use std::time::Duration;
+
+use kbooks_common::khnum::wiring::Config;
+use kbooks_common::repository::book_handler;
+
+use crate::controllers::fixtures::{managed_state, populate_two_users};
+
+use super::{AcquireCommandResult, AcquireForm, WishCommandResult, WishForm, WishesCommandResult};
+
+fn wish_form(title: &str, priority: i32) -> WishForm {
+    WishForm {
+        title: title.to_string(),
+        author: Some("Perec, Georges".to_string()),
+        isbn: Some("2-07-036822-X".to_string()),
+        priority: Some(priority),
+        notes: Some(" first edition ".to_string()),
+        target_price: Some("12,5".to_string()),
+        where_to_buy: Some("".to_string()),
+    }
+}
+
+#[test]
+fn test_form_price() {
+    assert_eq!(super::form_price(&Some("12".to_string())).unwrap(), Some(1200));
+    assert_eq!(super::form_price(&Some("12.05".to_string())).unwrap(), Some(1205));
+    assert_eq!(super::form_price(&Some(" ".to_string())).unwrap(), None);
+    for raw in &["-1", "1.234", "twelve", "12.", "1.-5"] {
+        assert!(super::form_price(&Some(raw.to_string())).is_err(), "{} should be refused", raw);
+    }
+}
+
+#[actix_rt::test]
+async fn test_wishlist() {
+    dotenv().ok();
+    let pool = kbooks_common::khnum::wiring::test_conn_init();
+    {
+        let conn = &pool.get().unwrap();
+        populate_two_users(conn);
+    }
+    let app_pool = pool.clone();
+    let srv = test::start( move || {
+        App::new()
+            .app_data(managed_state())
+            .data(Config {pool: app_pool.clone(), front_url: String::from("http://dummy")})
+            .service( web::resource("/wish")
+                .route( web::get().to(super::list))
+                .route( web::post().to(super::create)))
+            .service( web::resource("/wish/{id}")
+                .route( web::put().to(super::update))
+                .route( web::delete().to(super::delete)))
+            .service( web::resource("/wish/{id}/acquire").route( web::post().to(super::acquire)))
+    });
+
+    //==== Wishlist
+    let mut response = srv.post("/wish").timeout(Duration::new(15, 0)).send_form(&wish_form("La Disparition", 4)).await.unwrap();
+    assert!(response.status().is_success());
+    let result: WishCommandResult = response.json().await.unwrap();
+    let wish = result.wish;
+    assert_eq!((wish.isbn.as_str(), wish.notes.as_deref(), wish.target_price, wish.where_to_buy), ("207036822X", Some("first edition"), Some(1250), None));
+
+    let mut response = srv.post("/wish").timeout(Duration::new(15, 0)).send_form(&wish_form("W", 1)).await.unwrap();
+    let result: WishCommandResult = response.json().await.unwrap();
+    let most_wanted = result.wish;
+
+    for form in vec![
+        wish_form(" ", 1),
+        wish_form("Les Choses", 6),
+        WishForm { isbn: Some("1234".to_string()), ..wish_form("Les Choses", 1) },
+        WishForm { target_price: Some("cheap".to_string()), ..wish_form("Les Choses", 1) },
+        WishForm { target_price: Some("-0.50".to_string()), ..wish_form("Les Choses", 1) },
+    ] {
+        let response = srv.post("/wish").timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    let mut response = srv.get("/wish").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: WishesCommandResult = response.json().await.unwrap();
+    assert_eq!(result.wishes.iter().map(|wish| wish.id).collect::<Vec<i32>>(), vec![most_wanted.id, wish.id]);
+
+    let form = WishForm { target_price: None, ..wish_form("La Disparition", 2) };
+    let mut response = srv.put(format!("/wish/{}", wish.id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    let result: WishCommandResult = response.json().await.unwrap();
+    assert_eq!((result.wish.priority, result.wish.target_price), (2, None));
+
+    //==== Acquisition
+    let form = AcquireForm { date: Some(NaiveDate::from_ymd(2020, 5, 1).and_hms(0, 0, 0)) };
+    let mut response = srv.post(format!("/wish/{}/acquire", wish.id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert!(response.status().is_success());
+    let result: AcquireCommandResult = response.json().await.unwrap();
+    let book = result.book;
+    assert_eq!((book.book.title.as_str(), book.book.author_lf.as_str()), ("La Disparition", "Perec, Georges"));
+    assert_eq!(book.book.dateacquired_stamp, form.date);
+    assert_eq!(book.contributors.len(), 1);
+    assert!(book_handler::get(pool.clone(), 1, book.book.id).is_ok());
+
+    // already acquired
+    let response = srv.post(format!("/wish/{}/acquire", wish.id)).timeout(Duration::new(15, 0)).send_form(&form).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    let mut response = srv.get("/wish").timeout(Duration::new(15, 0)).send().await.unwrap();
+    let result: WishesCommandResult = response.json().await.unwrap();
+    assert_eq!(result.wishes.len(), 1);
+
+    let response = srv.delete(format!("/wish/{}", most_wanted.id)).timeout(Duration::new(15, 0)).send().await.unwrap();
+    assert!(response.status().is_success());
+}
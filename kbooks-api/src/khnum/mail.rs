@@ -0,0 +1,41 @@
+use lettre::Transport;
+use lettre::sendmail::SendmailTransport;
+use lettre_email::Email;
+
+use kbooks_common::khnum::wiring::CommandResult;
+
+// Texts written by the users, to be included in an html mail
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Sends an html mail through sendmail (SENDMAIL command, /usr/sbin/sendmail by default)
+pub fn send_mail(recipient: &str, subject: &str, html_body: String) -> CommandResult {
+    let sending_email = std::env::var("SENDING_EMAIL_ADDRESS")
+        .expect("SENDING_EMAIL_ADDRESS must be set");
+    let email = Email::builder()
+        .from((sending_email, "khnum"))
+        .to(recipient)
+        .subject(subject)
+        .html(html_body)
+        .build();
+    let email = match email {
+        Ok(email) => email,
+        Err(error) => return CommandResult::error(format!("Error building mail. {:#?}", error)),
+    };
+
+    // We don't send the mail in test environment
+    if cfg!(test) {
+        return CommandResult::success();
+    }
+
+    let sendmail = dotenv::var("SENDMAIL").unwrap_or_else(|_| "/usr/sbin/sendmail".to_string());
+    let mut mailer = SendmailTransport::new_with_command(sendmail);
+    match mailer.send(email.into()) {
+        Ok(_res) => CommandResult::success(),
+        Err(error) => CommandResult::error(format!("Error sending mail. {:#?}", error)),
+    }
+}
@@ -7,18 +7,13 @@ use futures::future::{Future, err};
 
 use url::form_urlencoded;
 
-use lettre_email::Email;
-use lettre::{SmtpClient, Transport};
-use lettre::file::FileTransport;
-use lettre::smtp::authentication::{Credentials, Mechanism};
-use lettre::sendmail::SendmailTransport;
-
 use kbooks_common::khnum::wiring::{DbPool, Config, CommandResult, make_front_url};
 use kbooks_common::khnum::errors::ServiceError;
 
 use kbooks_common::khnum::users::repository::user_handler;
 use kbooks_common::khnum::users::models::{SlimUser, User};
 use kbooks_common::khnum::users::operations::check_existence;
+use crate::khnum::mail::send_mail;
 use crate::khnum::users::utils::{hash_password, to_url, from_url};
 
 use actix_i18n::I18n;
@@ -161,8 +156,6 @@ fn make_register_link(base_url: &String, username: &String, hpassword: &String,
 fn send_confirmation(catalog: &Catalog, username: String, hpassword: String, email: String, expires_at: NaiveDateTime) -> CommandResult {
     println!("{}{}", email, expires_at.timestamp());
 
-    let sending_email = std::env::var("SENDING_EMAIL_ADDRESS")
-        .expect("SENDING_EMAIL_ADDRESS must be set");
     let base_url = dotenv::var("BASE_URL").unwrap_or_else(|_| "localhost".to_string());
     let url = make_register_link(&base_url, &username, &hpassword, &email, expires_at.timestamp());
     let recipient = &email[..];
@@ -180,39 +173,7 @@ fn send_confirmation(catalog: &Catalog, username: String, hpassword: String, ema
     // panic!("{}", email_body);
     // println!("{}", recipient);
 
-    let email = Email::builder()
-        .from((sending_email, "khnum"))
-        .to(recipient)
-        .subject("You have been invited to join khnum")
-        .html(email_body)
-        .build();
-    assert!(email.is_ok());
-
-    // let smtp_login = dotenv::var("SMTP_LOGIN").unwrap_or_else(|_| "user".to_string());
-    // let smtp_pass = dotenv::var("SMTP_PASSWORD").unwrap_or_else(|_| "password".to_string());
-    // let smtp_server = dotenv::var("SMTP_SERVER").unwrap_or_else(|_| "smtp.localhost".to_string()); 
-    // let creds = Credentials::new( smtp_login, smtp_pass );
-    // let mut mailer = SmtpClient::new_simple(&smtp_server)
-    //     .unwrap()
-    //     .credentials(creds)
-    //     .transport();
-
-    // We don't send the mail in test environment
-    #[cfg(test)]
-    return CommandResult::success();
-
-    // let mut mailer = SmtpClient::new_unencrypted_localhost().unwrap().transport();
-    let sendmail = dotenv::var("SENDMAIL").unwrap_or_else(|_| "/usr/sbin/sendmail".to_string()); 
-    let mut mailer = SendmailTransport::new_with_command(sendmail);
-
-    let result = mailer.send(email.unwrap().into());
-    match result {
-        Ok(_res) => CommandResult::success(),
-        Err(error) => {
-            // println!("error \n {:#?}", error);
-            CommandResult::error(format!("Error sending mail. {:#?}", error))
-        }
-    }
+    send_mail(recipient, "You have been invited to join khnum", email_body)
 }
 
 #[cfg(test)]
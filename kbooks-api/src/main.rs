@@ -9,7 +9,7 @@
 use actix::prelude::*;
 use actix_files as fs;
 use actix_identity::{CookieIdentityPolicy, IdentityService};
-use actix_web::middleware::Logger;
+use actix_web::middleware::{DefaultHeaders, Logger};
 use actix_web::{web, App, HttpServer};
 use actix_session::{CookieSession, Session};
 // use chrono::Duration;
@@ -19,6 +19,10 @@ use actix_i18n::Translations;
 use gettext_macros::{compile_i18n, include_i18n, init_i18n};
 
 use kbooks_common::khnum::wiring;
+use kbooks_common::cover::CoverStore;
+use kbooks_common::metadata::{openlibrary, MetadataProviders, OpenLibrary};
+use kbooks_common::repository::data_migration_handler;
+use kbooks_common::storage::LocalStorage;
 
 init_i18n!("khnum", en, fr); // Put this before modules containing messages to be translated
 
@@ -48,6 +52,13 @@ async fn main() -> std::io::Result<()> {
     let front_url = dotenv::var("FRONT_URL").expect("FRONT_URL must be set");
     let db_url = dotenv::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let pool = wiring::db_init(db_url);
+    for (step, changed) in data_migration_handler::run(pool.clone()).expect("Failed to run data migrations") {
+        println!("Data migration {} : {} rows changed", step, changed);
+    }
+    let covers_dir = dotenv::var("COVERS_DIR").unwrap_or_else(|_| "./covers/".to_string());
+    std::fs::create_dir_all(&covers_dir)?;
+    let openlibrary_url = dotenv::var("OPENLIBRARY_URL").unwrap_or_else(|_| openlibrary::DEFAULT_BASE_URL.to_string());
+    let openlibrary_covers_url = dotenv::var("OPENLIBRARY_COVERS_URL").unwrap_or_else(|_| openlibrary::DEFAULT_COVERS_URL.to_string());
 
     HttpServer::new(move || {
         // secret is a random minimum 32 bytes long base 64 string
@@ -59,6 +70,10 @@ async fn main() -> std::io::Result<()> {
                 pool: pool.clone(),
                 front_url: front_url.clone()
             })
+            .data(CoverStore::new(Box::new(LocalStorage::new(covers_dir.clone()))))
+            .data(MetadataProviders::new(vec![
+                Box::new(OpenLibrary::new(&openlibrary_url, &openlibrary_covers_url)),
+            ]))
             .app_data(managed_state())
             .wrap(Logger::default())
             .wrap(CookieSession::signed(secret.as_bytes()).secure(false))
@@ -80,6 +95,136 @@ async fn main() -> std::io::Result<()> {
                             .route(web::post().to(controllers::book::create))
                             .route( web::get().to(controllers::book::list))
                     )
+                    .service( web::resource("/book/export")
+                            .route( web::get().to(controllers::export::export))
+                    )
+                    .service( web::resource("/book/isbn")
+                            .route( web::post().to(controllers::lookup::create))
+                    )
+                    .service( web::resource("/book/import")
+                            .route( web::post().to(controllers::import::import))
+                    )
+                    .service( web::resource("/book/search")
+                            .route( web::get().to(controllers::book::search))
+                    )
+                    .service( web::resource("/book/{id}")
+                            .route( web::get().to(controllers::book::get))
+                            .route( web::put().to(controllers::book::update))
+                            .route( web::patch().to(controllers::book::patch))
+                            .route( web::delete().to(controllers::book::delete))
+                    )
+                    .service( web::resource("/book/{id}/tag")
+                            .route( web::post().to(controllers::tag::add))
+                    )
+                    .service( web::resource("/book/{id}/tag/{name}")
+                            .route( web::delete().to(controllers::tag::remove))
+                    )
+                    .service( web::resource("/book/{id}/cover")
+                            .route( web::get().to(controllers::cover::get))
+                            .route( web::post().to(controllers::cover::upload))
+                            .route( web::delete().to(controllers::cover::delete))
+                    )
+                    .service( web::resource("/book/{id}/loan")
+                            .route( web::get().to(controllers::loan::list))
+                    )
+                    .service( web::resource("/book/{id}/loan/lend")
+                            .route( web::post().to(controllers::loan::lend))
+                    )
+                    .service( web::resource("/book/{id}/loan/return")
+                            .route( web::post().to(controllers::loan::give_back))
+                    )
+                    .service( web::resource("/book/{id}/progress")
+                            .route( web::post().to(controllers::reading::progress))
+                    )
+                    .service( web::resource("/book/{id}/reading")
+                            .route( web::get().to(controllers::reading::list))
+                    )
+                    .service( web::resource("/book/{id}/reading/start")
+                            .route( web::post().to(controllers::reading::start))
+                    )
+                    .service( web::resource("/book/{id}/reading/finish")
+                            .route( web::post().to(controllers::reading::finish))
+                    )
+                    .service( web::resource("/book/{id}/reading/abandon")
+                            .route( web::post().to(controllers::reading::abandon))
+                    )
+                    .service( web::resource("/book/{id}/reading/{reading_id}")
+                            .route( web::delete().to(controllers::reading::delete))
+                    )
+                    .service( web::resource("/borrow")
+                            .route( web::get().to(controllers::borrow::list))
+                            .route( web::post().to(controllers::borrow::create))
+                    )
+                    .service( web::resource("/borrow/{id}/accept")
+                            .route( web::post().to(controllers::borrow::accept))
+                    )
+                    .service( web::resource("/borrow/{id}/decline")
+                            .route( web::post().to(controllers::borrow::decline))
+                    )
+                    .service( web::resource("/borrow/{id}/return")
+                            .route( web::post().to(controllers::borrow::give_back))
+                    )
+                    .service( web::resource("/friend")
+                            .route( web::get().to(controllers::friend::list))
+                            .route( web::post().to(controllers::friend::add))
+                    )
+                    .service( web::resource("/friend/{id}")
+                            .route( web::delete().to(controllers::friend::remove))
+                    )
+                    .service( web::resource("/goal")
+                            .route( web::get().to(controllers::goal::list))
+                            .route( web::post().to(controllers::goal::set))
+                    )
+                    .service( web::resource("/goal/{id}")
+                            .route( web::delete().to(controllers::goal::delete))
+                    )
+                    .service( web::resource("/language")
+                            .route( web::get().to(controllers::language::list))
+                    )
+                    .service( web::resource("/loan")
+                            .route( web::get().to(controllers::loan::lent))
+                    )
+                    .service( web::resource("/loan/overdue")
+                            .route( web::get().to(controllers::loan::overdue))
+                    )
+                    .service( web::resource("/lookup/isbn/{isbn}")
+                            .route( web::get().to(controllers::lookup::isbn))
+                    )
+                    .service( web::resource("/shelf")
+                            .route( web::get().to(controllers::shelf::list))
+                            .route( web::post().to(controllers::shelf::create))
+                    )
+                    .service( web::resource("/shelf/{id}")
+                            .route( web::get().to(controllers::shelf::get))
+                            .route( web::put().to(controllers::shelf::update))
+                            .route( web::delete().to(controllers::shelf::delete))
+                    )
+                    .service( web::resource("/shelf/{id}/book")
+                            .route( web::post().to(controllers::shelf::add_book))
+                    )
+                    .service( web::resource("/shelf/{id}/book/{book_id}")
+                            .route( web::delete().to(controllers::shelf::remove_book))
+                    )
+                    .service( web::resource("/shelf/{id}/order")
+                            .route( web::put().to(controllers::shelf::reorder))
+                    )
+                    .service( web::resource("/stats")
+                            .route( web::get().to(controllers::stats::stats))
+                    )
+                    .service( web::resource("/tag")
+                            .route( web::get().to(controllers::tag::cloud))
+                    )
+                    .service( web::resource("/wish")
+                            .route( web::get().to(controllers::wish::list))
+                            .route( web::post().to(controllers::wish::create))
+                    )
+                    .service( web::resource("/wish/{id}")
+                            .route( web::put().to(controllers::wish::update))
+                            .route( web::delete().to(controllers::wish::delete))
+                    )
+                    .service( web::resource("/wish/{id}/acquire")
+                            .route( web::post().to(controllers::wish::acquire))
+                    )
             )
             .service( web::scope("/register") // everything under '/register/' route
                   .service( web::resource("/request").route(
@@ -96,6 +241,11 @@ async fn main() -> std::io::Result<()> {
             // .service( web::resource("/hello").route(
             //         web::get().to(hello)
             // ))
+            // covers of the local storage : a new cover gets a new name, the files never change
+            .service( web::scope(controllers::cover::COVERS_PATH)
+                  .wrap(DefaultHeaders::new().header("Cache-Control", "public, max-age=31536000, immutable"))
+                  .service(fs::Files::new("/", &covers_dir))
+            )
             // serve static files
             .service(fs::Files::new("/", "./static/").index_file("index.html"))
     })
@@ -0,0 +1,162 @@
+use chrono::{Datelike, Utc};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+
+use kbooks_common::khnum::users::repository::user_handler;
+use kbooks_common::khnum::wiring::DbPool;
+use kbooks_common::language;
+use kbooks_common::models::{GoalKind, NewGoal};
+use kbooks_common::repository::goal_handler;
+
+use crate::book::db_pool;
+
+pub const name: &str = "goal";
+
+fn user_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("user")
+        .long("user")
+        .value_name("LOGIN")
+        .help("owner of the goals")
+        .required(true)
+}
+
+fn year_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("year")
+        .long("year")
+        .value_name("YEAR")
+        .help(help)
+}
+
+pub fn add_command<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b>  {
+    app.subcommand(
+    SubCommand::with_name(name)
+        .about("yearly reading goals")
+        .subcommand(
+            SubCommand::with_name("set").about("set the number of books or pages to read in a year")
+            .arg(user_arg())
+            .arg(year_arg("year of the goal, the current one if missing"))
+            .arg(
+                Arg::with_name("books")
+                .long("books")
+                .value_name("COUNT")
+                .help("number of books to read")
+            )
+            .arg(
+                Arg::with_name("pages")
+                .long("pages")
+                .value_name("COUNT")
+                .help("number of pages to read")
+            )
+            .group(ArgGroup::with_name("target").args(&["books", "pages"]).required(true))
+            .arg(
+                Arg::with_name("language")
+                .long("language")
+                .value_name("LANGUAGE")
+                .help("only count the books read in this language")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("list").about("show the goals and their progress")
+            .arg(user_arg())
+            .arg(year_arg("year of the goals, all years if missing"))
+        )
+        .subcommand(
+            SubCommand::with_name("delete").about("delete a goal")
+            .arg(
+                Arg::with_name("ID")
+                .help("id of the goal, as shown by list")
+                .required(true)
+            )
+            .arg(user_arg())
+        ))
+}
+
+fn user_id(pool: DbPool, matches: &ArgMatches) -> Result<i32, String> {
+    let login = matches.value_of("user").unwrap();
+    user_handler::get_by_login(pool, login).map(|user| user.id).map_err(|_| format!("Unknown user {}", login))
+}
+
+fn year(matches: &ArgMatches) -> Result<Option<i32>, String> {
+    matches.value_of("year")
+        .map(|year| year.parse().map_err(|_| format!("Invalid year {}", year)))
+        .transpose()
+}
+
+// Set a goal
+fn set(matches: &ArgMatches) -> Result<(), String> {
+    let pool = db_pool();
+    let user_id = user_id(pool.clone(), matches)?;
+
+    let now = Utc::now().naive_utc();
+    let year = year(matches)?.unwrap_or_else(|| now.year());
+    let (kind, target) = match matches.value_of("books") {
+        Some(count) => (GoalKind::Books, count),
+        None => (GoalKind::Pages, matches.value_of("pages").unwrap()),
+    };
+    let target: i32 = match target.parse() {
+        Ok(target) if target > 0 => target,
+        _ => return Err(format!("Invalid count {} : it must be positive", target)),
+    };
+    let language = match matches.value_of("language") {
+        Some(raw) => language::normalize(raw).map_err(|_| format!("Unknown language {}", raw))?,
+        None => String::new(),
+    };
+    let language = Some(language).filter(|code| !code.is_empty());
+    let goal = NewGoal { user_id, year, kind: kind.as_str().to_string(), target, language, created_at: now };
+    let goal = goal_handler::set(pool, goal).map_err(|error| format!("Error when saving the goal : {}", error))?;
+    println!("Goal {} set", goal.id);
+    Ok(())
+}
+
+// Goals and their progress
+fn list(matches: &ArgMatches) -> Result<(), String> {
+    let pool = db_pool();
+    let user_id = user_id(pool.clone(), matches)?;
+
+    let year = year(matches)?;
+    let goals = goal_handler::list(pool.clone(), user_id, year).map_err(|error| format!("Error when reading goals : {}", error))?;
+    let today = Utc::now().naive_utc().date();
+    let goals = goal_handler::with_progress(pool, user_id, goals, today).map_err(|error| format!("Error when reading books : {}", error))?;
+    for progress in goals {
+        let goal = &progress.goal;
+        let projection = if progress.on_track {
+            String::from("on track")
+        } else {
+            format!("behind by {}", progress.behind_by)
+        };
+        println!("{:>5} {} {}/{} {}{} ({}%) {}",
+                 goal.id,
+                 goal.year,
+                 progress.done,
+                 goal.target,
+                 goal.kind,
+                 goal.language.as_ref().map(|language| format!(" in {}", language)).unwrap_or_default(),
+                 progress.percent,
+                 projection);
+    }
+    Ok(())
+}
+
+// Delete a goal
+fn delete(matches: &ArgMatches) -> Result<(), String> {
+    let pool = db_pool();
+    let user_id = user_id(pool.clone(), matches)?;
+
+    let raw_id = matches.value_of("ID").unwrap();
+    let goal_id: i32 = raw_id.parse().map_err(|_| format!("Invalid id {}", raw_id))?;
+    goal_handler::delete(pool, user_id, goal_id).map_err(|_| format!("Unknown goal {}", goal_id))?;
+    println!("Goal {} deleted", goal_id);
+    Ok(())
+}
+
+pub fn actions(matches: &ArgMatches) {
+    let result = match matches.subcommand() {
+        ("set", Some(matches)) => set(matches),
+        ("list", Some(matches)) => list(matches),
+        ("delete", Some(matches)) => delete(matches),
+        _ => Ok(()),
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
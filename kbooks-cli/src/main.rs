@@ -1,5 +1,7 @@
 use clap::{Arg, App, SubCommand};
 
+mod book;
+mod goal;
 mod user;
 
 
@@ -26,12 +28,10 @@ fn main() {
                          .help("print debug information verbosely")));
 
     app = user::add_command(app);
+    app = book::add_command(app);
+    app = goal::add_command(app);
     let matches = app.get_matches();
 
-    // Gets a value for config if supplied by user, or defaults to "default.conf"
-    let config = matches.value_of("config").unwrap_or("default.conf");
-    println!("Value for config: {}", config);
-
     // You can handle information about subcommands by requesting their matches by name
     // (as below), requesting just the name used, or both at the same time
     if let Some(matches) = matches.subcommand_matches("test") {
@@ -46,4 +46,12 @@ fn main() {
         user::actions(user_matches);
     }
 
+    if let Some(book_matches) = matches.subcommand_matches(book::name) {
+        book::actions(book_matches);
+    }
+
+    if let Some(goal_matches) = matches.subcommand_matches(goal::name) {
+        goal::actions(goal_matches);
+    }
+
 }
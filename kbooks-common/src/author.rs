@@ -0,0 +1,145 @@
+// Author names and LibraryThing-like author codes
+
+// Name particles kept with the last name ("Le Guin", "de Beauvoir")
+const PARTICLES: &[&str] = &[
+    "da", "de", "del", "della", "der", "des", "di", "du", "la", "le", "van", "von",
+];
+
+// Normalizes an author name to "Last, First".
+// "Jacques Roubaud", " Roubaud ,Jacques " and "Roubaud, Jacques" all give "Roubaud, Jacques".
+pub fn normalize_name(name: &str) -> String {
+    let name = name.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if let Some(pos) = name.find(',') {
+        let last = name[..pos].trim();
+        let first = name[pos + 1..].trim();
+        if first.is_empty() {
+            return last.to_string();
+        }
+        return format!("{}, {}", last, first);
+    }
+
+    let words: Vec<&str> = name.split(' ').filter(|w| !w.is_empty()).collect();
+    if words.len() < 2 {
+        return name;
+    }
+    let mut last_start = words.len() - 1;
+    while last_start > 1 && PARTICLES.contains(&words[last_start - 1].to_lowercase().as_str()) {
+        last_start -= 1;
+    }
+    format!("{}, {}", words[last_start..].join(" "), words[..last_start].join(" "))
+}
+
+// "Last, First" back to "First Last", as in the Goodreads additional authors
+pub fn display_name(normalized_name: &str) -> String {
+    match normalized_name.find(',') {
+        Some(pos) => format!("{} {}", normalized_name[pos + 1..].trim(), normalized_name[..pos].trim()),
+        None => normalized_name.to_string(),
+    }
+}
+
+// Code derived from a normalized name : lowercase ascii letters and digits of the last name followed by the first name.
+// "Roubaud, Jacques" gives "roubaudjacques"
+pub fn base_code(normalized_name: &str) -> String {
+    let code: String = fold_accents(normalized_name)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if code.is_empty() {
+        String::from("unknown")
+    } else {
+        code
+    }
+}
+
+// First code not already taken : base, base-2, base-3...
+pub fn unique_code(base: &str, taken: &[String]) -> String {
+    if !taken.iter().any(|code| code == base) {
+        return base.to_string();
+    }
+    let mut n = 2;
+    loop {
+        let candidate = format!("{}-{}", base, n);
+        if !taken.contains(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+// Replaces latin accented letters by their ascii equivalent
+pub fn fold_accents(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => folded.push('a'),
+            'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => folded.push('A'),
+            'ç' | 'ć' | 'č' => folded.push('c'),
+            'Ç' | 'Ć' | 'Č' => folded.push('C'),
+            'ď' | 'đ' => folded.push('d'),
+            'Ď' | 'Đ' => folded.push('D'),
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => folded.push('e'),
+            'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ę' | 'Ě' => folded.push('E'),
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => folded.push('i'),
+            'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' => folded.push('I'),
+            'ł' => folded.push('l'),
+            'Ł' => folded.push('L'),
+            'ñ' | 'ń' | 'ň' => folded.push('n'),
+            'Ñ' | 'Ń' | 'Ň' => folded.push('N'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => folded.push('o'),
+            'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => folded.push('O'),
+            'ř' => folded.push('r'),
+            'Ř' => folded.push('R'),
+            'ś' | 'š' | 'ş' => folded.push('s'),
+            'Ś' | 'Š' | 'Ş' => folded.push('S'),
+            'ť' | 'ţ' => folded.push('t'),
+            'Ť' | 'Ţ' => folded.push('T'),
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => folded.push('u'),
+            'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' => folded.push('U'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'Ý' | 'Ÿ' => folded.push('Y'),
+            'ź' | 'ż' | 'ž' => folded.push('z'),
+            'Ź' | 'Ż' | 'Ž' => folded.push('Z'),
+            'æ' => folded.push_str("ae"),
+            'Æ' => folded.push_str("AE"),
+            'œ' => folded.push_str("oe"),
+            'Œ' => folded.push_str("OE"),
+            'ß' => folded.push_str("ss"),
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_name("Roubaud, Jacques"), "Roubaud, Jacques");
+        assert_eq!(normalize_name("  Roubaud ,Jacques "), "Roubaud, Jacques");
+        assert_eq!(normalize_name("Jacques Roubaud"), "Roubaud, Jacques");
+        assert_eq!(normalize_name("Ursula K. Le Guin"), "Le Guin, Ursula K.");
+        assert_eq!(normalize_name("Simone de Beauvoir"), "de Beauvoir, Simone");
+        assert_eq!(normalize_name("Homère"), "Homère");
+        assert_eq!(normalize_name("Queneau,"), "Queneau");
+        assert_eq!(display_name("Le Guin, Ursula K."), "Ursula K. Le Guin");
+        assert_eq!(display_name("Homère"), "Homère");
+    }
+
+    #[test]
+    fn codes() {
+        assert_eq!(base_code("Roubaud, Jacques"), "roubaudjacques");
+        assert_eq!(base_code("Pérec, Georges"), "perecgeorges");
+        assert_eq!(base_code("Œdipe"), "oedipe");
+        assert_eq!(base_code("???"), "unknown");
+    }
+
+    #[test]
+    fn unique_codes() {
+        let taken = vec![String::from("dumasalexandre"), String::from("dumasalexandre-2")];
+        assert_eq!(unique_code("dumasalexandre", &taken), "dumasalexandre-3");
+        assert_eq!(unique_code("queneauraymond", &taken), "queneauraymond");
+    }
+}
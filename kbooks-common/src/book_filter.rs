@@ -0,0 +1,497 @@
+// Filter expressions saved by smart shelves, for example
+//   language_original != language_main AND rating >= 4 AND tag:poetry
+// Comparisons are combined with AND, OR, NOT and parentheses, AND binding tighter than OR.
+// Values are numbers, double quoted texts, bare words or null; the keywords are case insensitive.
+// `~` tests if a text contains a value, ignoring case.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::language;
+use crate::models::ReadingStatus;
+use crate::repository::tag_handler;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Field {
+    Title,
+    Author,
+    Isbn,
+    LanguageMain,
+    LanguageSecondary,
+    LanguageOriginal,
+    Status,
+    Review,
+    Rating,
+    PageCount,
+    // publication year
+    Year,
+}
+
+const FIELDS: &[(&str, Field)] = &[
+    ("title", Field::Title),
+    ("author", Field::Author),
+    ("isbn", Field::Isbn),
+    ("language_main", Field::LanguageMain),
+    ("language_secondary", Field::LanguageSecondary),
+    ("language_original", Field::LanguageOriginal),
+    ("status", Field::Status),
+    ("review", Field::Review),
+    ("rating", Field::Rating),
+    ("page_count", Field::PageCount),
+    ("year", Field::Year),
+];
+
+impl Field {
+    pub fn name(self) -> &'static str {
+        FIELDS.iter().find(|(_name, field)| *field == self).map(|(name, _field)| *name).unwrap()
+    }
+
+    fn from_name(name: &str) -> Option<Field> {
+        FIELDS.iter().find(|(field_name, _field)| field_name.eq_ignore_ascii_case(name)).map(|(_name, field)| *field)
+    }
+
+    pub fn is_numeric(self) -> bool {
+        match self {
+            Field::Rating | Field::PageCount | Field::Year => true,
+            _ => false,
+        }
+    }
+
+    fn is_language(self) -> bool {
+        match self {
+            Field::LanguageMain | Field::LanguageSecondary | Field::LanguageOriginal => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operand {
+    Field(Field),
+    Text(String),
+    Number(i32),
+    Null,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Operand),
+    // normalized tag name
+    Tag(String),
+}
+
+// ---------------- Tokens -------------
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Op(Op),
+    Word(String),
+    Quoted(String),
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"()=!<>~\"".contains(c)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => { chars.next(); }
+            '(' => { chars.next(); tokens.push(Token::Open); }
+            ')' => { chars.next(); tokens.push(Token::Close); }
+            '=' => { chars.next(); tokens.push(Token::Op(Op::Eq)); }
+            '~' => { chars.next(); tokens.push(Token::Op(Op::Contains)); }
+            '!' | '<' | '>' => {
+                chars.next();
+                let with_equal = chars.peek() == Some(&'=');
+                if with_equal {
+                    chars.next();
+                }
+                tokens.push(Token::Op(match (c, with_equal) {
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    _ => return Err(String::from("unexpected !")),
+                }));
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.push(chars.next().ok_or_else(|| String::from("unterminated text"))?),
+                        Some(c) => text.push(c),
+                        None => return Err(String::from("unterminated text")),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// ---------------- Parser -------------
+
+// Parentheses and NOT nested deeper are refused, the expressions are compiled recursively
+const MAX_DEPTH: usize = 32;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    // parentheses and NOT around the current token
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("more than {} nested parentheses or NOT", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return self.nested(|parser| Ok(Expr::Not(Box::new(parser.unary()?))));
+        }
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.nested(Self::or)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(String::from("missing )")),
+                }
+            }
+            Some(Token::Word(ref word)) if word.to_lowercase().starts_with("tag:") => {
+                let name = match &word[4..] {
+                    "" => match self.next() {
+                        Some(Token::Word(name)) | Some(Token::Quoted(name)) => name,
+                        _ => return Err(String::from("missing tag name")),
+                    },
+                    name => name.to_string(),
+                };
+                let name = tag_handler::normalize(&name);
+                if name.is_empty() {
+                    return Err(String::from("missing tag name"));
+                }
+                Ok(Expr::Tag(name))
+            }
+            Some(Token::Word(word)) => {
+                let field = Field::from_name(&word).ok_or_else(|| format!("unknown field {}", word))?;
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    _ => return Err(format!("missing operator after {}", field.name())),
+                };
+                let operand = self.operand()?;
+                checked(field, op, operand)
+            }
+            Some(token) => Err(format!("unexpected {}", describe(&token))),
+            None => Err(String::from("unexpected end of expression")),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        match self.next() {
+            Some(Token::Quoted(text)) => Ok(Operand::Text(text)),
+            Some(Token::Word(word)) => {
+                if word.eq_ignore_ascii_case("null") {
+                    Ok(Operand::Null)
+                } else if let Ok(number) = word.parse::<i32>() {
+                    Ok(Operand::Number(number))
+                } else if let Some(field) = Field::from_name(&word) {
+                    Ok(Operand::Field(field))
+                } else {
+                    Ok(Operand::Text(word))
+                }
+            }
+            Some(token) => Err(format!("unexpected {}", describe(&token))),
+            None => Err(String::from("missing value")),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => String::from("("),
+        Token::Close => String::from(")"),
+        Token::Op(op) => op.symbol().to_string(),
+        Token::Word(word) => word.clone(),
+        Token::Quoted(text) => format!("\"{}\"", text),
+    }
+}
+
+// Checks the types of a comparison and normalizes its value
+fn checked(field: Field, op: Op, operand: Operand) -> Result<Expr, String> {
+    let operand = match operand {
+        Operand::Null if op != Op::Eq && op != Op::Ne => {
+            return Err(format!("{}: null can only be compared with = or !=", field.name()));
+        }
+        Operand::Null => Operand::Null,
+        _ if op == Op::Contains && field.is_numeric() => {
+            return Err(format!("{}: ~ only applies to texts", field.name()));
+        }
+        Operand::Field(other) if field.is_numeric() != other.is_numeric() => {
+            return Err(format!("{}: can not be compared with {}", field.name(), other.name()));
+        }
+        Operand::Field(other) if op == Op::Contains || field == Field::Year || other == Field::Year => {
+            return Err(format!("{}: can only be compared with a value", if other == Field::Year { other.name() } else { field.name() }));
+        }
+        Operand::Field(other) => Operand::Field(other),
+        Operand::Text(text) if field.is_numeric() => {
+            return Err(format!("{}: {} is not a number", field.name(), text));
+        }
+        Operand::Number(number) if field == Field::Year && (number == 0 || number.abs() > 9999) => {
+            return Err(format!("year: invalid year {}", number));
+        }
+        Operand::Number(number) if field.is_numeric() => Operand::Number(number),
+        // texts made of digits, such as ISBN
+        Operand::Number(number) => text_operand(field, number.to_string())?,
+        Operand::Text(text) => text_operand(field, text)?,
+    };
+    Ok(Expr::Compare(field, op, operand))
+}
+
+fn text_operand(field: Field, text: String) -> Result<Operand, String> {
+    match field {
+        Field::Status => ReadingStatus::from_str(&text.to_lowercase())
+            .map(|status| Operand::Text(status.as_str().to_string()))
+            .map_err(|_| format!("status: unknown status {}", text)),
+        field if field.is_language() => Ok(Operand::Text(language::normalize_or_keep(&text))),
+        _ => Ok(Operand::Text(text)),
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, String> {
+    let mut parser = Parser { tokens: tokenize(input)?, position: 0, depth: 0 };
+    if parser.tokens.is_empty() {
+        return Err(String::from("empty expression"));
+    }
+    let expr = parser.or()?;
+    match parser.next() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {}", describe(&token))),
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse(input)
+    }
+}
+
+// ---------------- Canonical form -------------
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Field(field) => write!(f, "{}", field.name()),
+            Operand::Text(text) => write!(f, "{}", quoted(text)),
+            Operand::Number(number) => write!(f, "{}", number),
+            Operand::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl Expr {
+    // 0 for OR, 1 for AND, 2 for the others
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(_, _) => 0,
+            Expr::And(_, _) => 1,
+            _ => 2,
+        }
+    }
+
+    fn fmt_operand(&self, operand: &Expr, precedence: u8, f: &mut fmt::Formatter) -> fmt::Result {
+        if operand.precedence() < precedence {
+            write!(f, "({})", operand)
+        } else {
+            write!(f, "{}", operand)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Or(left, right) => {
+                self.fmt_operand(left, 0, f)?;
+                write!(f, " OR ")?;
+                self.fmt_operand(right, 1, f)
+            }
+            Expr::And(left, right) => {
+                self.fmt_operand(left, 1, f)?;
+                write!(f, " AND ")?;
+                self.fmt_operand(right, 2, f)
+            }
+            Expr::Not(expr) => {
+                write!(f, "NOT ")?;
+                self.fmt_operand(expr, 2, f)
+            }
+            Expr::Compare(field, op, operand) => write!(f, "{} {} {}", field.name(), op.symbol(), operand),
+            Expr::Tag(name) => write!(f, "tag:{}", quoted(name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(field: Field, op: Op, operand: Operand) -> Box<Expr> {
+        Box::new(Expr::Compare(field, op, operand))
+    }
+
+    #[test]
+    fn parses() {
+        let expr = parse("language_original != language_main AND rating >= 4 AND tag:poetry").unwrap();
+        assert_eq!(expr, Expr::And(
+            Box::new(Expr::And(
+                compare(Field::LanguageOriginal, Op::Ne, Operand::Field(Field::LanguageMain)),
+                compare(Field::Rating, Op::Ge, Operand::Number(4)),
+            )),
+            Box::new(Expr::Tag(String::from("poetry"))),
+        ));
+        let expr = parse("not status = Read or (title ~ \"la \\\"vie\\\"\" and year<1980)").unwrap();
+        assert_eq!(expr, Expr::Or(
+            Box::new(Expr::Not(compare(Field::Status, Op::Eq, Operand::Text(String::from("read"))))),
+            Box::new(Expr::And(
+                compare(Field::Title, Op::Contains, Operand::Text(String::from("la \"vie\""))),
+                compare(Field::Year, Op::Lt, Operand::Number(1980)),
+            )),
+        ));
+    }
+
+    #[test]
+    fn normalizes_values() {
+        assert_eq!(parse("language_main = French").unwrap(), *compare(Field::LanguageMain, Op::Eq, Operand::Text(String::from("fr"))));
+        assert_eq!(parse("tag:\"Science  Fiction\"").unwrap(), Expr::Tag(String::from("science fiction")));
+        assert_eq!(parse("isbn = 9782070368228").unwrap(), *compare(Field::Isbn, Op::Eq, Operand::Text(String::from("9782070368228"))));
+        assert_eq!(parse("rating = NULL").unwrap(), *compare(Field::Rating, Op::Eq, Operand::Null));
+    }
+
+    #[test]
+    fn errors() {
+        for input in &[
+            "", "rating", "rating >= ", "pages > 3", "rating >= high", "rating ~ 3", "rating < null",
+            "year = 0", "year = rating", "title = rating", "status = lost", "(rating > 3", "rating > 3)",
+            "tag:", "title = \"open", "rating ! 3", "rating > 3 title = x",
+        ] {
+            assert!(parse(input).is_err(), "{} should not parse", input);
+        }
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let nested = |depth: usize| format!("{}rating > 3{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(parse(&format!("{}rating > 3", "NOT ".repeat(MAX_DEPTH + 1))).is_err());
+        assert!(parse(&format!("{}NOT rating > 3{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH))).is_err());
+        assert!(parse(&"(rating > 3) AND ".repeat(MAX_DEPTH + 1).trim_end_matches(" AND ")).is_ok());
+    }
+
+    #[test]
+    fn canonical_form() {
+        for input in &[
+            "language_original != language_main AND rating >= 4 AND tag:\"poetry\"",
+            "(rating = null OR page_count > 300) AND NOT (title ~ \"a \\\"b\\\"\" OR year <= -400)",
+            "NOT NOT status = \"to-read\"",
+        ] {
+            let expr = parse(input).unwrap();
+            assert_eq!(expr.to_string(), *input);
+            assert_eq!(parse(&expr.to_string()).unwrap(), expr);
+        }
+    }
+}
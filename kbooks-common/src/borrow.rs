@@ -0,0 +1,53 @@
+// Life of a request to borrow the book of another user : the owner accepts or declines a pending request,
+// then the book lent for an accepted request is returned. Declined and returned requests are closed.
+use crate::models::BorrowState;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BorrowAction {
+    Accept,
+    Decline,
+    Return,
+}
+
+impl BorrowAction {
+    fn past(self) -> &'static str {
+        match self {
+            BorrowAction::Accept => "accepted",
+            BorrowAction::Decline => "declined",
+            BorrowAction::Return => "returned",
+        }
+    }
+}
+
+// State of the request after the action, or why the action is not possible
+pub fn next(state: BorrowState, action: BorrowAction) -> Result<BorrowState, String> {
+    match (state, action) {
+        (BorrowState::Pending, BorrowAction::Accept) => Ok(BorrowState::Accepted),
+        (BorrowState::Pending, BorrowAction::Decline) => Ok(BorrowState::Declined),
+        (BorrowState::Accepted, BorrowAction::Return) => Ok(BorrowState::Returned),
+        (state, action) => Err(format!("a {} request cannot be {}", state.as_str(), action.past())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        assert_eq!(next(BorrowState::Pending, BorrowAction::Accept), Ok(BorrowState::Accepted));
+        assert_eq!(next(BorrowState::Pending, BorrowAction::Decline), Ok(BorrowState::Declined));
+        assert_eq!(next(BorrowState::Accepted, BorrowAction::Return), Ok(BorrowState::Returned));
+    }
+
+    #[test]
+    fn refused_transitions() {
+        assert_eq!(next(BorrowState::Pending, BorrowAction::Return), Err("a pending request cannot be returned".to_string()));
+        assert!(next(BorrowState::Accepted, BorrowAction::Decline).is_err());
+        for state in &[BorrowState::Declined, BorrowState::Returned] {
+            for action in &[BorrowAction::Accept, BorrowAction::Decline, BorrowAction::Return] {
+                assert!(next(*state, *action).is_err());
+            }
+        }
+    }
+}
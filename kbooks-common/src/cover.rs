@@ -0,0 +1,211 @@
+// Covers of the books : the uploaded images are decoded and re-encoded as JPEG in each size,
+// so that only valid images of a known format are stored and served.
+// Book.cover holds the key of the cover, each size being stored under "<key>-<size>.jpg".
+// A new key is made for each upload : the stored files never change and can be cached forever.
+use std::fmt;
+use std::io::{self, Cursor};
+use std::str::FromStr;
+
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use uuid::Uuid;
+
+use crate::storage::Storage;
+
+// Photos of covers taken by phones are a few megabytes
+pub const MAX_COVER_SIZE: usize = 10 * 1024 * 1024;
+const MAX_DIMENSION: u32 = 8000;
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoverSize {
+    Large,
+    Medium,
+    Small,
+}
+
+pub const COVER_SIZES: [CoverSize; 3] = [CoverSize::Large, CoverSize::Medium, CoverSize::Small];
+
+impl CoverSize {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoverSize::Large => "large",
+            CoverSize::Medium => "medium",
+            CoverSize::Small => "small",
+        }
+    }
+
+    // The image fits in these width and height, keeping its proportions
+    pub fn bounds(&self) -> (u32, u32) {
+        match self {
+            CoverSize::Large => (1200, 1800),
+            CoverSize::Medium => (300, 450),
+            CoverSize::Small => (100, 150),
+        }
+    }
+}
+
+impl FromStr for CoverSize {
+    type Err = String;
+
+    fn from_str(size: &str) -> Result<Self, Self::Err> {
+        match size {
+            "large" => Ok(CoverSize::Large),
+            "medium" => Ok(CoverSize::Medium),
+            "small" => Ok(CoverSize::Small),
+            _ => Err(format!("Unknown cover size {}", size)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CoverError {
+    TooLarge,
+    InvalidImage(String),
+    Storage(io::Error),
+}
+
+impl fmt::Display for CoverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CoverError::TooLarge => write!(f, "the image is larger than {} MB", MAX_COVER_SIZE / 1024 / 1024),
+            CoverError::InvalidImage(error) => write!(f, "invalid image : {}", error),
+            CoverError::Storage(error) => write!(f, "storage error : {}", error),
+        }
+    }
+}
+
+impl From<io::Error> for CoverError {
+    fn from(error: io::Error) -> CoverError {
+        CoverError::Storage(error)
+    }
+}
+
+// Name of the stored file of a size of the cover
+pub fn file_name(key: &str, size: CoverSize) -> String {
+    format!("{}-{}.jpg", key, size.as_str())
+}
+
+fn resized(image: &DynamicImage, size: CoverSize) -> DynamicImage {
+    let (width, height) = size.bounds();
+    if image.width() <= width && image.height() <= height {
+        image.clone()
+    } else {
+        image.thumbnail(width, height)
+    }
+}
+
+// The JPEG content of each size of the cover
+pub fn process(data: &[u8]) -> Result<Vec<(CoverSize, Vec<u8>)>, CoverError> {
+    if data.len() > MAX_COVER_SIZE {
+        return Err(CoverError::TooLarge);
+    }
+    let invalid = |error: image::ImageError| CoverError::InvalidImage(error.to_string());
+    // a few kilobytes can declare an image too large to be held in memory : the header is checked before decoding
+    let (width, height) = image::io::Reader::new(Cursor::new(data)).with_guessed_format()?
+        .into_dimensions().map_err(invalid)?;
+    if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(CoverError::InvalidImage(format!("unexpected dimensions {}x{}", width, height)));
+    }
+    let image = image::load_from_memory(data).map_err(invalid)?;
+    // JPEG has no transparency
+    let image = DynamicImage::ImageRgb8(image.to_rgb());
+    let mut sizes = Vec::new();
+    for size in COVER_SIZES.iter() {
+        let mut jpeg: Vec<u8> = Vec::new();
+        resized(&image, *size).write_to(&mut jpeg, ImageOutputFormat::Jpeg(JPEG_QUALITY)).map_err(invalid)?;
+        sizes.push((*size, jpeg));
+    }
+    Ok(sizes)
+}
+
+// The covers kept in a storage
+pub struct CoverStore {
+    storage: Box<dyn Storage>,
+}
+
+impl CoverStore {
+    pub fn new(storage: Box<dyn Storage>) -> CoverStore {
+        CoverStore { storage }
+    }
+
+    // Stores all the sizes of a new cover of the book, as returned by process, and returns its key.
+    // Nothing is left in the storage when one of them can not be written.
+    pub fn save(&self, book_id: i32, sizes: &[(CoverSize, Vec<u8>)]) -> Result<String, CoverError> {
+        let key = format!("{}-{}", book_id, Uuid::new_v4().to_simple());
+        for (size, jpeg) in sizes {
+            if let Err(error) = self.storage.put(&file_name(&key, *size), jpeg) {
+                let _ = self.delete(&key);
+                return Err(error.into());
+            }
+        }
+        Ok(key)
+    }
+
+    pub fn get(&self, key: &str, size: CoverSize) -> Result<Vec<u8>, CoverError> {
+        Ok(self.storage.get(&file_name(key, size))?)
+    }
+
+    pub fn delete(&self, key: &str) -> Result<(), CoverError> {
+        for size in COVER_SIZES.iter() {
+            self.storage.delete(&file_name(key, *size))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    use crate::storage::LocalStorage;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height)).write_to(&mut data, ImageOutputFormat::Png).unwrap();
+        data
+    }
+
+    fn dimensions(jpeg: &[u8]) -> (u32, u32) {
+        image::load_from_memory(jpeg).unwrap().dimensions()
+    }
+
+    #[test]
+    fn sizes() {
+        let sizes = process(&png(600, 1000)).unwrap();
+        let dimensions: Vec<(CoverSize, (u32, u32))> = sizes.iter().map(|(size, jpeg)| (*size, dimensions(jpeg))).collect();
+        assert_eq!(dimensions, vec![
+            (CoverSize::Large, (600, 1000)),
+            (CoverSize::Medium, (270, 450)),
+            (CoverSize::Small, (90, 150)),
+        ]);
+    }
+
+    #[test]
+    fn invalid_images() {
+        match process(b"<svg></svg>") {
+            Err(CoverError::InvalidImage(_)) => (),
+            other => panic!("unexpected {:?}", other.map(|sizes| sizes.len())),
+        }
+        match process(&png(MAX_DIMENSION + 1, 1)) {
+            Err(CoverError::InvalidImage(error)) => assert_eq!(error, "unexpected dimensions 8001x1"),
+            other => panic!("unexpected {:?}", other.map(|sizes| sizes.len())),
+        }
+        match process(&vec![0; MAX_COVER_SIZE + 1]) {
+            Err(CoverError::TooLarge) => (),
+            other => panic!("unexpected {:?}", other.map(|sizes| sizes.len())),
+        }
+    }
+
+    #[test]
+    fn store() {
+        let root = std::env::temp_dir().join(format!("kbooks-covers-{}", std::process::id()));
+        let store = CoverStore::new(Box::new(LocalStorage::new(root.clone())));
+        let key = store.save(12, &process(&png(50, 80)).unwrap()).unwrap();
+        assert!(key.starts_with("12-"));
+        assert_eq!(dimensions(&store.get(&key, CoverSize::Small).unwrap()), (50, 80));
+        store.delete(&key).unwrap();
+        assert!(store.get(&key, CoverSize::Large).is_err());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,353 @@
+// Books written in formats read back by our importers.
+// CSV uses the Goodreads column names, JSON and TSV those of LibraryThing.
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use serde_json::{json, Value};
+
+use crate::author::display_name;
+use crate::language::english_name;
+use crate::models::{Book, BookDetails, Contributor, Role};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Tsv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "tsv" => Ok(ExportFormat::Tsv),
+            _ => Err(format!("Unknown export format {}", format)),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Tsv => "text/tab-separated-values; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Tsv => "tsv",
+        }
+    }
+}
+
+const CSV_HEADERS: &[&str] = &[
+    "Title", "Author l-f", "Additional Authors", "ISBN13", "My Rating", "Number of Pages", "Original Publication Year",
+    "Date Read", "Date Added", "Bookshelves", "My Review",
+    // not in Goodreads exports
+    "Language", "Secondary Language", "Original Language", "Date Acquired", "Date Started", "KBooks Id",
+];
+
+const TSV_HEADERS: &[&str] = &[
+    "Book Id", "Title", "Primary Author", "Secondary Author", "Secondary Author Roles", "Date", "Page Count", "ISBNs",
+    "Rating", "Review", "Tags", "Languages", "Original Languages", "Entry Date", "Acquired", "Date Started", "Date Read",
+];
+
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// LibraryThing only reads days
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Writes the books a page at a time : the header, then each page of books, then the footer
+pub struct Exporter {
+    format: ExportFormat,
+    written: usize,
+}
+
+impl Exporter {
+    pub fn new(format: ExportFormat) -> Exporter {
+        Exporter { format, written: 0 }
+    }
+
+    pub fn header(&self) -> Result<Vec<u8>, String> {
+        match self.format {
+            ExportFormat::Csv => write_records(b',', &[strings(CSV_HEADERS)]),
+            ExportFormat::Json => Ok(b"[".to_vec()),
+            ExportFormat::Tsv => write_records(b'\t', &[strings(TSV_HEADERS)]),
+        }
+    }
+
+    pub fn books(&mut self, books: &[BookDetails]) -> Result<Vec<u8>, String> {
+        let data = match self.format {
+            ExportFormat::Csv => write_records(b',', &books.iter().map(csv_record).collect::<Vec<_>>()),
+            ExportFormat::Json => {
+                let mut data = Vec::new();
+                for (index, book) in books.iter().enumerate() {
+                    if self.written + index > 0 {
+                        data.push(b',');
+                    }
+                    data.push(b'\n');
+                    serde_json::to_writer(&mut data, &json_entry(book)).map_err(|err| err.to_string())?;
+                }
+                Ok(data)
+            }
+            ExportFormat::Tsv => write_records(b'\t', &books.iter().map(tsv_record).collect::<Vec<_>>()),
+        };
+        self.written += books.len();
+        data
+    }
+
+    pub fn footer(&self) -> Vec<u8> {
+        match self.format {
+            ExportFormat::Json => b"\n]\n".to_vec(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn write_records(delimiter: u8, records: &[Vec<String>]) -> Result<Vec<u8>, String> {
+    // LibraryThing does not read quoted fields
+    let quote_style = if delimiter == b'\t' { csv::QuoteStyle::Never } else { csv::QuoteStyle::Necessary };
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .quote_style(quote_style)
+        .from_writer(Vec::new());
+    for record in records {
+        writer.write_record(record).map_err(|err| err.to_string())?;
+    }
+    writer.into_inner().map_err(|err| err.to_string())
+}
+
+fn format_date(date: Option<NaiveDateTime>, format: &str) -> String {
+    date.map(|date| date.format(format).to_string()).unwrap_or_default()
+}
+
+fn page_count(book: &BookDetails) -> String {
+    book.book.page_count.map(|count| count.to_string()).unwrap_or_default()
+}
+
+fn rating(book: &BookDetails) -> String {
+    book.book.rating.map(|rating| rating.to_string()).unwrap_or_default()
+}
+
+// Contributors other than the primary author, which is the first one
+fn secondary_contributors(book: &BookDetails) -> Vec<&Contributor> {
+    let mut contributors: Vec<&Contributor> = book.contributors.iter().collect();
+    if let Some(first) = contributors.first() {
+        if first.role == Role::Author && Some(first.author_id) == book.book.author_id {
+            contributors.remove(0);
+        }
+    }
+    contributors
+}
+
+fn role_label(role: Role) -> &'static str {
+    match role {
+        Role::Author => "Author",
+        Role::Translator => "Translator",
+        Role::Editor => "Editor",
+        Role::Illustrator => "Illustrator",
+        Role::Preface => "Preface",
+    }
+}
+
+// Tabs and line breaks would split the TSV fields
+fn tsv_field(value: &str) -> String {
+    value.replace(|c| c == '\t' || c == '\r' || c == '\n', " ")
+}
+
+// LibraryThing uses the English names of the languages
+fn language_names(book: &Book) -> Vec<String> {
+    let mut languages = vec![english_name(&book.language_main)];
+    if let Some(ref secondary) = book.language_secondary {
+        languages.push(english_name(secondary));
+    }
+    languages
+}
+
+fn csv_record(details: &BookDetails) -> Vec<String> {
+    let book = &details.book;
+    let additional_authors: Vec<String> = secondary_contributors(details).iter()
+        .filter(|contributor| contributor.role == Role::Author)
+        .map(|contributor| display_name(&contributor.name))
+        .collect();
+    vec![
+        book.title.clone(),
+        book.author_lf.clone(),
+        additional_authors.join(", "),
+        book.isbn.clone(),
+        rating(details),
+        page_count(details),
+        book.publicationdate.clone(),
+        format_date(book.finished_stamp, DATETIME_FORMAT),
+        format_date(Some(book.created_at), DATETIME_FORMAT),
+        details.tags.join(", "),
+        book.review.clone().unwrap_or_default(),
+        book.language_main.clone(),
+        book.language_secondary.clone().unwrap_or_default(),
+        book.language_original.clone(),
+        format_date(book.dateacquired_stamp, DATETIME_FORMAT),
+        format_date(book.started_stamp, DATETIME_FORMAT),
+        book.id.to_string(),
+    ]
+}
+
+fn tsv_record(details: &BookDetails) -> Vec<String> {
+    let book = &details.book;
+    let secondary = secondary_contributors(details);
+    let languages = language_names(book);
+    let record = vec![
+        book.librarything_id.clone().unwrap_or_default(),
+        book.title.clone(),
+        book.author_lf.clone(),
+        secondary.iter().map(|contributor| contributor.name.as_str()).collect::<Vec<&str>>().join("|"),
+        secondary.iter().map(|contributor| role_label(contributor.role)).collect::<Vec<&str>>().join("|"),
+        book.publicationdate.clone(),
+        page_count(details),
+        book.isbn.clone(),
+        rating(details),
+        book.review.clone().unwrap_or_default(),
+        details.tags.join(", "),
+        languages.join(", "),
+        english_name(&book.language_original),
+        format_date(Some(book.created_at), DATE_FORMAT),
+        format_date(book.dateacquired_stamp, DATE_FORMAT),
+        format_date(book.started_stamp, DATE_FORMAT),
+        format_date(book.finished_stamp, DATE_FORMAT),
+    ];
+    record.iter().map(|value| tsv_field(value)).collect()
+}
+
+fn json_entry(details: &BookDetails) -> Value {
+    let book = &details.book;
+    let mut authors = vec![json!({"lf": book.author_lf, "role": role_label(Role::Author)})];
+    for contributor in secondary_contributors(details) {
+        authors.push(json!({"lf": contributor.name, "role": role_label(contributor.role)}));
+    }
+    json!({
+        "books_id": book.librarything_id,
+        "title": book.title,
+        "authors": authors,
+        "date": book.publicationdate,
+        "pages": book.page_count.map(|count| count.to_string()),
+        "originalisbn": book.isbn,
+        "rating": book.rating,
+        "review": book.review,
+        "language": language_names(book),
+        "originallanguage": [english_name(&book.language_original)],
+        "tags": details.tags,
+        "entrydate": format_date(Some(book.created_at), DATETIME_FORMAT),
+        "dateacquired": format_date(book.dateacquired_stamp, DATETIME_FORMAT),
+        "datestarted": format_date(book.started_stamp, DATETIME_FORMAT),
+        "datefinished": format_date(book.finished_stamp, DATETIME_FORMAT),
+        "kbooks_id": book.id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::import::{self, ImportFormat, ImportedBook};
+
+    fn details() -> BookDetails {
+        let mut book = Book::new();
+        book.id = 12;
+        book.librarything_id = Some(String::from("162415042"));
+        book.title = String::from("La Vie mode d'emploi");
+        book.author_lf = String::from("Perec, Georges");
+        book.author_id = Some(1);
+        book.isbn = String::from("9782253053002");
+        book.publicationdate = String::from("1978");
+        book.page_count = Some(657);
+        book.rating = Some(5);
+        book.language_main = String::from("fr");
+        book.language_original = String::from("fr");
+        book.review = Some(String::from("Un immeuble,\tses habitants\net leurs histoires"));
+        book.created_at = NaiveDate::from_ymd(2019, 1, 15).and_hms(10, 12, 0);
+        book.dateacquired_stamp = None;
+        book.started_stamp = None;
+        book.finished_stamp = Some(NaiveDate::from_ymd(2019, 2, 1).and_hms(0, 0, 0));
+        let contributor = |author_id: i32, name: &str, role: Role| Contributor {
+            author_id, name: name.to_string(), code: String::new(), role
+        };
+        BookDetails {
+            book,
+            contributors: vec![
+                contributor(1, "Perec, Georges", Role::Author),
+                contributor(2, "Bellos, David", Role::Translator),
+            ],
+            tags: vec![String::from("novel"), String::from("oulipo")],
+            progress: None,
+        }
+    }
+
+    fn export(format: ExportFormat) -> Vec<u8> {
+        let mut exporter = Exporter::new(format);
+        let mut data = exporter.header().unwrap();
+        data.extend(exporter.books(&[details()]).unwrap());
+        data.extend(exporter.books(&[details()]).unwrap());
+        data.extend(exporter.footer());
+        data
+    }
+
+    fn imported(format: ImportFormat, data: &[u8]) -> Vec<ImportedBook> {
+        import::parse(format, data).unwrap().into_iter().map(|row| row.book.unwrap()).collect()
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let books = imported(ImportFormat::LibraryThing, &export(ExportFormat::Json));
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].librarything_id, Some(String::from("162415042")));
+        assert_eq!(books[0].author, "Perec, Georges");
+        assert_eq!(books[0].contributors, vec![(String::from("Bellos, David"), Role::Translator)]);
+        assert_eq!(books[0].review, details().book.review);
+        assert_eq!(books[0].created_at, Some(details().book.created_at));
+        assert_eq!(books[0].page_count, Some(657));
+        assert_eq!(books[0].finished_stamp, details().book.finished_stamp);
+        assert_eq!(books[0].tags, details().tags);
+        assert_eq!(books[0].kbooks_id, Some(12));
+    }
+
+    #[test]
+    fn tsv_round_trip() {
+        let books = imported(ImportFormat::LibraryThing, &export(ExportFormat::Tsv));
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].title, "La Vie mode d'emploi");
+        assert_eq!(books[0].contributors, vec![(String::from("Bellos, David"), Role::Translator)]);
+        assert_eq!(books[0].isbn, "9782253053002");
+        assert_eq!(books[0].rating, Some(5));
+        assert_eq!(books[0].page_count, Some(657));
+        assert_eq!(books[0].language_main, Some(String::from("French")));
+        assert_eq!(books[0].review, Some(String::from("Un immeuble, ses habitants et leurs histoires")));
+        assert_eq!(books[0].finished_stamp, details().book.finished_stamp);
+        assert_eq!(books[0].tags, details().tags);
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let books = imported(ImportFormat::Goodreads, &export(ExportFormat::Csv));
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].author, "Perec, Georges");
+        assert_eq!(books[0].isbn, "9782253053002");
+        assert_eq!(books[0].publicationdate, "1978");
+        assert_eq!(books[0].page_count, Some(657));
+        assert_eq!(books[0].language_original, Some(String::from("fr")));
+        assert_eq!(books[0].review, details().book.review);
+        assert_eq!(books[0].created_at, Some(details().book.created_at));
+        assert_eq!(books[0].dateacquired_stamp, None);
+        assert_eq!(books[0].tags, details().tags);
+        assert_eq!(books[0].kbooks_id, Some(12));
+    }
+}
@@ -0,0 +1,130 @@
+// Progress of the yearly reading goals, computed from the finished readings.
+// The amount expected at a date grows linearly over the year.
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::models::{Goal, GoalKind};
+use crate::stats::FinishedReading;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GoalProgress {
+    #[serde(flatten)]
+    pub goal: Goal,
+    // books or pages read
+    pub done: i64,
+    // capped at 100
+    pub percent: i32,
+    // what should have been read at this date to reach the target
+    pub expected: i64,
+    pub on_track: bool,
+    pub behind_by: i64,
+}
+
+fn days_in_year(year: i32) -> u32 {
+    NaiveDate::from_ymd(year + 1, 1, 1).signed_duration_since(NaiveDate::from_ymd(year, 1, 1)).num_days() as u32
+}
+
+// Part of the year elapsed at the end of the given day
+fn elapsed(year: i32, today: NaiveDate) -> f64 {
+    if today.year() < year {
+        0.0
+    } else if today.year() > year {
+        1.0
+    } else {
+        f64::from(today.ordinal()) / f64::from(days_in_year(year))
+    }
+}
+
+fn counts(goal: &Goal, reading: &FinishedReading) -> bool {
+    reading.finished_at.year() == goal.year
+        && goal.language.as_ref().map(|language| *language == reading.language_main).unwrap_or(true)
+}
+
+pub fn progress(goal: Goal, readings: &[FinishedReading], today: NaiveDate) -> GoalProgress {
+    let kind = GoalKind::from_str(&goal.kind).unwrap_or(GoalKind::Books);
+    let done: i64 = readings.iter()
+        .filter(|reading| counts(&goal, reading))
+        .map(|reading| match kind {
+            GoalKind::Books => 1,
+            GoalKind::Pages => reading.page_count.unwrap_or(0) as i64,
+        })
+        .sum();
+    let target = i64::from(goal.target.max(0));
+    let expected = (target as f64 * elapsed(goal.year, today)).floor() as i64;
+    let percent = if target == 0 { 100 } else { (done * 100 / target).min(100) as i32 };
+    GoalProgress {
+        goal,
+        done,
+        percent,
+        expected,
+        on_track: done >= expected,
+        behind_by: (expected - done).max(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(kind: GoalKind, target: i32, language: Option<&str>) -> Goal {
+        Goal {
+            id: 1,
+            user_id: 1,
+            year: 2026,
+            kind: kind.as_str().to_string(),
+            target,
+            language: language.map(String::from),
+            created_at: NaiveDate::from_ymd(2026, 1, 1).and_hms(0, 0, 0),
+        }
+    }
+
+    fn reading(finished: (i32, u32, u32), page_count: Option<i32>, language: &str) -> FinishedReading {
+        FinishedReading {
+            book_id: 1,
+            finished_at: NaiveDate::from_ymd(finished.0, finished.1, finished.2).and_hms(0, 0, 0),
+            page_count,
+            rating: None,
+            language_main: language.to_string(),
+            language_original: language.to_string(),
+            author_code: String::new(),
+            author_lf: String::new(),
+            dateacquired_stamp: None,
+            first: true,
+        }
+    }
+
+    fn readings() -> Vec<FinishedReading> {
+        vec![
+            reading((2025, 12, 31), Some(1000), "fr"),
+            reading((2026, 1, 15), Some(300), "fr"),
+            reading((2026, 3, 1), None, "en"),
+            reading((2026, 5, 1), Some(200), "en"),
+        ]
+    }
+
+    #[test]
+    fn books() {
+        // 2026-07-02 is the 183rd day of 365
+        let progress = progress(goal(GoalKind::Books, 40, None), &readings(), NaiveDate::from_ymd(2026, 7, 2));
+        assert_eq!((progress.done, progress.expected, progress.percent), (3, 20, 7));
+        assert_eq!((progress.on_track, progress.behind_by), (false, 17));
+    }
+
+    #[test]
+    fn pages_and_language() {
+        let today = NaiveDate::from_ymd(2026, 1, 20);
+        let pages = progress(goal(GoalKind::Pages, 5000, None), &readings(), today);
+        assert_eq!((pages.done, pages.expected, pages.on_track), (500, 273, true));
+        let english = progress(goal(GoalKind::Books, 2, Some("en")), &readings(), today);
+        assert_eq!((english.done, english.percent), (2, 100));
+    }
+
+    #[test]
+    fn other_years() {
+        let before = progress(goal(GoalKind::Books, 40, None), &readings(), NaiveDate::from_ymd(2025, 12, 1));
+        assert_eq!((before.expected, before.on_track), (0, true));
+        let after = progress(goal(GoalKind::Books, 40, None), &readings(), NaiveDate::from_ymd(2027, 2, 1));
+        assert_eq!((after.expected, after.behind_by), (40, 37));
+    }
+}
@@ -0,0 +1,149 @@
+// Goodreads CSV export ("My Books" > "Import and export")
+use crate::import::{check, non_empty, parse_date, parse_page_count, parse_rating, Columns, ImportedBook, ParsedRow};
+use crate::models::Role;
+
+const TITLE: &[&str] = &["title"];
+const AUTHOR: &[&str] = &["author l-f", "author"];
+const ADDITIONAL_AUTHORS: &[&str] = &["additional authors"];
+const ISBN: &[&str] = &["isbn13", "isbn"];
+const RATING: &[&str] = &["my rating"];
+const PUBLICATION: &[&str] = &["original publication year", "year published"];
+const PAGES: &[&str] = &["number of pages"];
+const DATE_READ: &[&str] = &["date read"];
+const DATE_ADDED: &[&str] = &["date added"];
+const BOOKSHELVES: &[&str] = &["bookshelves"];
+const EXCLUSIVE_SHELF: &[&str] = &["exclusive shelf"];
+const REVIEW: &[&str] = &["my review"];
+// not in Goodreads exports, written by our CSV export
+const LANGUAGE: &[&str] = &["language"];
+const SECONDARY_LANGUAGE: &[&str] = &["secondary language"];
+const ORIGINAL_LANGUAGE: &[&str] = &["original language"];
+const DATE_ACQUIRED: &[&str] = &["date acquired"];
+const DATE_STARTED: &[&str] = &["date started"];
+const KBOOKS_ID: &[&str] = &["kbooks id"];
+
+// Books finished are recognized by their reading date, other exclusive shelves are kept as tags
+const READ_SHELF: &str = "read";
+
+// ISBNs are exported as ="2020104725" to keep spreadsheets from reading them as numbers
+fn unquote_isbn(isbn: &str) -> String {
+    isbn.trim_start_matches('=').trim_matches('"').trim().to_string()
+}
+
+fn csv_book(columns: &Columns, record: &csv::StringRecord) -> Result<ImportedBook, String> {
+    let contributors = columns.get(record, ADDITIONAL_AUTHORS).split(',')
+        .filter_map(non_empty)
+        .map(|name| (name, Role::Author))
+        .collect();
+    // the ISBN13 column may hold an empty quoted value
+    let isbn = ISBN.iter()
+        .map(|column| unquote_isbn(columns.get(record, &[column])))
+        .find(|isbn| !isbn.is_empty())
+        .unwrap_or_default();
+    let mut tags: Vec<String> = columns.get(record, BOOKSHELVES).split(',').filter_map(non_empty).collect();
+    if let Some(shelf) = non_empty(columns.get(record, EXCLUSIVE_SHELF)) {
+        if shelf != READ_SHELF && !tags.contains(&shelf) {
+            tags.push(shelf);
+        }
+    }
+    let added = parse_date(columns.get(record, DATE_ADDED));
+    let acquired = match columns.optional(record, DATE_ACQUIRED) {
+        Some(acquired) => parse_date(acquired),
+        None => added,
+    };
+
+    let book = ImportedBook {
+        kbooks_id: columns.get(record, KBOOKS_ID).parse().ok(),
+        title: columns.get(record, TITLE).to_string(),
+        author: columns.get(record, AUTHOR).to_string(),
+        contributors,
+        isbn,
+        publicationdate: columns.get(record, PUBLICATION).to_string(),
+        page_count: parse_page_count(columns.get(record, PAGES)),
+        rating: parse_rating(columns.get(record, RATING)),
+        review: non_empty(columns.get(record, REVIEW)),
+        tags,
+        created_at: added,
+        dateacquired_stamp: acquired,
+        started_stamp: columns.optional(record, DATE_STARTED).map(parse_date),
+        finished_stamp: parse_date(columns.get(record, DATE_READ)),
+        language_main: columns.optional(record, LANGUAGE).map(str::to_string),
+        language_secondary: columns.optional(record, SECONDARY_LANGUAGE).map(non_empty),
+        language_original: columns.optional(record, ORIGINAL_LANGUAGE).map(str::to_string),
+        authors_only: true,
+        // our CSV exports hold the tags of the books
+        shelves: !columns.contains(KBOOKS_ID[0]),
+        ..ImportedBook::default()
+    };
+    check(book)
+}
+
+pub fn parse(content: &str) -> Result<Vec<ParsedRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers = reader.headers().map_err(|err| format!("Invalid CSV : {}", err))?;
+    let columns = Columns::new(headers);
+    if !columns.contains("title") {
+        return Err(String::from("Invalid Goodreads CSV export : no title column"));
+    }
+
+    Ok(reader.records().enumerate().map(|(index, record)| ParsedRow {
+        // the header is the first line
+        row: index + 2,
+        book: match record {
+            Ok(record) => csv_book(&columns, &record),
+            Err(err) => Err(format!("{}", err)),
+        },
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const EXPORT: &str = "Book Id,Title,Author,Author l-f,Additional Authors,ISBN,ISBN13,My Rating,Average Rating,Publisher,Binding,Number of Pages,Year Published,Original Publication Year,Date Read,Date Added,Bookshelves,Bookshelves with positions,Exclusive Shelf,My Review,Spoiler,Private Notes,Read Count,Owned Copies\n\
+        2474,Exercices de style,Raymond Queneau,\"Queneau, Raymond\",Barbara Wright,\"=\"\"0811220117\"\"\",\"=\"\"9780811220118\"\"\",4,4.01,New Directions,Paperback,198,1981,1947,2019/03/12,2019/01/15,\"oulipo, favorites\",\"oulipo (#1), favorites (#3)\",read,\"99 ways, one story\",,,1,0\n\
+        12345,Zazie dans le métro,Raymond Queneau,\"Queneau, Raymond\",,\"=\"\"\"\"\",\"=\"\"\"\"\",0,3.7,Gallimard,Paperback,,1959,1959,,2020/05/02,to-read,to-read (#1),to-read,,,,0,0\n\
+        1,,Nobody,,,,,0,,,,,,,,,,,,,,,,\n";
+
+    #[test]
+    fn csv() {
+        let rows = parse(EXPORT).unwrap();
+        assert_eq!(rows.len(), 3);
+
+        let book = rows[0].book.clone().unwrap();
+        assert_eq!(book.title, "Exercices de style");
+        assert_eq!(book.author, "Queneau, Raymond");
+        assert_eq!(book.contributors, vec![(String::from("Barbara Wright"), Role::Author)]);
+        assert_eq!(book.isbn, "9780811220118");
+        assert_eq!(book.publicationdate, "1947");
+        assert_eq!(book.page_count, Some(198));
+        assert_eq!(book.rating, Some(4));
+        assert_eq!(book.review, Some(String::from("99 ways, one story")));
+        assert_eq!(book.finished_stamp, Some(NaiveDate::from_ymd(2019, 3, 12).and_hms(0, 0, 0)));
+        assert_eq!(book.dateacquired_stamp, Some(NaiveDate::from_ymd(2019, 1, 15).and_hms(0, 0, 0)));
+        assert_eq!(book.tags, vec![String::from("oulipo"), String::from("favorites")]);
+        // not in Goodreads exports
+        assert_eq!((book.language_main, book.language_original, book.started_stamp), (None, None, None));
+        assert!(book.authors_only && book.shelves);
+
+        let book = rows[1].book.clone().unwrap();
+        assert_eq!(book.isbn, "");
+        assert_eq!(book.rating, None);
+        assert_eq!(book.page_count, None);
+        assert_eq!(book.finished_stamp, None);
+        assert_eq!(book.tags, vec![String::from("to-read")]);
+
+        assert_eq!(rows[2].book, Err(String::from("title is missing")));
+    }
+
+    #[test]
+    fn isbn_quoting() {
+        assert_eq!(unquote_isbn("=\"0811220117\""), "0811220117");
+        assert_eq!(unquote_isbn("0811220117"), "0811220117");
+        assert_eq!(unquote_isbn("=\"\""), "");
+    }
+}
@@ -0,0 +1,283 @@
+// LibraryThing exports : JSON and tab-delimited (TSV)
+use serde_json::Value;
+
+use crate::import::{check, non_empty, parse_date, parse_page_count, parse_rating, Columns, ImportedBook, ParsedRow};
+use crate::models::Role;
+
+// The file format is detected from its content
+pub fn parse(content: &str) -> Result<Vec<ParsedRow>, String> {
+    match content.trim_start().chars().next() {
+        Some('{') | Some('[') => parse_json(content),
+        _ => parse_tsv(content),
+    }
+}
+
+fn role(role: &str) -> Option<Role> {
+    match role.trim().to_lowercase().as_str() {
+        "" | "author" | "primary author" => Some(Role::Author),
+        "translator" => Some(Role::Translator),
+        "editor" => Some(Role::Editor),
+        "illustrator" | "cover artist" => Some(Role::Illustrator),
+        "preface" | "introduction" | "foreword" | "afterword" => Some(Role::Preface),
+        _ => None,
+    }
+}
+
+// "[2020104725, 9782020104726]" or "2020104725"
+fn first_isbn(isbns: &str) -> String {
+    isbns.trim_matches(|c| c == '[' || c == ']')
+        .split(|c| c == ',' || c == ' ')
+        .map(|isbn| isbn.trim())
+        .find(|isbn| !isbn.is_empty())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn split_list(values: &str) -> Vec<String> {
+    values.split(|c| c == ',' || c == ';')
+        .filter_map(non_empty)
+        .collect()
+}
+
+// None when the export has no languages : the stored ones are kept
+fn book_from_languages(mut book: ImportedBook, languages: Option<Vec<String>>, original: Option<Vec<String>>) -> ImportedBook {
+    if let Some(languages) = languages {
+        let mut languages = languages.into_iter();
+        book.language_main = Some(languages.next().unwrap_or_default());
+        book.language_secondary = Some(languages.next());
+    }
+    book.language_original = original.map(|original| original.into_iter().next().unwrap_or_default());
+    book
+}
+
+// ---------------- JSON -------------
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.trim().to_string(),
+        Value::Number(number) => number.to_string(),
+        _ => String::new(),
+    }
+}
+
+// Lists are exported either as arrays or as objects indexed by position
+fn texts(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(values) => values.iter().map(text).filter(|v| !v.is_empty()).collect(),
+        Value::Object(values) => values.values().map(text).filter(|v| !v.is_empty()).collect(),
+        Value::String(_) | Value::Number(_) => vec![text(value)].into_iter().filter(|v| !v.is_empty()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn json_book(entry: &Value) -> Result<ImportedBook, String> {
+    let mut book = ImportedBook {
+        librarything_id: non_empty(&text(&entry["books_id"])),
+        title: text(&entry["title"]),
+        // Only in our own exports
+        kbooks_id: text(&entry["kbooks_id"]).parse().ok(),
+        ..ImportedBook::default()
+    };
+
+    if let Value::Array(authors) = &entry["authors"] {
+        for author in authors {
+            let name = match non_empty(&text(&author["lf"])) {
+                Some(name) => name,
+                None => text(&author["fl"]),
+            };
+            if name.is_empty() {
+                continue;
+            }
+            match role(&text(&author["role"])) {
+                Some(Role::Author) if book.author.is_empty() => book.author = name,
+                Some(role) => book.contributors.push((name, role)),
+                None => ()
+            }
+        }
+    }
+    if book.author.is_empty() {
+        book.author = text(&entry["primaryauthor"]);
+    }
+
+    book.isbn = match non_empty(&text(&entry["originalisbn"])) {
+        Some(isbn) => isbn,
+        None => texts(&entry["isbn"]).into_iter().next().unwrap_or_default(),
+    };
+    book.publicationdate = text(&entry["date"]);
+    book.page_count = parse_page_count(&text(&entry["pages"]));
+    book.rating = parse_rating(&text(&entry["rating"]));
+    book.review = non_empty(&text(&entry["review"]));
+    book.tags = texts(&entry["tags"]);
+    book.created_at = parse_date(&text(&entry["entrydate"]));
+    book.dateacquired_stamp = parse_date(&text(&entry["dateacquired"]));
+    book.started_stamp = entry.get("datestarted").map(|started| parse_date(&text(started)));
+    book.finished_stamp = parse_date(&text(&entry["datefinished"]));
+    let book = book_from_languages(book, entry.get("language").map(texts), entry.get("originallanguage").map(texts));
+    check(book)
+}
+
+pub fn parse_json(content: &str) -> Result<Vec<ParsedRow>, String> {
+    let export: Value = serde_json::from_str(content).map_err(|err| format!("Invalid JSON : {}", err))?;
+    let entries: Vec<&Value> = match &export {
+        Value::Object(entries) => entries.values().collect(),
+        Value::Array(entries) => entries.iter().collect(),
+        _ => return Err(String::from("Invalid LibraryThing JSON export")),
+    };
+    Ok(entries.into_iter().enumerate().map(|(index, entry)| ParsedRow {
+        row: index + 1,
+        book: json_book(entry),
+    }).collect())
+}
+
+// ---------------- TSV -------------
+
+// Column names of the current export, followed by those of the legacy one
+const ID: &[&str] = &["book id", "book_id"];
+const TITLE: &[&str] = &["title"];
+const AUTHOR: &[&str] = &["primary author", "author (last, first)"];
+const SECONDARY_AUTHORS: &[&str] = &["secondary author", "other authors"];
+const SECONDARY_ROLES: &[&str] = &["secondary author roles"];
+const DATE: &[&str] = &["date"];
+const PAGES: &[&str] = &["page count", "pagination"];
+const ISBN: &[&str] = &["isbn", "isbns"];
+const RATING: &[&str] = &["rating", "stars"];
+const REVIEW: &[&str] = &["review"];
+const TAGS: &[&str] = &["tags"];
+const LANGUAGES: &[&str] = &["languages", "lang1"];
+const SECONDARY_LANGUAGE: &[&str] = &["lang2"];
+const ORIGINAL_LANGUAGES: &[&str] = &["original languages", "lang original"];
+const ENTRY_DATE: &[&str] = &["entry date", "date entered"];
+const ACQUIRED: &[&str] = &["acquired", "date acquired"];
+const STARTED: &[&str] = &["date started"];
+const FINISHED: &[&str] = &["date read", "date ended"];
+
+fn tsv_book(columns: &Columns, record: &csv::StringRecord) -> Result<ImportedBook, String> {
+    let mut book = ImportedBook {
+        librarything_id: non_empty(columns.get(record, ID)),
+        title: columns.get(record, TITLE).to_string(),
+        author: columns.get(record, AUTHOR).to_string(),
+        ..ImportedBook::default()
+    };
+
+    let secondary_authors: Vec<&str> = columns.get(record, SECONDARY_AUTHORS).split('|').collect();
+    let secondary_roles: Vec<&str> = columns.get(record, SECONDARY_ROLES).split('|').collect();
+    for (index, name) in secondary_authors.iter().enumerate() {
+        if let Some(name) = non_empty(name) {
+            if let Some(role) = role(secondary_roles.get(index).unwrap_or(&"")) {
+                book.contributors.push((name, role));
+            }
+        }
+    }
+
+    book.isbn = first_isbn(columns.get(record, ISBN));
+    book.publicationdate = columns.get(record, DATE).to_string();
+    book.page_count = parse_page_count(columns.get(record, PAGES));
+    book.rating = parse_rating(columns.get(record, RATING));
+    book.review = non_empty(columns.get(record, REVIEW));
+    book.tags = split_list(columns.get(record, TAGS));
+    book.created_at = parse_date(columns.get(record, ENTRY_DATE));
+    book.dateacquired_stamp = parse_date(columns.get(record, ACQUIRED));
+    book.started_stamp = columns.optional(record, STARTED).map(parse_date);
+    book.finished_stamp = parse_date(columns.get(record, FINISHED));
+
+    let languages = match (columns.optional(record, LANGUAGES), columns.optional(record, SECONDARY_LANGUAGE)) {
+        (None, None) => None,
+        (languages, secondary) => {
+            let mut languages = split_list(languages.unwrap_or(""));
+            languages.extend(secondary.and_then(non_empty));
+            Some(languages)
+        }
+    };
+    let book = book_from_languages(book, languages, columns.optional(record, ORIGINAL_LANGUAGES).map(split_list));
+    check(book)
+}
+
+pub fn parse_tsv(content: &str) -> Result<Vec<ParsedRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers = reader.headers().map_err(|err| format!("Invalid TSV : {}", err))?;
+    let columns = Columns::new(headers);
+    if !columns.contains("title") {
+        return Err(String::from("Invalid LibraryThing TSV export : no title column"));
+    }
+
+    Ok(reader.records().enumerate().map(|(index, record)| ParsedRow {
+        // the header is the first line
+        row: index + 2,
+        book: match record {
+            Ok(record) => tsv_book(&columns, &record),
+            Err(err) => Err(format!("{}", err)),
+        },
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON_EXPORT: &str = r#"{
+        "162415042": {
+            "books_id": "162415042",
+            "title": "Le grand incendie de Londres",
+            "authors": [
+                {"lf": "Roubaud, Jacques", "fl": "Jacques Roubaud", "role": "Author"},
+                {"lf": "Waldrop, Rosmarie", "fl": "Rosmarie Waldrop", "role": "Translator"}
+            ],
+            "primaryauthor": "Jacques Roubaud",
+            "date": "1989",
+            "originalisbn": "2020104725",
+            "rating": 4.5,
+            "language": ["French"],
+            "originallanguage": ["French"],
+            "tags": ["oulipo", "memoirs"],
+            "entrydate": "2019-01-15",
+            "datefinished": "2019-02-01"
+        },
+        "162415043": {
+            "books_id": "162415043",
+            "authors": []
+        }
+    }"#;
+
+    #[test]
+    fn json() {
+        let rows = parse(JSON_EXPORT).unwrap();
+        assert_eq!(rows.len(), 2);
+        let book = rows[0].book.clone().unwrap();
+        assert_eq!(book.librarything_id, Some(String::from("162415042")));
+        assert_eq!(book.author, "Roubaud, Jacques");
+        assert_eq!(book.contributors, vec![(String::from("Waldrop, Rosmarie"), Role::Translator)]);
+        assert_eq!(book.isbn, "2020104725");
+        assert_eq!(book.rating, Some(5));
+        assert_eq!(book.language_main, Some(String::from("French")));
+        assert_eq!(book.tags, vec![String::from("oulipo"), String::from("memoirs")]);
+        assert!(book.finished_stamp.is_some());
+        assert_eq!(book.started_stamp, None);
+        assert_eq!(rows[1].book, Err(String::from("title is missing")));
+    }
+
+    #[test]
+    fn tsv() {
+        let export = "Book Id\tTitle\tPrimary Author\tSecondary Author\tSecondary Author Roles\tDate\tRating\tTags\tLanguages\tOriginal Languages\tISBNs\tDate Read\n\
+                      162415042\tLa Vie mode d'emploi\tPerec, Georges\tBellos, David\tTranslator\t1978\t5\tnovel, oulipo\tEnglish\tFrench\t[0879237511, 9780879237516]\t2020-03-04\n";
+        let rows = parse(export).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].row, 2);
+        let book = rows[0].book.clone().unwrap();
+        assert_eq!(book.title, "La Vie mode d'emploi");
+        assert_eq!(book.author, "Perec, Georges");
+        assert_eq!(book.contributors, vec![(String::from("Bellos, David"), Role::Translator)]);
+        assert_eq!(book.isbn, "0879237511");
+        assert_eq!(book.tags, vec![String::from("novel"), String::from("oulipo")]);
+        assert_eq!(book.language_main, Some(String::from("English")));
+        assert_eq!(book.language_secondary, Some(None));
+        assert_eq!(book.language_original, Some(String::from("French")));
+        assert!(book.finished_stamp.is_some());
+        // no such column
+        assert_eq!(book.started_stamp, None);
+    }
+}
@@ -0,0 +1,254 @@
+// Books read from the exports of other applications
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::models::Role;
+
+pub mod goodreads;
+pub mod librarything;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    LibraryThing,
+    Goodreads,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "librarything" => Ok(ImportFormat::LibraryThing),
+            "goodreads" => Ok(ImportFormat::Goodreads),
+            _ => Err(format!("Unknown import format {}", format)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportedBook {
+    // id of the book in our CSV exports
+    pub kbooks_id: Option<i32>,
+    pub librarything_id: Option<String>,
+    pub title: String,
+    // primary author
+    pub author: String,
+    pub contributors: Vec<(String, Role)>,
+    pub isbn: String,
+    pub publicationdate: String,
+    pub page_count: Option<i32>,
+    pub rating: Option<i32>,
+    // None when the export has no such column : the stored value is kept
+    pub language_main: Option<String>,
+    pub language_secondary: Option<Option<String>>,
+    pub language_original: Option<String>,
+    pub review: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub dateacquired_stamp: Option<NaiveDateTime>,
+    // None when the export has no such column : the stored value is kept
+    pub started_stamp: Option<Option<NaiveDateTime>>,
+    pub finished_stamp: Option<NaiveDateTime>,
+    // Goodreads has no contributor roles : only the authors are replaced, the translators, editors... are kept
+    pub authors_only: bool,
+    // the tags are Goodreads shelves : they are added to the stored tags instead of replacing them
+    pub shelves: bool,
+}
+
+// A row of the imported file : the book, or the reason why it could not be read
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedRow {
+    pub row: usize,
+    pub book: Result<ImportedBook, String>,
+}
+
+pub fn parse(format: ImportFormat, data: &[u8]) -> Result<Vec<ParsedRow>, String> {
+    let content = decode(data)?;
+    match format {
+        ImportFormat::LibraryThing => librarything::parse(&content),
+        ImportFormat::Goodreads => goodreads::parse(&content),
+    }
+}
+
+// ---------------- Import report -------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Created,
+    Updated,
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportReportRow {
+    pub row: usize,
+    pub title: String,
+    pub status: ImportStatus,
+    pub book_id: Option<i32>,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub rows: Vec<ImportReportRow>,
+}
+
+impl ImportReport {
+    pub fn count(&self, status: ImportStatus) -> usize {
+        self.rows.iter().filter(|row| row.status == status).count()
+    }
+}
+
+// ---------------- Helpers -------------
+
+// Exports may be UTF-8 or UTF-16 (LibraryThing tab-delimited export), with a byte order mark
+pub fn decode(data: &[u8]) -> Result<String, String> {
+    let utf16 = |bytes: &[u8], little_endian: bool| {
+        let units: Vec<u16> = bytes.chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| if little_endian {
+                u16::from_le_bytes([pair[0], pair[1]])
+            } else {
+                u16::from_be_bytes([pair[0], pair[1]])
+            })
+            .collect();
+        String::from_utf16(&units).map_err(|_| String::from("Invalid UTF-16 content"))
+    };
+    if data.starts_with(&[0xFF, 0xFE]) {
+        utf16(&data[2..], true)
+    } else if data.starts_with(&[0xFE, 0xFF]) {
+        utf16(&data[2..], false)
+    } else if data.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8(data[3..].to_vec()).map_err(|_| String::from("Invalid UTF-8 content"))
+    } else {
+        String::from_utf8(data.to_vec()).map_err(|_| String::from("Invalid UTF-8 content"))
+    }
+}
+
+// "380", "380 p.", "xii, 380 pages" : the last number is the page count
+pub fn parse_page_count(pages: &str) -> Option<i32> {
+    pages.split(|c: char| !c.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .last()
+        .and_then(|number| number.parse::<i32>().ok())
+        .filter(|count| *count > 0)
+}
+
+// Accepts dates with or without time
+pub fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    let date = date.trim();
+    if date.is_empty() {
+        return None;
+    }
+    for format in &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y/%m/%d %H:%M:%S"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(date, format) {
+            return Some(datetime);
+        }
+    }
+    for format in &["%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y"] {
+        if let Ok(day) = NaiveDate::parse_from_str(date, format) {
+            return Some(day.and_hms(0, 0, 0));
+        }
+    }
+    None
+}
+
+// Ratings are stored from 0 to 5 stars, half stars are rounded
+pub fn parse_rating(rating: &str) -> Option<i32> {
+    match rating.trim().parse::<f32>() {
+        Ok(stars) if stars > 0.0 && stars <= 5.0 => Some(stars.round() as i32),
+        _ => None,
+    }
+}
+
+pub fn check(book: ImportedBook) -> Result<ImportedBook, String> {
+    if book.title.is_empty() {
+        return Err(String::from("title is missing"));
+    }
+    if book.author.is_empty() {
+        return Err(String::from("author is missing"));
+    }
+    Ok(book)
+}
+
+// Columns of a CSV or TSV export, found by their (lowercased) names
+pub struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    pub fn new(headers: &csv::StringRecord) -> Columns {
+        Columns(headers.iter().enumerate()
+            .map(|(index, name)| (name.trim().trim_matches('\'').to_lowercase(), index))
+            .collect())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    // As get, None when the export has none of these columns
+    pub fn optional<'a>(&self, record: &'a csv::StringRecord, names: &[&str]) -> Option<&'a str> {
+        if names.iter().any(|name| self.contains(name)) {
+            Some(self.get(record, names))
+        } else {
+            None
+        }
+    }
+
+    // Value of the first non empty column among the given names
+    pub fn get<'a>(&self, record: &'a csv::StringRecord, names: &[&str]) -> &'a str {
+        names.iter()
+            .filter_map(|name| self.0.get(*name))
+            .filter_map(|index| record.get(*index))
+            .map(|value| value.trim())
+            .find(|value| !value.is_empty())
+            .unwrap_or("")
+    }
+}
+
+pub fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_utf16() {
+        let mut data = vec![0xFF, 0xFE];
+        for unit in "Éloge".encode_utf16() {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+        assert_eq!(decode(&data), Ok(String::from("Éloge")));
+        assert_eq!(decode("Éloge".as_bytes()), Ok(String::from("Éloge")));
+    }
+
+    #[test]
+    fn dates_and_ratings() {
+        assert_eq!(parse_date("2019-01-15"), Some(NaiveDate::from_ymd(2019, 1, 15).and_hms(0, 0, 0)));
+        assert_eq!(parse_date("2019/01/15 10:12:00"), Some(NaiveDate::from_ymd(2019, 1, 15).and_hms(10, 12, 0)));
+        assert_eq!(parse_date("sometime"), None);
+        assert_eq!(parse_rating("4.5"), Some(5));
+        assert_eq!(parse_rating("3"), Some(3));
+        assert_eq!(parse_rating("0"), None);
+        assert_eq!(parse_rating(""), None);
+    }
+
+    #[test]
+    fn page_counts() {
+        assert_eq!(parse_page_count("380"), Some(380));
+        assert_eq!(parse_page_count("380 p."), Some(380));
+        assert_eq!(parse_page_count("xii, 380"), Some(380));
+        assert_eq!(parse_page_count("0"), None);
+        assert_eq!(parse_page_count(""), None);
+    }
+}
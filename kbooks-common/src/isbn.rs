@@ -0,0 +1,132 @@
+// ISBN-10 and ISBN-13 checks and conversions
+
+// Removes hyphens and spaces : "978-2-07-036822-8" gives "9782070368228"
+pub fn normalize(isbn: &str) -> String {
+    isbn.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+// Weights 10 to 1, the last character may be X (10)
+pub fn is_valid_isbn10(isbn: &str) -> bool {
+    let chars: Vec<char> = isbn.chars().collect();
+    if chars.len() != 10 {
+        return false;
+    }
+    let mut sum = 0;
+    for (index, c) in chars.iter().enumerate() {
+        let value = match c.to_digit(10) {
+            Some(digit) => digit,
+            None if *c == 'X' && index == 9 => 10,
+            None => return false,
+        };
+        sum += value * (10 - index as u32);
+    }
+    sum % 11 == 0
+}
+
+// Weights alternating 1 and 3, with a 978 or 979 (Bookland) prefix
+pub fn is_valid_isbn13(isbn: &str) -> bool {
+    if isbn.len() != 13 || !isbn.is_ascii() || !(isbn.starts_with("978") || isbn.starts_with("979")) {
+        return false;
+    }
+    match isbn13_check_digit(&isbn[..12]) {
+        Some(check) => isbn[12..].chars().next().and_then(|c| c.to_digit(10)) == Some(check),
+        None => false,
+    }
+}
+
+fn isbn13_check_digit(first_digits: &str) -> Option<u32> {
+    let mut sum = 0;
+    for (index, c) in first_digits.chars().enumerate() {
+        let digit = c.to_digit(10)?;
+        sum += if index % 2 == 0 { digit } else { digit * 3 };
+    }
+    Some((10 - sum % 10) % 10)
+}
+
+fn isbn10_check_digit(first_digits: &str) -> Option<char> {
+    let mut sum = 0;
+    for (index, c) in first_digits.chars().enumerate() {
+        sum += c.to_digit(10)? * (10 - index as u32);
+    }
+    match (11 - sum % 11) % 11 {
+        10 => Some('X'),
+        check => std::char::from_digit(check, 10),
+    }
+}
+
+// Normalized ISBN, or the reason why it is not one
+pub fn validate(isbn: &str) -> Result<String, String> {
+    let isbn = normalize(isbn);
+    match isbn.len() {
+        10 if is_valid_isbn10(&isbn) => Ok(isbn),
+        13 if is_valid_isbn13(&isbn) => Ok(isbn),
+        10 | 13 => Err(String::from("invalid ISBN check digit")),
+        _ => Err(String::from("an ISBN has 10 or 13 digits")),
+    }
+}
+
+pub fn to_isbn13(isbn10: &str) -> Option<String> {
+    if !is_valid_isbn10(isbn10) {
+        return None;
+    }
+    let first_digits = format!("978{}", &isbn10[..9]);
+    let check = isbn13_check_digit(&first_digits)?;
+    Some(format!("{}{}", first_digits, check))
+}
+
+// ISBNs with a 979 prefix have no ISBN-10 form
+pub fn to_isbn10(isbn13: &str) -> Option<String> {
+    if !is_valid_isbn13(isbn13) || !isbn13.starts_with("978") {
+        return None;
+    }
+    let first_digits = &isbn13[3..12];
+    let check = isbn10_check_digit(first_digits)?;
+    Some(format!("{}{}", first_digits, check))
+}
+
+// Every form under which a book may have been recorded, to look it up
+pub fn variants(isbn: &str) -> Vec<String> {
+    let isbn = normalize(isbn);
+    let other = if isbn.len() == 10 { to_isbn13(&isbn) } else { to_isbn10(&isbn) };
+    let mut variants = vec![isbn];
+    variants.extend(other);
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits() {
+        assert!(is_valid_isbn10("207036822X"));
+        assert!(is_valid_isbn10("080442957X"));
+        assert!(!is_valid_isbn10("2070368228"));
+        assert!(is_valid_isbn13("9782070368228"));
+        assert!(is_valid_isbn13("9791032100455"));
+        assert!(!is_valid_isbn13("978207036822X"));
+        assert!(!is_valid_isbn13("1234564654654"));
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(validate("978-2-07-036822-8"), Ok(String::from("9782070368228")));
+        assert_eq!(validate(" 0-8044-2957-x "), Ok(String::from("080442957X")));
+        assert_eq!(validate("1234564654654654645"), Err(String::from("an ISBN has 10 or 13 digits")));
+        assert_eq!(validate("2070368228"), Err(String::from("invalid ISBN check digit")));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(to_isbn13("207036822X"), Some(String::from("9782070368228")));
+        assert_eq!(to_isbn10("9782070368228"), Some(String::from("207036822X")));
+        assert_eq!(to_isbn13("080442957X"), Some(String::from("9780804429573")));
+        assert_eq!(to_isbn10("9780804429573"), Some(String::from("080442957X")));
+        assert_eq!(to_isbn10("9791032100455"), None);
+        assert_eq!(variants("978-2-07-036822-8"), vec![String::from("9782070368228"), String::from("207036822X")]);
+        assert_eq!(variants("9791032100455"), vec![String::from("9791032100455")]);
+    }
+}
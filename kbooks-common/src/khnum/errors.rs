@@ -16,6 +16,9 @@ pub enum ServiceError {
 
     #[display(fmt = "Unauthorized: {}", _0)]
     Unauthorized(String),
+
+    #[display(fmt = "NotFound: {}", _0)]
+    NotFound(String),
 }
 
 // we can return early in our handlers if UUID provided by the user is not valid
@@ -39,6 +42,7 @@ impl From<DBError> for ServiceError {
                 // }
                 // ServiceError::InternalServerError
             }
+            DBError::NotFound => ServiceError::NotFound("Not found".into()),
             _ => {
                 // println!("debug: default error {:?}", error);
                 ServiceError::InternalServerError
@@ -59,6 +63,9 @@ impl ResponseError for ServiceError {
             ServiceError::Unauthorized(ref message) => {
                 HttpResponse::Unauthorized().json(message)
             }
+            ServiceError::NotFound(ref message) => {
+                HttpResponse::NotFound().json(message)
+            }
         }
     }
 }
@@ -60,3 +60,13 @@ pub fn fetch(pool: DbPool, email: &str, login: &str) -> Result<Vec<SlimUser>, DB
     ).load::<User>(conn)?;
     return Ok(items.into_iter().map(|item| item.into()).collect());
 }
+
+pub fn get_by_login(pool: DbPool, login: &str) -> Result<User, DBError> {
+    let conn = &pool.get().unwrap();
+    dsl::users.filter(dsl::login.eq(login)).first::<User>(conn)
+}
+
+pub fn get(pool: DbPool, user_id: i32) -> Result<User, DBError> {
+    let conn = &pool.get().unwrap();
+    dsl::users.filter(dsl::id.eq(user_id)).first::<User>(conn)
+}
@@ -11,12 +11,16 @@ use diesel::r2d2;
 // #[cfg(test)]
 #[cfg(feature = "test")]
 pub type MyConnection = SqliteConnection;
+#[cfg(feature = "test")]
+pub type MyBackend = diesel::sqlite::Sqlite;
 
 // #[cfg(not(feature = "diesel/sqlite"))]
 // #[cfg(all(not(feature = "diesel/sqlite"), feature = "diesel/postgres"))]
 // #[cfg(not(test))]
 #[cfg(not(feature = "test"))]
 pub type MyConnection = PgConnection;
+#[cfg(not(feature = "test"))]
+pub type MyBackend = diesel::pg::Pg;
 
 pub type DbPool = r2d2::Pool<r2d2::ConnectionManager<MyConnection>>;
 
@@ -78,8 +82,11 @@ embed_migrations!("../migrations/sqlite");
 pub fn test_conn_init() -> DbPool {
     let manager = r2d2::ConnectionManager::<MyConnection>::new(":memory:");
     let pool = r2d2::Pool::builder().max_size(2).build(manager).expect("Failed to create pool.");
-    let conn = &pool.get().unwrap();
-    let _res = embedded_migrations::run(conn);
+    {
+        let conn = &pool.get().unwrap();
+        let _res = embedded_migrations::run(conn);
+    }
+    crate::repository::data_migration_handler::run(pool.clone()).expect("Failed to run data migrations.");
     pool
 }
 
@@ -0,0 +1,315 @@
+// Book languages, stored as ISO 639-1 codes, or ISO 639-3 codes for the languages without one (ancient languages)
+use crate::author::fold_accents;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Language {
+    // ISO 639-1 when available, ISO 639-3 otherwise
+    pub code: &'static str,
+    pub iso639_3: &'static str,
+    // bibliographic ISO 639-2 code, when it differs from the ISO 639-3 one (used by library catalogs)
+    pub iso639_2b: Option<&'static str>,
+    // English name, translated by the gettext catalogs
+    pub name: &'static str,
+    // other names accepted as input : French and native names, LibraryThing names
+    pub aliases: &'static [&'static str],
+}
+
+const fn language(code: &'static str, iso639_3: &'static str, iso639_2b: Option<&'static str>, name: &'static str, aliases: &'static [&'static str]) -> Language {
+    Language { code, iso639_3, iso639_2b, name, aliases }
+}
+
+// Every ISO 639-1 language, then the ancient languages read in library catalogs
+pub const LANGUAGES: &[Language] = &[
+    language("aa", "aar", None, "Afar", &[]),
+    language("ab", "abk", None, "Abkhazian", &["abkhaze", "аԥсуа"]),
+    language("ae", "ave", None, "Avestan", &["avestique"]),
+    language("af", "afr", None, "Afrikaans", &[]),
+    language("ak", "aka", None, "Akan", &[]),
+    language("am", "amh", None, "Amharic", &["amharique", "አማርኛ"]),
+    language("an", "arg", None, "Aragonese", &["aragonais", "aragonés"]),
+    language("ar", "ara", None, "Arabic", &["arabe", "العربية"]),
+    language("as", "asm", None, "Assamese", &["assamais"]),
+    language("av", "ava", None, "Avaric", &["avar"]),
+    language("ay", "aym", None, "Aymara", &[]),
+    language("az", "aze", None, "Azerbaijani", &["azéri", "azərbaycan"]),
+    language("ba", "bak", None, "Bashkir", &["bachkir"]),
+    language("be", "bel", None, "Belarusian", &["biélorusse", "беларуская"]),
+    language("bg", "bul", None, "Bulgarian", &["bulgare", "български"]),
+    // collective code : the Bihari languages have no ISO 639-3 code of their own
+    language("bh", "bih", None, "Bihari languages", &["langues biharis", "bihari"]),
+    language("bi", "bis", None, "Bislama", &["bichelamar"]),
+    language("bm", "bam", None, "Bambara", &[]),
+    language("bn", "ben", None, "Bengali", &["bangla", "বাংলা"]),
+    language("bo", "bod", Some("tib"), "Tibetan", &["tibétain"]),
+    language("br", "bre", None, "Breton", &["brezhoneg"]),
+    language("bs", "bos", None, "Bosnian", &["bosniaque", "bosanski"]),
+    language("ca", "cat", None, "Catalan", &["català"]),
+    language("ce", "che", None, "Chechen", &["tchétchène"]),
+    language("ch", "cha", None, "Chamorro", &[]),
+    language("co", "cos", None, "Corsican", &["corse", "corsu"]),
+    language("cr", "cre", None, "Cree", &["cri"]),
+    language("cs", "ces", Some("cze"), "Czech", &["tchèque", "čeština"]),
+    language("cu", "chu", None, "Church Slavonic", &["slavon d'église", "old church slavonic", "vieux slave"]),
+    language("cv", "chv", None, "Chuvash", &["tchouvache"]),
+    language("cy", "cym", Some("wel"), "Welsh", &["gallois", "cymraeg"]),
+    language("da", "dan", None, "Danish", &["danois", "dansk"]),
+    language("de", "deu", Some("ger"), "German", &["allemand", "deutsch"]),
+    language("dv", "div", None, "Divehi", &["maldivien", "dhivehi", "maldivian"]),
+    language("dz", "dzo", None, "Dzongkha", &[]),
+    language("ee", "ewe", None, "Ewe", &[]),
+    language("el", "ell", Some("gre"), "Greek", &["grec", "grec moderne", "greek, modern (1453-)", "ελληνικά"]),
+    language("en", "eng", None, "English", &["anglais"]),
+    language("eo", "epo", None, "Esperanto", &["espéranto"]),
+    language("es", "spa", None, "Spanish", &["espagnol", "español", "castellano"]),
+    language("et", "est", None, "Estonian", &["estonien", "eesti"]),
+    language("eu", "eus", Some("baq"), "Basque", &["euskara"]),
+    language("fa", "fas", Some("per"), "Persian", &["persan", "farsi", "فارسی"]),
+    language("ff", "ful", None, "Fulah", &["peul", "fula"]),
+    language("fi", "fin", None, "Finnish", &["finnois", "suomi"]),
+    language("fj", "fij", None, "Fijian", &["fidjien"]),
+    language("fo", "fao", None, "Faroese", &["féroïen", "føroyskt"]),
+    language("fr", "fra", Some("fre"), "French", &["français"]),
+    language("fy", "fry", None, "Western Frisian", &["frison occidental", "frisian", "frison"]),
+    language("ga", "gle", None, "Irish", &["irlandais", "gaeilge"]),
+    language("gd", "gla", None, "Scottish Gaelic", &["gaélique écossais", "gaelic", "gàidhlig"]),
+    language("gl", "glg", None, "Galician", &["galicien", "galego"]),
+    language("gn", "grn", None, "Guarani", &[]),
+    language("gu", "guj", None, "Gujarati", &["goudjarati", "ગુજરાતી"]),
+    language("gv", "glv", None, "Manx", &["mannois"]),
+    language("ha", "hau", None, "Hausa", &["haoussa"]),
+    language("he", "heb", None, "Hebrew", &["hébreu", "עברית"]),
+    language("hi", "hin", None, "Hindi", &["हिन्दी"]),
+    language("ho", "hmo", None, "Hiri Motu", &[]),
+    language("hr", "hrv", None, "Croatian", &["croate", "hrvatski"]),
+    language("ht", "hat", None, "Haitian Creole", &["créole haïtien", "haitian"]),
+    language("hu", "hun", None, "Hungarian", &["hongrois", "magyar"]),
+    language("hy", "hye", Some("arm"), "Armenian", &["arménien", "հայերեն"]),
+    language("hz", "her", None, "Herero", &[]),
+    language("ia", "ina", None, "Interlingua", &[]),
+    language("id", "ind", None, "Indonesian", &["indonésien", "bahasa indonesia"]),
+    language("ie", "ile", None, "Interlingue", &["occidental"]),
+    language("ig", "ibo", None, "Igbo", &[]),
+    language("ii", "iii", None, "Sichuan Yi", &["yi du Sichuan", "nuosu"]),
+    language("ik", "ipk", None, "Inupiaq", &[]),
+    language("io", "ido", None, "Ido", &[]),
+    language("is", "isl", Some("ice"), "Icelandic", &["islandais", "íslenska"]),
+    language("it", "ita", None, "Italian", &["italien", "italiano"]),
+    language("iu", "iku", None, "Inuktitut", &[]),
+    language("ja", "jpn", None, "Japanese", &["japonais", "日本語"]),
+    language("jv", "jav", None, "Javanese", &["javanais", "basa jawa"]),
+    language("ka", "kat", Some("geo"), "Georgian", &["géorgien", "ქართული"]),
+    language("kg", "kon", None, "Kongo", &["kikongo"]),
+    language("ki", "kik", None, "Kikuyu", &[]),
+    language("kj", "kua", None, "Kuanyama", &[]),
+    language("kk", "kaz", None, "Kazakh", &["қазақ"]),
+    language("kl", "kal", None, "Kalaallisut", &["groenlandais", "greenlandic"]),
+    language("km", "khm", None, "Khmer", &[]),
+    language("kn", "kan", None, "Kannada", &[]),
+    language("ko", "kor", None, "Korean", &["coréen", "한국어"]),
+    language("kr", "kau", None, "Kanuri", &["kanouri"]),
+    language("ks", "kas", None, "Kashmiri", &["cachemiri"]),
+    language("ku", "kur", None, "Kurdish", &["kurde", "kurdî"]),
+    language("kv", "kom", None, "Komi", &[]),
+    language("kw", "cor", None, "Cornish", &["cornique", "kernewek"]),
+    language("ky", "kir", None, "Kyrgyz", &["kirghiz"]),
+    language("la", "lat", None, "Latin", &["latina"]),
+    language("lb", "ltz", None, "Luxembourgish", &["luxembourgeois", "lëtzebuergesch"]),
+    language("lg", "lug", None, "Ganda", &["luganda"]),
+    language("li", "lim", None, "Limburgish", &["limbourgeois", "limburgan"]),
+    language("ln", "lin", None, "Lingala", &[]),
+    language("lo", "lao", None, "Lao", &[]),
+    language("lt", "lit", None, "Lithuanian", &["lituanien", "lietuvių"]),
+    language("lu", "lub", None, "Luba-Katanga", &[]),
+    language("lv", "lav", None, "Latvian", &["letton", "latviešu"]),
+    language("mg", "mlg", None, "Malagasy", &["malgache"]),
+    language("mh", "mah", None, "Marshallese", &["marshallais"]),
+    language("mi", "mri", Some("mao"), "Maori", &["māori"]),
+    language("mk", "mkd", Some("mac"), "Macedonian", &["macédonien", "македонски"]),
+    language("ml", "mal", None, "Malayalam", &[]),
+    language("mn", "mon", None, "Mongolian", &["mongol"]),
+    language("mr", "mar", None, "Marathi", &[]),
+    language("ms", "msa", Some("may"), "Malay", &["malais", "bahasa melayu"]),
+    language("mt", "mlt", None, "Maltese", &["maltais", "malti"]),
+    language("my", "mya", Some("bur"), "Burmese", &["birman"]),
+    language("na", "nau", None, "Nauru", &["nauruan"]),
+    language("nb", "nob", None, "Norwegian Bokmål", &["norvégien bokmål", "bokmål"]),
+    language("nd", "nde", None, "North Ndebele", &["ndébélé du Nord"]),
+    language("ne", "nep", None, "Nepali", &["népalais"]),
+    language("ng", "ndo", None, "Ndonga", &[]),
+    language("nl", "nld", Some("dut"), "Dutch", &["néerlandais", "nederlands", "flemish", "flamand"]),
+    language("nn", "nno", None, "Norwegian Nynorsk", &["norvégien nynorsk", "nynorsk"]),
+    language("no", "nor", None, "Norwegian", &["norvégien", "norsk"]),
+    language("nr", "nbl", None, "South Ndebele", &["ndébélé du Sud"]),
+    language("nv", "nav", None, "Navajo", &[]),
+    language("ny", "nya", None, "Chichewa", &["nyanja"]),
+    language("oc", "oci", None, "Occitan", &["occitan (post 1500)"]),
+    language("oj", "oji", None, "Ojibwa", &["ojibwé"]),
+    language("om", "orm", None, "Oromo", &[]),
+    language("or", "ori", None, "Oriya", &["odia"]),
+    language("os", "oss", None, "Ossetian", &["ossète"]),
+    language("pa", "pan", None, "Punjabi", &["pendjabi", "panjabi"]),
+    language("pi", "pli", None, "Pali", &[]),
+    language("pl", "pol", None, "Polish", &["polonais", "polski"]),
+    language("ps", "pus", None, "Pashto", &["pachto", "pushto"]),
+    language("pt", "por", None, "Portuguese", &["portugais", "português"]),
+    language("qu", "que", None, "Quechua", &[]),
+    language("rm", "roh", None, "Romansh", &["romanche", "rumantsch"]),
+    language("rn", "run", None, "Rundi", &["kirundi"]),
+    language("ro", "ron", Some("rum"), "Romanian", &["roumain", "română"]),
+    language("ru", "rus", None, "Russian", &["russe", "русский"]),
+    language("rw", "kin", None, "Kinyarwanda", &[]),
+    language("sa", "san", None, "Sanskrit", &["sanskrit", "संस्कृतम्"]),
+    language("sc", "srd", None, "Sardinian", &["sarde", "sardu"]),
+    language("sd", "snd", None, "Sindhi", &[]),
+    language("se", "sme", None, "Northern Sami", &["same du Nord", "sami"]),
+    language("sg", "sag", None, "Sango", &[]),
+    language("si", "sin", None, "Sinhala", &["cingalais", "sinhalese"]),
+    language("sk", "slk", Some("slo"), "Slovak", &["slovaque", "slovenčina"]),
+    language("sl", "slv", None, "Slovenian", &["slovène", "slovenščina"]),
+    language("sm", "smo", None, "Samoan", &[]),
+    language("sn", "sna", None, "Shona", &[]),
+    language("so", "som", None, "Somali", &[]),
+    language("sq", "sqi", Some("alb"), "Albanian", &["albanais", "shqip"]),
+    language("sr", "srp", None, "Serbian", &["serbe", "српски"]),
+    language("ss", "ssw", None, "Swati", &[]),
+    language("st", "sot", None, "Southern Sotho", &["sotho du Sud", "sesotho"]),
+    language("su", "sun", None, "Sundanese", &["soundanais"]),
+    language("sv", "swe", None, "Swedish", &["suédois", "svenska"]),
+    language("sw", "swa", None, "Swahili", &["kiswahili"]),
+    language("ta", "tam", None, "Tamil", &["tamoul", "தமிழ்"]),
+    language("te", "tel", None, "Telugu", &["télougou"]),
+    language("tg", "tgk", None, "Tajik", &["tadjik"]),
+    language("th", "tha", None, "Thai", &["ไทย"]),
+    language("ti", "tir", None, "Tigrinya", &["tigrigna"]),
+    language("tk", "tuk", None, "Turkmen", &["turkmène"]),
+    language("tl", "tgl", None, "Tagalog", &["filipino"]),
+    language("tn", "tsn", None, "Tswana", &[]),
+    language("to", "ton", None, "Tongan", &["tongien"]),
+    language("tr", "tur", None, "Turkish", &["turc", "türkçe"]),
+    language("ts", "tso", None, "Tsonga", &[]),
+    language("tt", "tat", None, "Tatar", &[]),
+    language("tw", "twi", None, "Twi", &[]),
+    language("ty", "tah", None, "Tahitian", &["tahitien"]),
+    language("ug", "uig", None, "Uyghur", &["ouïghour", "uighur"]),
+    language("uk", "ukr", None, "Ukrainian", &["ukrainien", "українська"]),
+    language("ur", "urd", None, "Urdu", &["ourdou", "اردو"]),
+    language("uz", "uzb", None, "Uzbek", &["ouzbek", "oʻzbek"]),
+    language("ve", "ven", None, "Venda", &[]),
+    language("vi", "vie", None, "Vietnamese", &["vietnamien", "tiếng việt"]),
+    language("vo", "vol", None, "Volapük", &[]),
+    language("wa", "wln", None, "Walloon", &["wallon", "walon"]),
+    language("wo", "wol", None, "Wolof", &[]),
+    language("xh", "xho", None, "Xhosa", &[]),
+    language("yi", "yid", None, "Yiddish", &["yiddish", "ייִדיש"]),
+    language("yo", "yor", None, "Yoruba", &[]),
+    language("za", "zha", None, "Zhuang", &[]),
+    language("zh", "zho", Some("chi"), "Chinese", &["chinois", "中文"]),
+    language("zu", "zul", None, "Zulu", &["zoulou", "isizulu"]),
+    // no ISO 639-1 code
+    language("ang", "ang", None, "Old English", &["vieil anglais", "anglo-saxon", "english, old (ca. 450-1100)"]),
+    language("enm", "enm", None, "Middle English", &["moyen anglais", "english, middle (1100-1500)"]),
+    language("fro", "fro", None, "Old French", &["ancien français", "french, old (842-ca. 1400)"]),
+    language("frm", "frm", None, "Middle French", &["moyen français", "french, middle (ca. 1400-1600)"]),
+    language("grc", "grc", None, "Ancient Greek", &["grec ancien", "greek (ancient)", "greek, ancient (to 1453)"]),
+    language("non", "non", None, "Old Norse", &["vieux norrois", "norse, old"]),
+];
+
+fn simplified(text: &str) -> String {
+    fold_accents(&text.trim().to_lowercase())
+}
+
+pub fn find(code: &str) -> Option<&'static Language> {
+    LANGUAGES.iter().find(|language| language.code == code)
+}
+
+// Language from any of its codes or names, whatever the case and accents : "FR", "fre", "French", "français"
+pub fn lookup(input: &str) -> Option<&'static Language> {
+    let input = simplified(input);
+    if input.is_empty() {
+        return None;
+    }
+    LANGUAGES.iter().find(|language| {
+        language.code == input
+            || language.iso639_3 == input
+            || language.iso639_2b == Some(input.as_str())
+            || simplified(language.name) == input
+            || language.aliases.iter().any(|alias| simplified(alias) == input)
+    })
+}
+
+// Code of a language given by the user, empty when no language is given
+pub fn normalize(input: &str) -> Result<String, String> {
+    if input.trim().is_empty() {
+        return Ok(String::new());
+    }
+    lookup(input)
+        .map(|language| language.code.to_string())
+        .ok_or_else(|| format!("unknown language {}", input.trim()))
+}
+
+// Code of a known language, or the input itself (imports keep what they can not read)
+pub fn normalize_or_keep(input: &str) -> String {
+    match lookup(input) {
+        Some(language) => language.code.to_string(),
+        None => input.trim().to_string(),
+    }
+}
+
+// Language of a user interface locale : "fr", "en_US"
+pub fn from_locale(locale: &str) -> Option<&'static Language> {
+    locale.split(|c| c == '_' || c == '-').next().and_then(lookup)
+}
+
+// English name of a known code, the key of its translations
+pub fn name(code: &str) -> Option<&'static str> {
+    find(code).map(|language| language.name)
+}
+
+// English name of a code, or the code itself when unknown
+pub fn english_name(code: &str) -> String {
+    name(code).unwrap_or(code).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalization() {
+        assert_eq!(normalize("FR"), Ok(String::from("fr")));
+        assert_eq!(normalize("fre"), Ok(String::from("fr")));
+        assert_eq!(normalize("fra"), Ok(String::from("fr")));
+        assert_eq!(normalize("French"), Ok(String::from("fr")));
+        assert_eq!(normalize(" Francais "), Ok(String::from("fr")));
+        assert_eq!(normalize("Greek (Ancient)"), Ok(String::from("grc")));
+        assert_eq!(normalize("tha"), Ok(String::from("th")));
+        assert_eq!(normalize("geo"), Ok(String::from("ka")));
+        assert_eq!(normalize("Norwegian Bokmål"), Ok(String::from("nb")));
+        assert_eq!(normalize(""), Ok(String::new()));
+        assert_eq!(normalize("Klingon"), Err(String::from("unknown language Klingon")));
+        assert_eq!(normalize_or_keep("Klingon"), "Klingon");
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(english_name("de"), "German");
+        assert_eq!(english_name("tlh"), "tlh");
+        assert_eq!(name("grc"), Some("Ancient Greek"));
+        assert_eq!(name("tlh"), None);
+    }
+
+    #[test]
+    fn locales() {
+        assert_eq!(from_locale("en_US").map(|language| language.code), Some("en"));
+        assert_eq!(from_locale("FR").map(|language| language.code), Some("fr"));
+        assert_eq!(from_locale(""), None);
+    }
+
+    #[test]
+    fn unique_codes() {
+        for (index, language) in LANGUAGES.iter().enumerate() {
+            assert!(LANGUAGES[index + 1..].iter().all(|other| other.code != language.code), "{}", language.code);
+        }
+        assert_eq!(LANGUAGES.iter().filter(|language| language.code.len() == 2).count(), 184);
+    }
+}
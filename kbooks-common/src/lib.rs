@@ -10,6 +10,20 @@ pub mod khnum;
 pub mod schema;
 pub mod models;
 pub mod repository;
+pub mod author;
+pub mod book_filter;
+pub mod borrow;
+pub mod cover;
+pub mod goals;
+pub mod isbn;
+pub mod language;
+pub mod metadata;
+pub mod partial_date;
+pub mod placeholder;
+pub mod stats;
+pub mod storage;
+pub mod import;
+pub mod export;
 
 #[cfg(test)]
 mod tests {
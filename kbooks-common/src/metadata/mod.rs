@@ -0,0 +1,45 @@
+// Book data found from an ISBN by online catalogues.
+// The providers do no I/O : they give the URL to fetch and read the response,
+// the caller fetching it with the HTTP client of its runtime.
+
+pub mod openlibrary;
+
+pub use openlibrary::OpenLibrary;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BookMetadata {
+    pub isbn: String,
+    pub title: String,
+    // "Last, First", the primary author first
+    pub authors: Vec<String>,
+    // canonical form, see partial_date
+    pub publicationdate: String,
+    // language codes, or what the provider gave when unknown
+    pub language_main: String,
+    pub language_original: String,
+    pub page_count: Option<i32>,
+    // URL of the largest cover image of the provider
+    pub cover_url: Option<String>,
+    // name of the provider
+    pub source: String,
+}
+
+pub trait MetadataProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn url(&self, isbn: &str) -> String;
+
+    // None when the provider does not know the ISBN
+    fn parse(&self, isbn: &str, body: &[u8]) -> Result<Option<BookMetadata>, String>;
+}
+
+// Providers queried in order, until one of them knows the ISBN
+pub struct MetadataProviders {
+    pub providers: Vec<Box<dyn MetadataProvider>>,
+}
+
+impl MetadataProviders {
+    pub fn new(providers: Vec<Box<dyn MetadataProvider>>) -> MetadataProviders {
+        MetadataProviders { providers }
+    }
+}
@@ -0,0 +1,182 @@
+// Open Library books API : https://openlibrary.org/dev/docs/api/books
+use std::collections::HashMap;
+
+use crate::{author, language, partial_date};
+use crate::import::parse_page_count;
+use crate::metadata::{BookMetadata, MetadataProvider};
+
+pub const DEFAULT_BASE_URL: &str = "https://openlibrary.org";
+pub const DEFAULT_COVERS_URL: &str = "https://covers.openlibrary.org";
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    details: Details,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Details {
+    title: String,
+    authors: Vec<Named>,
+    // "1993", "March 1978", "Jun 02, 2009"...
+    publish_date: String,
+    number_of_pages: Option<i32>,
+    // "220 p.", when number_of_pages is missing
+    pagination: String,
+    languages: Vec<Keyed>,
+    translated_from: Vec<Keyed>,
+    covers: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+// Reference to another record, such as "/languages/fre"
+#[derive(Debug, Deserialize)]
+struct Keyed {
+    key: String,
+}
+
+fn language_code(keyed: &[Keyed]) -> String {
+    keyed.first()
+        .map(|keyed| language::normalize_or_keep(keyed.key.rsplit('/').next().unwrap_or("")))
+        .unwrap_or_default()
+}
+
+pub struct OpenLibrary {
+    base_url: String,
+    covers_url: String,
+}
+
+impl OpenLibrary {
+    // The URLs of the API and of the covers, without trailing slash
+    pub fn new(base_url: &str, covers_url: &str) -> OpenLibrary {
+        OpenLibrary {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            covers_url: covers_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl Default for OpenLibrary {
+    fn default() -> OpenLibrary {
+        OpenLibrary::new(DEFAULT_BASE_URL, DEFAULT_COVERS_URL)
+    }
+}
+
+impl MetadataProvider for OpenLibrary {
+    fn name(&self) -> &'static str {
+        "openlibrary"
+    }
+
+    fn url(&self, isbn: &str) -> String {
+        format!("{}/api/books?bibkeys=ISBN:{}&format=json&jscmd=details", self.base_url, isbn)
+    }
+
+    // The response is an object keyed by the requested bibkeys, empty when the ISBN is unknown
+    fn parse(&self, isbn: &str, body: &[u8]) -> Result<Option<BookMetadata>, String> {
+        let mut entries: HashMap<String, Entry> = serde_json::from_slice(body)
+            .map_err(|error| format!("Invalid Open Library response : {}", error))?;
+        let details = match entries.remove(&format!("ISBN:{}", isbn)) {
+            Some(entry) => entry.details,
+            None => return Ok(None),
+        };
+        let title = details.title.trim().to_string();
+        if title.is_empty() {
+            return Ok(None);
+        }
+        let language_main = language_code(&details.languages);
+        let language_original = match language_code(&details.translated_from) {
+            original if original.is_empty() => language_main.clone(),
+            original => original,
+        };
+        Ok(Some(BookMetadata {
+            isbn: isbn.to_string(),
+            title,
+            authors: details.authors.iter()
+                .map(|author| author::normalize_name(&author.name))
+                .filter(|name| !name.is_empty())
+                .collect(),
+            publicationdate: partial_date::canonical(&details.publish_date),
+            language_main,
+            language_original,
+            page_count: details.number_of_pages.filter(|count| *count > 0)
+                .or_else(|| parse_page_count(&details.pagination)),
+            cover_url: details.covers.iter()
+                .find(|id| **id > 0)
+                .map(|id| format!("{}/b/id/{}-L.jpg", self.covers_url, id)),
+            source: self.name().to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"{"ISBN:9782070368228": {
+        "bib_key": "ISBN:9782070368228",
+        "info_url": "https://openlibrary.org/books/OL1M/W_ou_le_souvenir_d'enfance",
+        "preview": "noview",
+        "thumbnail_url": "https://covers.openlibrary.org/b/id/1234-S.jpg",
+        "details": {
+            "title": "W ou le souvenir d'enfance",
+            "authors": [{"key": "/authors/OL1A", "name": "Georges Perec"}],
+            "publish_date": "March 1993",
+            "pagination": "220 p.",
+            "languages": [{"key": "/languages/fre"}],
+            "covers": [-1, 1234],
+            "isbn_13": ["9782070368228"]
+        }
+    }}"#;
+
+    #[test]
+    fn url() {
+        let provider = OpenLibrary::new("http://localhost:8080/", DEFAULT_COVERS_URL);
+        assert_eq!(provider.url("9782070368228"), "http://localhost:8080/api/books?bibkeys=ISBN:9782070368228&format=json&jscmd=details");
+    }
+
+    #[test]
+    fn parse() {
+        let provider = OpenLibrary::default();
+        let metadata = provider.parse("9782070368228", RESPONSE.as_bytes()).unwrap().unwrap();
+        assert_eq!(metadata, BookMetadata {
+            isbn: "9782070368228".to_string(),
+            title: "W ou le souvenir d'enfance".to_string(),
+            authors: vec!["Perec, Georges".to_string()],
+            publicationdate: "1993-03".to_string(),
+            language_main: "fr".to_string(),
+            language_original: "fr".to_string(),
+            page_count: Some(220),
+            cover_url: Some("https://covers.openlibrary.org/b/id/1234-L.jpg".to_string()),
+            source: "openlibrary".to_string(),
+        });
+    }
+
+    #[test]
+    fn translation() {
+        let response = r#"{"ISBN:0441478123": {"details": {
+            "title": "The Left Hand of Darkness",
+            "number_of_pages": 304,
+            "languages": [{"key": "/languages/eng"}],
+            "translated_from": [{"key": "/languages/xyz"}]
+        }}}"#;
+        let metadata = OpenLibrary::default().parse("0441478123", response.as_bytes()).unwrap().unwrap();
+        assert_eq!(metadata.authors, Vec::<String>::new());
+        assert_eq!(metadata.page_count, Some(304));
+        assert_eq!(metadata.language_main, "en");
+        assert_eq!(metadata.language_original, "xyz");
+        assert_eq!(metadata.publicationdate, "");
+        assert_eq!(metadata.cover_url, None);
+    }
+
+    #[test]
+    fn unknown() {
+        let provider = OpenLibrary::default();
+        assert_eq!(provider.parse("9782070368228", b"{}").unwrap(), None);
+        assert_eq!(provider.parse("9782070368228", RESPONSE.replace("9782070368228", "9780000000002").as_bytes()).unwrap(), None);
+        assert!(provider.parse("9782070368228", b"<html>").is_err());
+    }
+}
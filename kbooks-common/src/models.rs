@@ -1,8 +1,11 @@
 use diesel::{self,RunQueryDsl,QueryDsl,ExpressionMethods};
 use chrono::{Utc, NaiveDateTime};
-use crate::schema::books;
+use std::str::FromStr;
 
-#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+use crate::schema::{authors, book_contributors, book_tags, books, borrow_requests, goals, loans, reading_progress, readings, shelf_books, shelves, tags, wishes};
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable,QueryableByName)]
+#[table_name="books"]
 pub struct Book {
     pub id: i32,
     pub user_id: i32,
@@ -17,12 +20,19 @@ pub struct Book {
     pub language_secondary: Option<String>,
     pub language_original: String,
     pub review: Option<String>,
+    // storage key of the cover, empty without cover
     pub cover: String,
-    // pub tags: Option<Vec<String>>,
     pub created_at: NaiveDateTime,
     pub dateacquired_stamp: Option<NaiveDateTime>,
+    // dates of the last reading, kept by reading_handler
     pub started_stamp: Option<NaiveDateTime>,
-    pub finished_stamp: Option<NaiveDateTime>
+    pub finished_stamp: Option<NaiveDateTime>,
+    pub author_id: Option<i32>,
+    // from the publication date, see partial_date
+    pub publication_sort: Option<i32>,
+    // see ReadingStatus
+    pub reading_status: String,
+    pub page_count: Option<i32>,
 }
 
 impl Book {
@@ -44,8 +54,12 @@ impl Book {
             cover: "".to_string(),
             created_at: Utc::now().naive_utc(),
             dateacquired_stamp: Some(Utc::now().naive_utc()),
-            started_stamp: Some(Utc::now().naive_utc()),
-            finished_stamp: Some(Utc::now().naive_utc()),
+            started_stamp: None,
+            finished_stamp: None,
+            author_id: None,
+            publication_sort: None,
+            reading_status: ReadingStatus::ToRead.as_str().to_string(),
+            page_count: None,
         }
     }
 }
@@ -68,9 +82,522 @@ pub struct NewBook {
     pub language_original: String,
     pub review: Option<String>,
     pub cover: String,
-    // pub tags: Option<Vec<String>>,
     pub created_at: NaiveDateTime,
     pub dateacquired_stamp: Option<NaiveDateTime>,
     pub started_stamp: Option<NaiveDateTime>,
-    pub finished_stamp: Option<NaiveDateTime>
+    pub finished_stamp: Option<NaiveDateTime>,
+    pub author_id: Option<i32>,
+    pub page_count: Option<i32>,
+}
+
+// ---------------- Store update -------------
+
+// Fields set to None are left untouched, nullable columns are cleared with Some(None)
+#[derive(Serialize,Deserialize,AsChangeset,Debug,Clone,Default)]
+#[table_name="books"]
+pub struct BookChanges {
+    pub title: Option<String>,
+    pub author_lf: Option<String>,
+    pub author_code: Option<String>,
+    pub isbn: Option<String>,
+    pub publicationdate: Option<String>,
+    pub rating: Option<Option<i32>>,
+    pub language_main: Option<String>,
+    pub language_secondary: Option<Option<String>>,
+    pub language_original: Option<String>,
+    pub review: Option<Option<String>>,
+    pub dateacquired_stamp: Option<Option<NaiveDateTime>>,
+    pub started_stamp: Option<Option<NaiveDateTime>>,
+    pub finished_stamp: Option<Option<NaiveDateTime>>,
+    pub author_id: Option<Option<i32>>,
+    pub page_count: Option<Option<i32>>,
+    // storage key of the cover, see cover
+    pub cover: Option<String>,
+    // follows publicationdate, set by book_handler::update
+    pub publication_sort: Option<Option<i32>>,
+}
+
+impl BookChanges {
+    // diesel refuses to run an update without any field to set
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.author_lf.is_none()
+            && self.author_code.is_none()
+            && self.isbn.is_none()
+            && self.publicationdate.is_none()
+            && self.rating.is_none()
+            && self.language_main.is_none()
+            && self.language_secondary.is_none()
+            && self.language_original.is_none()
+            && self.review.is_none()
+            && self.dateacquired_stamp.is_none()
+            && self.started_stamp.is_none()
+            && self.finished_stamp.is_none()
+            && self.author_id.is_none()
+            && self.page_count.is_none()
+            && self.cover.is_none()
+            && self.publication_sort.is_none()
+    }
+}
+
+// ---------------- Authors -------------
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Author {
+    pub id: i32,
+    // "Last, First"
+    pub name: String,
+    pub code: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="authors"]
+pub struct NewAuthor {
+    pub name: String,
+    pub code: String,
+    pub created_at: NaiveDateTime,
+}
+
+// ---------------- Contributors -------------
+
+#[derive(Clone,Copy,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Author,
+    Translator,
+    Editor,
+    Illustrator,
+    Preface,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Author => "author",
+            Role::Translator => "translator",
+            Role::Editor => "editor",
+            Role::Illustrator => "illustrator",
+            Role::Preface => "preface",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "author" => Ok(Role::Author),
+            "translator" => Ok(Role::Translator),
+            "editor" => Ok(Role::Editor),
+            "illustrator" => Ok(Role::Illustrator),
+            "preface" => Ok(Role::Preface),
+            _ => Err(format!("Unknown contributor role {}", role)),
+        }
+    }
+}
+
+#[derive(Serialize,Deserialize,Insertable,Queryable,Debug, Clone)]
+#[table_name="book_contributors"]
+pub struct BookContributor {
+    pub book_id: i32,
+    pub author_id: i32,
+    pub role: String,
+    pub position: i32,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct Contributor {
+    pub author_id: i32,
+    pub name: String,
+    pub code: String,
+    pub role: Role,
+}
+
+// Book with its contributors and tags, as sent to the front
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct BookDetails {
+    #[serde(flatten)]
+    pub book: Book,
+    pub contributors: Vec<Contributor>,
+    pub tags: Vec<String>,
+    // percentage read of the reading in progress
+    pub progress: Option<i32>,
+}
+
+// ---------------- Readings -------------
+
+#[derive(Clone,Copy,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadingStatus {
+    ToRead,
+    Reading,
+    Read,
+    Abandoned,
+}
+
+impl ReadingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadingStatus::ToRead => "to-read",
+            ReadingStatus::Reading => "reading",
+            ReadingStatus::Read => "read",
+            ReadingStatus::Abandoned => "abandoned",
+        }
+    }
+}
+
+impl FromStr for ReadingStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "to-read" => Ok(ReadingStatus::ToRead),
+            "reading" => Ok(ReadingStatus::Reading),
+            "read" => Ok(ReadingStatus::Read),
+            "abandoned" => Ok(ReadingStatus::Abandoned),
+            _ => Err(format!("Unknown reading status {}", status)),
+        }
+    }
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Reading {
+    pub id: i32,
+    pub book_id: i32,
+    pub user_id: i32,
+    pub started_at: Option<NaiveDateTime>,
+    // also set when the reading is abandoned
+    pub finished_at: Option<NaiveDateTime>,
+    pub abandoned: bool,
+    pub notes: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="readings"]
+pub struct NewReading {
+    pub book_id: i32,
+    pub user_id: i32,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub abandoned: bool,
+    pub notes: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Progress {
+    pub id: i32,
+    pub reading_id: i32,
+    pub page: Option<i32>,
+    pub percent: i32,
+    pub recorded_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="reading_progress"]
+pub struct NewProgress {
+    pub reading_id: i32,
+    pub page: Option<i32>,
+    pub percent: i32,
+    pub recorded_at: NaiveDateTime,
+}
+
+// Reading with its progress, oldest first
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct ReadingDetails {
+    #[serde(flatten)]
+    pub reading: Reading,
+    pub progress: Vec<Progress>,
+}
+
+// ---------------- Loans -------------
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Loan {
+    pub id: i32,
+    pub book_id: i32,
+    // the lender
+    pub user_id: i32,
+    pub borrower_name: String,
+    // when the borrower is a registered user
+    pub borrower_id: Option<i32>,
+    pub lent_at: NaiveDateTime,
+    pub due_at: Option<NaiveDateTime>,
+    // none while the book is lent
+    pub returned_at: Option<NaiveDateTime>,
+    pub notes: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="loans"]
+pub struct NewLoan {
+    pub book_id: i32,
+    pub user_id: i32,
+    pub borrower_name: String,
+    pub borrower_id: Option<i32>,
+    pub lent_at: NaiveDateTime,
+    pub due_at: Option<NaiveDateTime>,
+    pub returned_at: Option<NaiveDateTime>,
+    pub notes: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct LentBook {
+    pub loan: Loan,
+    pub book: Book,
+}
+
+// ---------------- Friends -------------
+
+// A user the library is visible to
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Friend {
+    pub user_id: i32,
+    pub login: String,
+    pub created_at: NaiveDateTime,
+}
+
+// ---------------- Borrow requests -------------
+
+// See borrow for the transitions
+#[derive(Clone,Copy,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BorrowState {
+    Pending,
+    Accepted,
+    Declined,
+    Returned,
+}
+
+impl BorrowState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BorrowState::Pending => "pending",
+            BorrowState::Accepted => "accepted",
+            BorrowState::Declined => "declined",
+            BorrowState::Returned => "returned",
+        }
+    }
+}
+
+impl FromStr for BorrowState {
+    type Err = String;
+
+    fn from_str(state: &str) -> Result<Self, Self::Err> {
+        match state {
+            "pending" => Ok(BorrowState::Pending),
+            "accepted" => Ok(BorrowState::Accepted),
+            "declined" => Ok(BorrowState::Declined),
+            "returned" => Ok(BorrowState::Returned),
+            _ => Err(format!("Unknown borrow state {}", state)),
+        }
+    }
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct BorrowRequest {
+    pub id: i32,
+    pub book_id: i32,
+    pub owner_id: i32,
+    pub requester_id: i32,
+    // see BorrowState
+    pub state: String,
+    pub message: Option<String>,
+    // set when the request is accepted
+    pub loan_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="borrow_requests"]
+pub struct NewBorrowRequest {
+    pub book_id: i32,
+    pub owner_id: i32,
+    pub requester_id: i32,
+    pub state: String,
+    pub message: Option<String>,
+    pub loan_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// What the requester may know of the requested book
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct RequestedBook {
+    pub id: i32,
+    pub title: String,
+    // names of the authors, "Last, First"
+    pub authors: Vec<String>,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct BorrowRequestDetails {
+    pub request: BorrowRequest,
+    pub book: RequestedBook,
+}
+
+// ---------------- Goals -------------
+
+#[derive(Clone,Copy,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalKind {
+    Books,
+    Pages,
+}
+
+impl GoalKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GoalKind::Books => "books",
+            GoalKind::Pages => "pages",
+        }
+    }
+}
+
+impl FromStr for GoalKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "books" => Ok(GoalKind::Books),
+            "pages" => Ok(GoalKind::Pages),
+            _ => Err(format!("Unknown goal kind {}", kind)),
+        }
+    }
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Goal {
+    pub id: i32,
+    pub user_id: i32,
+    pub year: i32,
+    // see GoalKind
+    pub kind: String,
+    pub target: i32,
+    // only the books read in this language count
+    pub language: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="goals"]
+pub struct NewGoal {
+    pub user_id: i32,
+    pub year: i32,
+    pub kind: String,
+    pub target: i32,
+    pub language: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// ---------------- Shelves -------------
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Shelf {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub description: String,
+    pub created_at: NaiveDateTime,
+    // filter expression of a smart shelf, see book_filter
+    pub query: Option<String>,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="shelves"]
+pub struct NewShelf {
+    pub user_id: i32,
+    pub name: String,
+    pub description: String,
+    pub created_at: NaiveDateTime,
+    pub query: Option<String>,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Queryable,Debug, Clone)]
+#[table_name="shelf_books"]
+pub struct ShelfBook {
+    pub shelf_id: i32,
+    pub book_id: i32,
+    // order of the book on the shelf
+    pub position: i32,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct ShelfCount {
+    #[serde(flatten)]
+    pub shelf: Shelf,
+    pub count: i64,
+}
+
+// ---------------- Wishlist -------------
+
+pub const HIGHEST_PRIORITY: i32 = 1;
+pub const LOWEST_PRIORITY: i32 = 5;
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Wish {
+    pub id: i32,
+    pub user_id: i32,
+    pub title: String,
+    // as typed by the user, normalized when the book is acquired
+    pub author: String,
+    pub isbn: String,
+    // from HIGHEST_PRIORITY to LOWEST_PRIORITY
+    pub priority: i32,
+    pub notes: Option<String>,
+    // in cents
+    pub target_price: Option<i32>,
+    pub where_to_buy: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="wishes"]
+pub struct NewWish {
+    pub user_id: i32,
+    pub title: String,
+    pub author: String,
+    pub isbn: String,
+    pub priority: i32,
+    pub notes: Option<String>,
+    pub target_price: Option<i32>,
+    pub where_to_buy: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// ---------------- Tags -------------
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq,Queryable)]
+pub struct Tag {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Debug, Clone)]
+#[table_name="tags"]
+pub struct NewTag {
+    pub user_id: i32,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize,Deserialize,Insertable,Queryable,Debug, Clone)]
+#[table_name="book_tags"]
+pub struct BookTag {
+    pub book_id: i32,
+    pub tag_id: i32,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
 }
@@ -0,0 +1,291 @@
+// Publication dates known to the year, the month or the day, possibly approximate or before the Common Era.
+// They are stored in their canonical text form ("1989", "1989-01", "1989-01-15", "c. 1600", "400 BCE")
+// along with an integer sort key.
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialDate {
+    // negative before the Common Era
+    pub year: i32,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+    pub circa: bool,
+}
+
+const CIRCA_PREFIXES: &[&str] = &["circa ", "ca. ", "ca ", "c. ", "c ", "vers ", "~"];
+const BCE_SUFFIXES: &[&str] = &[" b.c.e.", " bce", " b.c.", " bc", " av. j.-c.", " av. j.c.", " av jc"];
+
+const MONTHS: &[&[&str]] = &[
+    &["january", "jan", "janvier", "janv"],
+    &["february", "feb", "février", "fevrier", "févr", "fevr"],
+    &["march", "mar", "mars"],
+    &["april", "apr", "avril", "avr"],
+    &["may", "mai"],
+    &["june", "jun", "juin"],
+    &["july", "jul", "juillet", "juil"],
+    &["august", "aug", "août", "aout"],
+    &["september", "sep", "sept", "septembre"],
+    &["october", "oct", "octobre"],
+    &["november", "nov", "novembre"],
+    &["december", "dec", "décembre", "decembre", "déc"],
+];
+
+impl PartialDate {
+    pub fn year(year: i32) -> PartialDate {
+        PartialDate { year, month: None, day: None, circa: false }
+    }
+
+    // Orders the dates in SQL : the year, month and day digits, missing parts being 0
+    pub fn sort_key(&self) -> i32 {
+        self.year * 10000 + self.month.unwrap_or(0) as i32 * 100 + self.day.unwrap_or(0) as i32
+    }
+
+    // Year of a sort key : a plain division would round toward zero before the Common Era
+    pub fn year_of_sort_key(sort_key: i32) -> i32 {
+        sort_key.div_euclid(10000)
+    }
+
+    fn checked(self) -> Result<PartialDate, String> {
+        if self.year == 0 {
+            return Err(String::from("there is no year 0"));
+        }
+        if self.year < -9999 || self.year > 9999 {
+            return Err(format!("invalid year {}", self.year));
+        }
+        if self.year < 0 && self.month.is_some() {
+            return Err(String::from("only the year is allowed before the Common Era"));
+        }
+        if let Some(month) = self.month {
+            if month < 1 || month > 12 {
+                return Err(format!("invalid month {}", month));
+            }
+        }
+        if let (Some(month), Some(day)) = (self.month, self.day) {
+            if NaiveDate::from_ymd_opt(self.year, month, day).is_none() {
+                return Err(format!("invalid day {}", day));
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl Ord for PartialDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for PartialDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for PartialDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.circa {
+            write!(f, "c. ")?;
+        }
+        if self.year < 0 {
+            return write!(f, "{} BCE", -self.year);
+        }
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{:02}", day)?;
+        }
+        Ok(())
+    }
+}
+
+fn number(text: &str) -> Result<u32, String> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("unknown date format {}", text));
+    }
+    text.parse::<u32>().map_err(|_| format!("unknown date format {}", text))
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.trim_end_matches('.');
+    MONTHS.iter().position(|names| names.contains(&name)).map(|index| index as u32 + 1)
+}
+
+// Checked before the conversion, which would wrap the large numbers
+fn checked_year(number: u32) -> Result<i32, String> {
+    if number > 9999 {
+        return Err(format!("invalid year {}", number));
+    }
+    Ok(number as i32)
+}
+
+// The date without its circa and era markers
+fn split_date(date: &str) -> Result<(i32, Option<u32>, Option<u32>), String> {
+    let numeric = |parts: &[&str]| -> Result<Vec<u32>, String> { parts.iter().map(|part| number(part)).collect() };
+
+    // "1989", "1989-01", "1989-01-15", "15/01/1989", "01/1989"
+    let parts: Vec<&str> = date.split(|c| c == '-' || c == '/' || c == '.').collect();
+    if let Ok(numbers) = numeric(&parts) {
+        let year_first = parts[0].len() > 2;
+        return match (numbers.as_slice(), year_first) {
+            ([year], _) => Ok((checked_year(*year)?, None, None)),
+            ([year, month], true) | ([month, year], false) => Ok((checked_year(*year)?, Some(*month), None)),
+            ([year, month, day], true) | ([day, month, year], false) => Ok((checked_year(*year)?, Some(*month), Some(*day))),
+            _ => Err(format!("unknown date format {}", date)),
+        };
+    }
+
+    // "march 1989", "2 march 1989", "march 2, 1989"
+    let words: Vec<&str> = date.split(|c: char| c.is_whitespace() || c == ',').filter(|word| !word.is_empty()).collect();
+    match words.as_slice() {
+        [month, year] => match month_number(month) {
+            Some(month) => Ok((checked_year(number(year)?)?, Some(month), None)),
+            None => Err(format!("unknown month {}", month)),
+        },
+        [first, second, year] => {
+            let (day, month) = match month_number(first) {
+                Some(month) => (number(second)?, month),
+                // "1st", "2nd"
+                None => (number(first.trim_end_matches(|c: char| c.is_alphabetic()))?,
+                         month_number(second).ok_or_else(|| format!("unknown month {}", second))?),
+            };
+            Ok((checked_year(number(year)?)?, Some(month), Some(day)))
+        }
+        _ => Err(format!("unknown date format {}", date)),
+    }
+}
+
+impl FromStr for PartialDate {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut date = text.trim().to_lowercase();
+        let mut circa = false;
+        if let Some(prefix) = CIRCA_PREFIXES.iter().find(|prefix| date.starts_with(*prefix)) {
+            date = date[prefix.len()..].trim_start().to_string();
+            circa = true;
+        }
+        if date.ends_with('?') {
+            date = date.trim_end_matches('?').trim_end().to_string();
+            circa = true;
+        }
+
+        let mut bce = false;
+        if let Some(suffix) = BCE_SUFFIXES.iter().find(|suffix| date.ends_with(*suffix)) {
+            date = date[..date.len() - suffix.len()].trim_end().to_string();
+            bce = true;
+        } else if date.starts_with('-') && date[1..].chars().all(|c| c.is_ascii_digit()) {
+            date = date[1..].to_string();
+            bce = true;
+        }
+
+        if date.is_empty() {
+            return Err(String::from("the date is empty"));
+        }
+        let (year, month, day) = split_date(&date)?;
+        let year = if bce { -year } else { year };
+        PartialDate { year, month, day, circa }.checked()
+    }
+}
+
+impl Serialize for PartialDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PartialDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(de::Error::custom)
+    }
+}
+
+// Canonical form of a free text date, or the text itself when it can not be read
+pub fn canonical(text: &str) -> String {
+    match text.parse::<PartialDate>() {
+        Ok(date) => date.to_string(),
+        Err(_) => text.trim().to_string(),
+    }
+}
+
+pub fn sort_key(text: &str) -> Option<i32> {
+    text.parse::<PartialDate>().ok().map(|date| date.sort_key())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> String {
+        text.parse::<PartialDate>().unwrap().to_string()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(parsed("1989"), "1989");
+        assert_eq!(parsed("1989-01"), "1989-01");
+        assert_eq!(parsed("2019-03-02"), "2019-03-02");
+        assert_eq!(parsed("2019/3/2"), "2019-03-02");
+        assert_eq!(parsed("02/03/2019"), "2019-03-02");
+        assert_eq!(parsed("03/2019"), "2019-03");
+        assert_eq!(parsed("March 1989"), "1989-03");
+        assert_eq!(parsed("2 mars 2019"), "2019-03-02");
+        assert_eq!(parsed("March 2, 2019"), "2019-03-02");
+        assert_eq!(parsed("1st March 2019"), "2019-03-01");
+        assert_eq!(parsed("800"), "0800");
+    }
+
+    #[test]
+    fn circa_and_era() {
+        assert_eq!(parsed("circa 1600"), "c. 1600");
+        assert_eq!(parsed("ca. 1600"), "c. 1600");
+        assert_eq!(parsed("1600?"), "c. 1600");
+        assert_eq!(parsed("c. 800 BC"), "c. 800 BCE");
+        assert_eq!(parsed("-400"), "400 BCE");
+        assert_eq!(parsed("400 av. J.-C."), "400 BCE");
+        assert_eq!(parsed("c. 400 BCE"), "c. 400 BCE");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("".parse::<PartialDate>().is_err());
+        assert!("2019-13".parse::<PartialDate>().is_err());
+        assert!("2019-02-30".parse::<PartialDate>().is_err());
+        assert!("sometime".parse::<PartialDate>().is_err());
+        assert!("0".parse::<PartialDate>().is_err());
+        assert!("12345678901".parse::<PartialDate>().is_err());
+        // would wrap to a negative year
+        assert_eq!("4294967295".parse::<PartialDate>(), Err(String::from("invalid year 4294967295")));
+        assert!("march 4294967295".parse::<PartialDate>().is_err());
+    }
+
+    #[test]
+    fn ordering() {
+        let dates: Vec<PartialDate> = vec!["400 BCE", "c. 1600", "1989", "1989-01", "1989-01-15", "1990"]
+            .into_iter().map(|text| text.parse().unwrap()).collect();
+        let mut sorted = dates.clone();
+        sorted.sort();
+        assert_eq!(sorted, dates);
+        assert_eq!(PartialDate::year_of_sort_key("400 BCE".parse::<PartialDate>().unwrap().sort_key()), -400);
+        assert_eq!(PartialDate::year_of_sort_key("1989-01-15".parse::<PartialDate>().unwrap().sort_key()), 1989);
+        assert_eq!(canonical("1989-1"), "1989-01");
+        assert_eq!(canonical(" some day "), "some day");
+        assert_eq!(sort_key("1989-1"), Some(19890100));
+        assert_eq!(sort_key("some day"), None);
+    }
+
+    #[test]
+    fn serde() {
+        let date: PartialDate = serde_json::from_str("\"c. 1600\"").unwrap();
+        assert_eq!(date, PartialDate { circa: true, ..PartialDate::year(1600) });
+        assert_eq!(serde_json::to_string(&date).unwrap(), "\"c. 1600\"");
+    }
+}
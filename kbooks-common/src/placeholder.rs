@@ -0,0 +1,144 @@
+// SVG covers of the books without an uploaded image : the title and the author on a plain background.
+// The colour comes from the author code, so that the books of an author look alike.
+
+const WIDTH: u32 = 200;
+const HEIGHT: u32 = 300;
+const TITLE_LINE_LENGTH: usize = 16;
+const TITLE_MAX_LINES: usize = 6;
+const AUTHOR_LINE_LENGTH: usize = 24;
+
+// FNV-1a : unlike the std hashers, stable between Rust versions
+fn hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193))
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+// Dark enough for a white text
+pub fn colour(author_code: &str) -> String {
+    let hue = f64::from(hash(author_code) % 360);
+    let (r, g, b) = hsl_to_rgb(hue, 0.45, 0.35);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Words are kept whole when they fit on a line, the last line ends with an ellipsis when the text is cut
+fn wrap(text: &str, line_length: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        loop {
+            let used = current.chars().count();
+            let separator = if used == 0 { 0 } else { 1 };
+            if used + separator + word.len() <= line_length {
+                if separator == 1 {
+                    current.push(' ');
+                }
+                current.extend(word.iter());
+                break;
+            }
+            if used > 0 {
+                lines.push(std::mem::take(&mut current));
+            } else {
+                let rest = word.split_off(line_length);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last: Vec<char> = lines[max_lines - 1].chars().collect();
+        let kept = last.len().min(line_length - 1);
+        lines[max_lines - 1] = format!("{}…", last[..kept].iter().collect::<String>().trim_end());
+    }
+    lines
+}
+
+fn text_lines(lines: &[String], first_y: u32, line_height: u32, font_size: u32, weight: &str) -> String {
+    lines.iter().enumerate()
+        .map(|(index, line)| format!(
+            "<text x=\"{x}\" y=\"{y}\" font-size=\"{size}\" font-weight=\"{weight}\">{line}</text>",
+            x = WIDTH / 2,
+            y = first_y + index as u32 * line_height,
+            size = font_size,
+            weight = weight,
+            line = escape(line),
+        ))
+        .collect()
+}
+
+pub fn svg(title: &str, author_lf: &str, author_code: &str) -> String {
+    let title_lines = wrap(title, TITLE_LINE_LENGTH, TITLE_MAX_LINES);
+    let author_lines = wrap(author_lf, AUTHOR_LINE_LENGTH, 2);
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\
+         <rect width=\"{width}\" height=\"{height}\" fill=\"{colour}\"/>\
+         <rect x=\"12\" y=\"12\" width=\"{inner_width}\" height=\"{inner_height}\" fill=\"none\" stroke=\"#ffffff\" stroke-opacity=\"0.5\"/>\
+         <g fill=\"#ffffff\" font-family=\"Georgia, serif\" text-anchor=\"middle\">{title}{author}</g>\
+         </svg>",
+        width = WIDTH,
+        height = HEIGHT,
+        inner_width = WIDTH - 24,
+        inner_height = HEIGHT - 24,
+        colour = colour(author_code),
+        title = text_lines(&title_lines, 70, 26, 20, "bold"),
+        author = text_lines(&author_lines, 250, 18, 14, "normal"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours() {
+        assert_eq!(colour("PERECGEOR"), colour("PERECGEOR"));
+        assert_ne!(colour("PERECGEOR"), colour("QUENEAURAY"));
+        assert_eq!(hsl_to_rgb(0.0, 1.0, 0.5), (255, 0, 0));
+        assert_eq!(hsl_to_rgb(240.0, 1.0, 0.5), (0, 0, 255));
+        assert_eq!(colour("").len(), 7);
+    }
+
+    #[test]
+    fn wrapped_lines() {
+        assert_eq!(wrap("La Vie mode d'emploi", 16, 6), vec!["La Vie mode", "d'emploi"]);
+        assert_eq!(wrap("Anticonstitutionnellement", 10, 6), vec!["Anticonsti", "tutionnell", "ement"]);
+        assert_eq!(wrap("one two three four", 8, 2), vec!["one two", "three…"]);
+        assert!(wrap("  ", 8, 2).is_empty());
+    }
+
+    #[test]
+    fn escaped_svg() {
+        let svg = svg("Tintin & <Milou>", "Hergé", "HERGE");
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(">Tintin &amp; &lt;Milou&gt;</text>"));
+        assert!(svg.contains(">Hergé</text>"));
+        assert!(svg.contains(&format!("fill=\"{}\"", colour("HERGE"))));
+    }
+}
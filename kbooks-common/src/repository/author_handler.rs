@@ -0,0 +1,124 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::author::{normalize_name, base_code, unique_code};
+use crate::schema::{book_contributors, books};
+use crate::schema::authors::dsl;
+use crate::models::{Author, NewAuthor};
+
+// A concurrent creation of the same author, or of an author with the same code, makes the insert fail
+const MAX_CREATE_ATTEMPTS: usize = 3;
+
+pub fn get(pool: DbPool, author_id: i32) -> Result<Author, DBError> {
+    let conn = &pool.get().unwrap();
+    dsl::authors.find(author_id).first::<Author>(conn)
+}
+
+pub fn find_by_code(pool: DbPool, code: &str) -> Result<Option<Author>, DBError> {
+    let conn = &pool.get().unwrap();
+    dsl::authors.filter(dsl::code.eq(code)).first::<Author>(conn).optional()
+}
+
+// Returns the existing author with the same normalized name, or creates it with a new unique code
+pub fn get_or_create(pool: DbPool, name: &str) -> Result<Author, DBError> {
+    let conn = &pool.get().unwrap();
+    find_or_create(conn, name)
+}
+
+pub(crate) fn find_or_create(conn: &MyConnection, name: &str) -> Result<Author, DBError> {
+    let name = normalize_name(name);
+    let mut attempt = 1;
+    loop {
+        // a savepoint when called in a transaction, so that the failed insert can be tried again
+        match conn.transaction(|| find_or_insert(conn, &name)) {
+            Err(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) if attempt < MAX_CREATE_ATTEMPTS => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+fn find_or_insert(conn: &MyConnection, name: &str) -> Result<Author, DBError> {
+    if let Some(author) = dsl::authors.filter(dsl::name.eq(name)).first::<Author>(conn).optional()? {
+        return Ok(author);
+    }
+
+    let base = base_code(name);
+    let taken: Vec<String> = dsl::authors
+        .select(dsl::code)
+        .filter(dsl::code.eq(&base).or(dsl::code.like(format!("{}-%", base))))
+        .load(conn)?;
+    let author = NewAuthor {
+        code: unique_code(&base, &taken),
+        name: name.to_string(),
+        created_at: Utc::now().naive_utc(),
+    };
+
+    #[cfg(not(feature = "test"))]
+    let inserted_author: Author = diesel::insert_into(dsl::authors).values(&author).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(dsl::authors).values(&author).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_author: Author = dsl::authors.order(dsl::id.desc()).first(conn)?;
+
+    Ok(inserted_author)
+}
+
+// Data migration : the authors created from the names of the existing books get the "Last, First" form.
+// An author whose normalized name is already taken is merged into the existing one.
+// Returns the number of authors changed.
+pub(crate) fn normalize_names(conn: &MyConnection) -> Result<usize, DBError> {
+    let authors: Vec<Author> = dsl::authors.order(dsl::id).load(conn)?;
+    let mut changed = 0;
+    for author in authors {
+        let name = normalize_name(&author.name);
+        if name == author.name {
+            continue;
+        }
+        let existing = dsl::authors.filter(dsl::name.eq(&name)).first::<Author>(conn).optional()?;
+        let target = match existing {
+            Some(ref existing) => existing.clone(),
+            None => {
+                diesel::update(dsl::authors.find(author.id)).set(dsl::name.eq(&name)).execute(conn)?;
+                Author { name, ..author.clone() }
+            }
+        };
+        diesel::update(books::table.filter(books::author_id.eq(author.id)))
+            .set((
+                books::author_id.eq(target.id),
+                books::author_lf.eq(&target.name),
+                books::author_code.eq(&target.code),
+            ))
+            .execute(conn)?;
+        if existing.is_some() {
+            merge(conn, author.id, target.id)?;
+        }
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+// Moves the contributions of an author to another one, then removes it
+fn merge(conn: &MyConnection, from_id: i32, to_id: i32) -> Result<(), DBError> {
+    let contributions: Vec<(i32, String)> = book_contributors::table
+        .filter(book_contributors::author_id.eq(from_id))
+        .select((book_contributors::book_id, book_contributors::role))
+        .load(conn)?;
+    for (book_id, role) in contributions {
+        let row = book_contributors::table
+            .filter(book_contributors::book_id.eq(book_id))
+            .filter(book_contributors::role.eq(&role));
+        let already_there: bool = diesel::dsl::select(diesel::dsl::exists(row.filter(book_contributors::author_id.eq(to_id))))
+            .get_result(conn)?;
+        let from_row = row.filter(book_contributors::author_id.eq(from_id));
+        if already_there {
+            diesel::delete(from_row).execute(conn)?;
+        } else {
+            diesel::update(from_row).set(book_contributors::author_id.eq(to_id)).execute(conn)?;
+        }
+    }
+    diesel::delete(dsl::authors.find(from_id)).execute(conn)?;
+    Ok(())
+}
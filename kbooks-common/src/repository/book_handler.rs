@@ -1,32 +1,635 @@
 use chrono::{Local, NaiveDateTime};
 use diesel::prelude::*;
 use diesel::result::Error as DBError;
+use diesel::query_dsl::GroupByDsl;
+use diesel::sql_types::{Bool, Integer, BigInt, Nullable, Text};
 use uuid::Uuid;
 
-use crate::khnum::wiring::DbPool;
+use crate::khnum::wiring::{DbPool, MyBackend, MyConnection};
 
+use crate::book_filter::{Expr, Field, Op, Operand};
+use crate::isbn;
+use crate::language;
+use crate::partial_date;
+use crate::repository::{contributor_handler, reading_handler, tag_handler};
+use crate::schema::{book_contributors, book_tags, books, borrow_requests, loans, reading_progress, readings, shelf_books, shelves, tags};
 use crate::schema::books::dsl;
-use crate::models::{Book, NewBook};
+use crate::models::{Book, BookDetails, NewBook, BookChanges, ReadingStatus, Role};
 
-pub fn add(pool: DbPool, book: NewBook) -> Result<(), DBError> {
+pub fn add(pool: DbPool, book: NewBook) -> Result<Book, DBError> {
     let conn = &pool.get().unwrap();
+    insert(conn, &book)
+}
+
+pub(crate) fn insert(conn: &MyConnection, book: &NewBook) -> Result<Book, DBError> {
+    let values = (book, dsl::publication_sort.eq(partial_date::sort_key(&book.publicationdate)));
     #[cfg(not(feature = "test"))]
-    let inserted_book: Book = diesel::insert_into(dsl::books).values(&book).get_result(conn)?;
+    let inserted_book: Book = diesel::insert_into(dsl::books).values(values).get_result(conn)?;
     #[cfg(feature = "test")]
-    diesel::insert_into(dsl::books).values(&book).execute(conn)?;
+    diesel::insert_into(dsl::books).values(values).execute(conn)?;
     #[cfg(feature = "test")]
     let inserted_book: Book = dsl::books.order(dsl::id.desc()).first(conn)?;
 
-    // let expire_date = (&inserted_user).expires_at.unwrap();
-    return Ok(());
+    Ok(inserted_book)
+}
+
+// Adds a book with its contributors (see contributor_handler::set_for_book) and its tags, all or nothing
+pub fn create(pool: DbPool, book: NewBook, contributors: &[(i32, Role)], tags: &[String]) -> Result<Book, DBError> {
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let inserted_book = insert(conn, &book)?;
+        contributor_handler::replace_for_book(conn, inserted_book.id, contributors)?;
+        tag_handler::replace_for_book(conn, book.user_id, inserted_book.id, tags)?;
+        Ok(inserted_book)
+    })
+}
+
+// Replaces the data of a book of the user with its contributors, and its tags when given, all or nothing
+pub fn replace(pool: DbPool, user_id: i32, book_id: i32, changes: &BookChanges, contributors: &[(i32, Role)], tags: Option<&[String]>) -> Result<Book, DBError> {
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let book = apply_changes(conn, user_id, book_id, changes)?;
+        contributor_handler::replace_for_book(conn, book.id, contributors)?;
+        if let Some(tags) = tags {
+            tag_handler::replace_for_book(conn, user_id, book.id, tags)?;
+        }
+        Ok(book)
+    })
 }
 
-pub fn list(pool: DbPool) -> Result<Vec<Book>, DBError> {
+// Only the books of the given user are returned
+pub fn list(pool: DbPool, user_id: i32) -> Result<Vec<Book>, DBError> {
     let conn = &pool.get().unwrap();
-    let items = dsl::books.load::<Book>(conn)?;
+    let items = dsl::books
+        .filter(dsl::user_id.eq(user_id))
+        .load::<Book>(conn)?;
     return Ok(items.into_iter().map(|item| item.into()).collect());
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Title,
+    AuthorLf,
+    Publicationdate,
+    CreatedAt,
+    Rating,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagsMode {
+    // books with at least one of the tags
+    Any,
+    // books with every tag
+    All,
+}
+
+impl Default for TagsMode {
+    fn default() -> Self {
+        TagsMode::Any
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BookFilters {
+    pub language_main: Option<String>,
+    // with or without a rating
+    pub rated: Option<bool>,
+    // with or without a finished_stamp
+    pub finished: Option<bool>,
+    // status of the last reading
+    pub status: Option<ReadingStatus>,
+    // matches both ISBN forms
+    pub isbn: Option<String>,
+    // normalized tag names
+    pub tags: Vec<String>,
+    pub tags_mode: TagsMode,
+    // books on a shelf of the user, or matching the query of a smart shelf
+    pub shelf: Option<i32>,
+    pub query: Option<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    pub filters: BookFilters,
+    pub sort: SortKey,
+    pub order: SortOrder,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        ListOptions {
+            filters: BookFilters::default(),
+            sort: SortKey::CreatedAt,
+            order: SortOrder::Asc,
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+fn filtered<'a>(user_id: i32, filters: &'a BookFilters) -> books::BoxedQuery<'a, MyBackend> {
+    let mut query = dsl::books
+        .filter(dsl::user_id.eq(user_id))
+        .into_boxed();
+    if let Some(ref language) = filters.language_main {
+        query = query.filter(dsl::language_main.eq(language));
+    }
+    match filters.rated {
+        Some(true) => query = query.filter(dsl::rating.is_not_null()),
+        Some(false) => query = query.filter(dsl::rating.is_null()),
+        None => ()
+    }
+    match filters.finished {
+        Some(true) => query = query.filter(dsl::finished_stamp.is_not_null()),
+        Some(false) => query = query.filter(dsl::finished_stamp.is_null()),
+        None => ()
+    }
+    if let Some(status) = filters.status {
+        query = query.filter(dsl::reading_status.eq(status.as_str()));
+    }
+    if let Some(ref isbn) = filters.isbn {
+        query = query.filter(dsl::isbn.eq_any(isbn::variants(isbn)));
+    }
+    if let Some(ref expr) = filters.query {
+        query = query.filter(condition(user_id, expr));
+    }
+    if let Some(shelf_id) = filters.shelf {
+        let shelved = shelf_books::table
+            .filter(shelf_books::shelf_id.eq(shelf_id))
+            .select(shelf_books::book_id);
+        query = query.filter(dsl::id.eq_any(shelved));
+    }
+    if !filters.tags.is_empty() {
+        match filters.tags_mode {
+            TagsMode::Any => {
+                let tagged = book_tags::table
+                    .inner_join(tags::table)
+                    .filter(tags::user_id.eq(user_id))
+                    .filter(tags::name.eq_any(&filters.tags))
+                    .select(book_tags::book_id);
+                query = query.filter(dsl::id.eq_any(tagged));
+            }
+            TagsMode::All => {
+                for name in &filters.tags {
+                    let tagged = book_tags::table
+                        .inner_join(tags::table)
+                        .filter(tags::user_id.eq(user_id))
+                        .filter(tags::name.eq(name))
+                        .select(book_tags::book_id);
+                    query = query.filter(dsl::id.eq_any(tagged));
+                }
+            }
+        }
+    }
+    query
+}
+
+// ---------------- Filter expressions -------------
+
+type Condition = Box<dyn BoxableExpression<books::table, MyBackend, SqlType = Bool>>;
+type TextExpression = Box<dyn BoxableExpression<books::table, MyBackend, SqlType = Nullable<Text>>>;
+type IntegerExpression = Box<dyn BoxableExpression<books::table, MyBackend, SqlType = Nullable<Integer>>>;
+
+sql_function!(fn lower(text: Nullable<Text>) -> Nullable<Text>);
+sql_function!(fn coalesce(condition: Bool, default: Bool) -> Bool);
+
+fn text_column(field: Field) -> TextExpression {
+    match field {
+        Field::Title => Box::new(dsl::title.nullable()),
+        Field::Author => Box::new(dsl::author_lf.nullable()),
+        Field::Isbn => Box::new(dsl::isbn.nullable()),
+        Field::LanguageMain => Box::new(dsl::language_main.nullable()),
+        Field::LanguageSecondary => Box::new(dsl::language_secondary),
+        Field::LanguageOriginal => Box::new(dsl::language_original.nullable()),
+        Field::Status => Box::new(dsl::reading_status.nullable()),
+        Field::Review => Box::new(dsl::review),
+        // numeric fields
+        Field::Rating | Field::PageCount | Field::Year => unreachable!(),
+    }
+}
+
+fn integer_column(field: Field) -> IntegerExpression {
+    match field {
+        Field::Rating => Box::new(dsl::rating),
+        Field::PageCount => Box::new(dsl::page_count),
+        Field::Year => Box::new(dsl::publication_sort),
+        _ => unreachable!(),
+    }
+}
+
+macro_rules! comparison {
+    ($left:expr, $op:expr, $right:expr) => {
+        match $op {
+            Op::Eq => Box::new($left.eq($right)) as Condition,
+            Op::Ne => Box::new($left.ne($right)),
+            Op::Lt => Box::new($left.lt($right)),
+            Op::Le => Box::new($left.le($right)),
+            Op::Gt => Box::new($left.gt($right)),
+            Op::Ge => Box::new($left.ge($right)),
+            Op::Contains => unreachable!(),
+        }
+    };
+}
+
+// Same case folding as lower() : SQLite only folds the ASCII letters
+#[cfg(not(feature = "test"))]
+fn lowercase(text: &str) -> String {
+    text.to_lowercase()
+}
+
+#[cfg(feature = "test")]
+fn lowercase(text: &str) -> String {
+    text.to_ascii_lowercase()
+}
+
+// LIKE pattern matching the text anywhere
+fn contains_pattern(text: &str) -> String {
+    let escaped = lowercase(text).replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+// The publication year is compared through the range of sort keys of the year
+fn year_condition(op: Op, year: i32) -> Condition {
+    let start = partial_date::PartialDate::year(year).sort_key();
+    let next = start + 10000;
+    let sort = dsl::publication_sort;
+    match op {
+        Op::Eq => Box::new(sort.ge(start).and(sort.lt(next))),
+        Op::Ne => Box::new(sort.lt(start).or(sort.ge(next))),
+        Op::Lt => Box::new(sort.lt(start)),
+        Op::Le => Box::new(sort.lt(next)),
+        Op::Gt => Box::new(sort.ge(next)),
+        Op::Ge => Box::new(sort.ge(start)),
+        Op::Contains => unreachable!(),
+    }
+}
+
+// Compiles a parsed filter expression, whose types have been checked by the parser.
+// Comparisons with a missing value are false, and their negation true.
+fn condition(user_id: i32, expr: &Expr) -> Condition {
+    match expr {
+        Expr::And(left, right) => Box::new(condition(user_id, left).and(condition(user_id, right))),
+        Expr::Or(left, right) => Box::new(condition(user_id, left).or(condition(user_id, right))),
+        // the conditions are null when a value is missing
+        Expr::Not(expr) => Box::new(diesel::dsl::not(coalesce(condition(user_id, expr), false))),
+        Expr::Tag(name) => {
+            let tagged = book_tags::table
+                .inner_join(tags::table)
+                .filter(tags::user_id.eq(user_id))
+                .filter(tags::name.eq(name.clone()))
+                .select(book_tags::book_id);
+            Box::new(dsl::id.eq_any(tagged))
+        }
+        Expr::Compare(field, op, Operand::Null) if field.is_numeric() => match op {
+            Op::Eq => Box::new(integer_column(*field).is_null()),
+            _ => Box::new(integer_column(*field).is_not_null()),
+        },
+        Expr::Compare(field, op, Operand::Null) => match op {
+            Op::Eq => Box::new(text_column(*field).is_null()),
+            _ => Box::new(text_column(*field).is_not_null()),
+        },
+        Expr::Compare(Field::Year, op, Operand::Number(year)) => year_condition(*op, *year),
+        Expr::Compare(field, op, Operand::Number(number)) => comparison!(integer_column(*field), *op, Some(*number)),
+        Expr::Compare(field, op, Operand::Field(other)) if field.is_numeric() => comparison!(integer_column(*field), *op, integer_column(*other)),
+        Expr::Compare(field, op, Operand::Field(other)) => comparison!(text_column(*field), *op, text_column(*other)),
+        Expr::Compare(field, Op::Contains, Operand::Text(text)) => Box::new(lower(text_column(*field)).like(contains_pattern(text)).escape('\\')),
+        Expr::Compare(field, op, Operand::Text(text)) => comparison!(text_column(*field), *op, Some(text.clone())),
+    }
+}
+
+// The query of a smart shelf replaces the shelf filter
+fn with_shelf_query(conn: &MyConnection, user_id: i32, filters: &BookFilters) -> Result<BookFilters, DBError> {
+    let shelf_query: Option<String> = match filters.shelf {
+        Some(shelf_id) => shelves::table
+            .filter(shelves::id.eq(shelf_id))
+            .filter(shelves::user_id.eq(user_id))
+            .select(shelves::query)
+            .first::<Option<String>>(conn)
+            .optional()?
+            .and_then(|query| query),
+        None => None,
+    };
+    let shelf_query = match shelf_query {
+        Some(shelf_query) => shelf_query.parse::<Expr>().map_err(|error| DBError::DeserializationError(error.into()))?,
+        None => return Ok(filters.clone()),
+    };
+    let query = match filters.query {
+        Some(ref expr) => Expr::And(Box::new(expr.clone()), Box::new(shelf_query)),
+        None => shelf_query,
+    };
+    Ok(BookFilters { shelf: None, query: Some(query), ..filters.clone() })
+}
+
+// All the books matching the filters, by title
+pub fn list_matching(pool: DbPool, user_id: i32, filters: &BookFilters) -> Result<Vec<Book>, DBError> {
+    let conn = &pool.get().unwrap();
+    let filters = &with_shelf_query(conn, user_id, filters)?;
+    filtered(user_id, filters)
+        .order((dsl::title, dsl::id))
+        .load::<Book>(conn)
+}
+
+pub fn count_matching(pool: DbPool, user_id: i32, filters: &BookFilters) -> Result<i64, DBError> {
+    let conn = &pool.get().unwrap();
+    let filters = &with_shelf_query(conn, user_id, filters)?;
+    filtered(user_id, filters).count().get_result(conn)
+}
+
+// Returns a page of the user books and the total number of books matching the filters
+pub fn list_page(pool: DbPool, user_id: i32, options: &ListOptions) -> Result<(Vec<Book>, i64), DBError> {
+    let conn = &pool.get().unwrap();
+    let filters = &with_shelf_query(conn, user_id, &options.filters)?;
+    let total: i64 = filtered(user_id, filters).count().get_result(conn)?;
+
+    let query = filtered(user_id, filters);
+    let query = match (options.sort, options.order) {
+        (SortKey::Title, SortOrder::Asc) => query.order(dsl::title.asc()),
+        (SortKey::Title, SortOrder::Desc) => query.order(dsl::title.desc()),
+        (SortKey::AuthorLf, SortOrder::Asc) => query.order(dsl::author_lf.asc()),
+        (SortKey::AuthorLf, SortOrder::Desc) => query.order(dsl::author_lf.desc()),
+        // undated books last
+        (SortKey::Publicationdate, SortOrder::Asc) => query.order((dsl::publication_sort.is_null(), dsl::publication_sort.asc())),
+        (SortKey::Publicationdate, SortOrder::Desc) => query.order((dsl::publication_sort.is_null(), dsl::publication_sort.desc())),
+        (SortKey::CreatedAt, SortOrder::Asc) => query.order(dsl::created_at.asc()),
+        (SortKey::CreatedAt, SortOrder::Desc) => query.order(dsl::created_at.desc()),
+        // unrated books last, NULL being sorted first or last depending on the database
+        (SortKey::Rating, SortOrder::Asc) => query.order((dsl::rating.is_null(), dsl::rating.asc())),
+        (SortKey::Rating, SortOrder::Desc) => query.order((dsl::rating.is_null(), dsl::rating.desc())),
+    };
+    // stable ordering between pages
+    let items = query
+        .then_order_by(dsl::id.asc())
+        .offset(options.offset)
+        .limit(options.limit)
+        .load::<Book>(conn)?;
+    Ok((items, total))
+}
+
+// Adds the contributors, tags and reading progress of each book
+pub fn with_details(pool: DbPool, books: Vec<Book>) -> Result<Vec<BookDetails>, DBError> {
+    let book_ids: Vec<i32> = books.iter().map(|book| book.id).collect();
+    let mut contributors = contributor_handler::list_for_books(pool.clone(), &book_ids)?;
+    let mut tags = tag_handler::list_for_books(pool.clone(), &book_ids)?;
+    let mut progress = reading_handler::current_progress(pool, &book_ids)?;
+    Ok(books.into_iter().map(|book| BookDetails {
+        contributors: contributors.remove(&book.id).unwrap_or_else(Vec::new),
+        tags: tags.remove(&book.id).unwrap_or_else(Vec::new),
+        progress: progress.remove(&book.id),
+        book,
+    }).collect())
+}
+
+// A page of the whole library with details, in creation order, for exports
+pub fn export_page(pool: DbPool, user_id: i32, offset: i64, limit: i64) -> Result<Vec<BookDetails>, DBError> {
+    let books = {
+        let conn = &pool.get().unwrap();
+        dsl::books
+            .filter(dsl::user_id.eq(user_id))
+            .order((dsl::created_at.asc(), dsl::id.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<Book>(conn)?
+    };
+    with_details(pool, books)
+}
+
+// Main languages of the user books with their number of books, most used first
+pub fn language_counts(pool: DbPool, user_id: i32) -> Result<Vec<(String, i64)>, DBError> {
+    let conn = &pool.get().unwrap();
+    // diesel 1.x refuses to mix count_star with the grouped column
+    let count = || diesel::dsl::sql::<BigInt>("COUNT(*)");
+    dsl::books
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::language_main.ne(""))
+        .group_by(dsl::language_main)
+        .select((dsl::language_main, count()))
+        .order((count().desc(), dsl::language_main))
+        .load(conn)
+}
+
+// ---------------- Full-text search -------------
+
+// Same expression as the books_search_idx index
+#[cfg(not(feature = "test"))]
+const SEARCH_VECTOR: &str = "to_tsvector('simple', books_unaccent(title || ' ' || author_lf || ' ' || isbn || ' ' || coalesce(review, '')))";
+
+// Searches title, author, isbn and review, best matches first.
+// Accents and case are ignored, every term of the query must match.
+#[cfg(not(feature = "test"))]
+pub fn search(pool: DbPool, user_id: i32, terms: &str, limit: i64) -> Result<Vec<Book>, DBError> {
+    let conn = &pool.get().unwrap();
+    let query = format!(
+        "SELECT * FROM books \
+         WHERE user_id = $1 AND {vector} @@ plainto_tsquery('simple', books_unaccent($2)) \
+         ORDER BY ts_rank({vector}, plainto_tsquery('simple', books_unaccent($2))) DESC, id \
+         LIMIT $3",
+        vector = SEARCH_VECTOR
+    );
+    diesel::sql_query(query)
+        .bind::<Integer, _>(user_id)
+        .bind::<Text, _>(terms)
+        .bind::<BigInt, _>(limit)
+        .load::<Book>(conn)
+}
+
+#[cfg(feature = "test")]
+pub fn search(pool: DbPool, user_id: i32, terms: &str, limit: i64) -> Result<Vec<Book>, DBError> {
+    let conn = &pool.get().unwrap();
+    diesel::sql_query(
+        "SELECT books.* FROM books JOIN books_fts ON books_fts.rowid = books.id \
+         WHERE books.user_id = ? AND books_fts MATCH ? \
+         ORDER BY books_fts.rank, books.id \
+         LIMIT ?")
+        .bind::<Integer, _>(user_id)
+        .bind::<Text, _>(fts_query(terms))
+        .bind::<BigInt, _>(limit)
+        .load::<Book>(conn)
+}
+
+// Quotes each term so that FTS5 operators typed by the user are not interpreted
+#[cfg(feature = "test")]
+fn fts_query(terms: &str) -> String {
+    terms.split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn find_by_librarything_id(pool: DbPool, user_id: i32, librarything_id: &str) -> Result<Option<Book>, DBError> {
+    let conn = &pool.get().unwrap();
+    find_librarything_book(conn, user_id, librarything_id)
+}
+
+pub(crate) fn find_librarything_book(conn: &MyConnection, user_id: i32, librarything_id: &str) -> Result<Option<Book>, DBError> {
+    dsl::books
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::librarything_id.eq(librarything_id))
+        .first::<Book>(conn)
+        .optional()
+}
+
+// The book of an export of the user library : the ids of another library are told apart by their creation date,
+// exported to the second
+pub(crate) fn find_exported_book(conn: &MyConnection, user_id: i32, book_id: i32, created_at: Option<NaiveDateTime>) -> Result<Option<Book>, DBError> {
+    let book = dsl::books
+        .filter(dsl::id.eq(book_id))
+        .filter(dsl::user_id.eq(user_id))
+        .first::<Book>(conn)
+        .optional()?;
+    Ok(book.filter(|book| Some(book.created_at.timestamp()) == created_at.map(|date| date.timestamp())))
+}
+
+// The first book of the user with this ISBN
+pub(crate) fn find_isbn_book(conn: &MyConnection, user_id: i32, isbn: &str) -> Result<Option<Book>, DBError> {
+    dsl::books
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::isbn.eq(isbn))
+        .order(dsl::id)
+        .first::<Book>(conn)
+        .optional()
+}
+
+// Checks that the book exists and belongs to the given user
+pub fn is_owner(pool: DbPool, user_id: i32, book_id: i32) -> Result<bool, DBError> {
+    let conn = &pool.get().unwrap();
+    diesel::dsl::select(diesel::dsl::exists(
+            dsl::books
+            .filter(dsl::id.eq(book_id))
+            .filter(dsl::user_id.eq(user_id))
+    ))
+        .get_result(conn)
+}
+
+// Returns DBError::NotFound when the book does not belong to the user
+pub fn get(pool: DbPool, user_id: i32, book_id: i32) -> Result<Book, DBError> {
+    let conn = &pool.get().unwrap();
+    dsl::books
+        .filter(dsl::id.eq(book_id))
+        .filter(dsl::user_id.eq(user_id))
+        .first::<Book>(conn)
+}
+
+pub fn update(pool: DbPool, user_id: i32, book_id: i32, changes: &BookChanges) -> Result<Book, DBError> {
+    let conn = &pool.get().unwrap();
+    apply_changes(conn, user_id, book_id, changes)
+}
+
+pub(crate) fn apply_changes(conn: &MyConnection, user_id: i32, book_id: i32, changes: &BookChanges) -> Result<Book, DBError> {
+    if changes.is_empty() {
+        return dsl::books
+            .filter(dsl::id.eq(book_id))
+            .filter(dsl::user_id.eq(user_id))
+            .first::<Book>(conn);
+    }
+    let changes = &BookChanges {
+        publication_sort: changes.publicationdate.as_ref().map(|date| partial_date::sort_key(date)),
+        ..changes.clone()
+    };
+    let target = dsl::books
+        .filter(dsl::id.eq(book_id))
+        .filter(dsl::user_id.eq(user_id));
+
+    #[cfg(not(feature = "test"))]
+    let updated_book: Book = diesel::update(target).set(changes).get_result(conn)?;
+
+    #[cfg(feature = "test")]
+    let updated_book: Book = {
+        let count = diesel::update(target).set(changes).execute(conn)?;
+        if count == 0 {
+            return Err(DBError::NotFound);
+        }
+        dsl::books.filter(dsl::id.eq(book_id)).first(conn)?
+    };
+
+    Ok(updated_book)
+}
+
+// Rewrites the publication dates in their canonical form with their sort key, returns the number of books changed
+pub fn normalize_publication_dates(pool: DbPool) -> Result<usize, DBError> {
+    let conn = &pool.get().unwrap();
+    normalize_dates(conn)
+}
+
+// Data migration : also run at startup, for the dates not converted by the publication_sort SQL migration
+pub(crate) fn normalize_dates(conn: &MyConnection) -> Result<usize, DBError> {
+    let dates: Vec<(i32, String, Option<i32>)> = dsl::books
+        .select((dsl::id, dsl::publicationdate, dsl::publication_sort))
+        .load(conn)?;
+    let mut changed = 0;
+    for (book_id, date, sort) in dates {
+        let canonical = partial_date::canonical(&date);
+        let canonical_sort = partial_date::sort_key(&canonical);
+        if canonical != date || canonical_sort != sort {
+            diesel::update(dsl::books.filter(dsl::id.eq(book_id)))
+                .set((dsl::publicationdate.eq(canonical), dsl::publication_sort.eq(canonical_sort)))
+                .execute(conn)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+// Data migration : the language names left by the language_codes SQL migration are replaced by their codes,
+// unknown languages are kept. Returns the number of books changed.
+pub(crate) fn normalize_languages(conn: &MyConnection) -> Result<usize, DBError> {
+    let languages: Vec<(i32, String, Option<String>, String)> = dsl::books
+        .select((dsl::id, dsl::language_main, dsl::language_secondary, dsl::language_original))
+        .load(conn)?;
+    let mut changed = 0;
+    for (book_id, main, secondary, original) in languages {
+        let codes = (
+            language::normalize_or_keep(&main),
+            secondary.as_ref().map(|secondary| language::normalize_or_keep(secondary)),
+            language::normalize_or_keep(&original),
+        );
+        if codes != (main, secondary, original) {
+            diesel::update(dsl::books.filter(dsl::id.eq(book_id)))
+                .set((dsl::language_main.eq(codes.0), dsl::language_secondary.eq(codes.1), dsl::language_original.eq(codes.2)))
+                .execute(conn)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+pub fn delete(pool: DbPool, user_id: i32, book_id: i32) -> Result<(), DBError> {
+    if !is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        // foreign keys are not enforced by sqlite
+        diesel::delete(book_contributors::table.filter(book_contributors::book_id.eq(book_id)))
+            .execute(conn)?;
+        diesel::delete(book_tags::table.filter(book_tags::book_id.eq(book_id)))
+            .execute(conn)?;
+        diesel::delete(shelf_books::table.filter(shelf_books::book_id.eq(book_id)))
+            .execute(conn)?;
+        diesel::delete(borrow_requests::table.filter(borrow_requests::book_id.eq(book_id)))
+            .execute(conn)?;
+        diesel::delete(loans::table.filter(loans::book_id.eq(book_id)))
+            .execute(conn)?;
+        let reading_ids = readings::table.select(readings::id).filter(readings::book_id.eq(book_id));
+        diesel::delete(reading_progress::table.filter(reading_progress::reading_id.eq_any(reading_ids)))
+            .execute(conn)?;
+        diesel::delete(readings::table.filter(readings::book_id.eq(book_id)))
+            .execute(conn)?;
+        diesel::delete(dsl::books.filter(dsl::id.eq(book_id))).execute(conn)?;
+        Ok(())
+    })
+}
+
 // pub fn fetch(pool: DbPool, email: &String, login: &String) -> Result<Vec<SlimUser>, DBError> {
 //     use crate::khnum::schema::users::dsl;
 //     let conn = &pool.get().unwrap();
@@ -0,0 +1,178 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::repository::{contributor_handler, friend_handler, loan_handler};
+use crate::repository::loan_handler::Borrower;
+use crate::schema::{books, borrow_requests, friends};
+use crate::models::{BorrowRequest, BorrowRequestDetails, BorrowState, NewBorrowRequest, RequestedBook, Role};
+
+fn visible_owner_of(conn: &MyConnection, user_id: i32, book_id: i32) -> Result<i32, DBError> {
+    let owner_id = books::table
+        .filter(books::id.eq(book_id))
+        .select(books::user_id)
+        .first(conn)?;
+    if !friend_handler::can_see(conn, user_id, owner_id)? {
+        return Err(DBError::NotFound);
+    }
+    Ok(owner_id)
+}
+
+// The user owning a book : DBError::NotFound when the book is not visible to the given user, see friend_handler
+pub fn owner_of(pool: DbPool, user_id: i32, book_id: i32) -> Result<i32, DBError> {
+    let conn = &pool.get().unwrap();
+    visible_owner_of(conn, user_id, book_id)
+}
+
+// The request of the user for this book still waiting for an answer, if any
+pub fn pending(pool: DbPool, requester_id: i32, book_id: i32) -> Result<Option<BorrowRequest>, DBError> {
+    let conn = &pool.get().unwrap();
+    borrow_requests::table
+        .filter(borrow_requests::requester_id.eq(requester_id))
+        .filter(borrow_requests::book_id.eq(book_id))
+        .filter(borrow_requests::state.eq(BorrowState::Pending.as_str()))
+        .first::<BorrowRequest>(conn)
+        .optional()
+}
+
+// DBError::NotFound when the book is not visible to the requester
+pub fn request(pool: DbPool, requester_id: i32, book_id: i32, message: Option<String>) -> Result<BorrowRequest, DBError> {
+    let conn = &pool.get().unwrap();
+    let owner_id = visible_owner_of(conn, requester_id, book_id)?;
+    let now = Utc::now().naive_utc();
+    let request = NewBorrowRequest {
+        book_id,
+        owner_id,
+        requester_id,
+        state: BorrowState::Pending.as_str().to_string(),
+        message,
+        loan_id: None,
+        created_at: now,
+        updated_at: now,
+    };
+    #[cfg(not(feature = "test"))]
+    let inserted_request: BorrowRequest = diesel::insert_into(borrow_requests::table).values(&request).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(borrow_requests::table).values(&request).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_request: BorrowRequest = borrow_requests::table.order(borrow_requests::id.desc()).first(conn)?;
+
+    Ok(inserted_request)
+}
+
+// Returns DBError::NotFound when the user is neither the owner nor the requester
+pub fn get(pool: DbPool, user_id: i32, request_id: i32) -> Result<BorrowRequest, DBError> {
+    let conn = &pool.get().unwrap();
+    borrow_requests::table
+        .filter(borrow_requests::id.eq(request_id))
+        .filter(borrow_requests::owner_id.eq(user_id).or(borrow_requests::requester_id.eq(user_id)))
+        .first::<BorrowRequest>(conn)
+}
+
+type RequestRow = (BorrowRequest, (i32, String, String));
+
+// The title and the authors of the requested books only : the review, the rating... are kept private
+fn with_books(conn: &MyConnection, rows: Vec<RequestRow>) -> Result<Vec<BorrowRequestDetails>, DBError> {
+    let book_ids: Vec<i32> = rows.iter().map(|(_request, (book_id, _title, _author_lf))| *book_id).collect();
+    let contributors = contributor_handler::load_for_books(conn, &book_ids)?;
+    Ok(rows.into_iter().map(|(request, (id, title, author_lf))| {
+        let mut authors: Vec<String> = contributors.get(&id).into_iter().flatten()
+            .filter(|contributor| contributor.role == Role::Author)
+            .map(|contributor| contributor.name.clone())
+            .collect();
+        // books without contributors
+        if authors.is_empty() && !author_lf.is_empty() {
+            authors.push(author_lf);
+        }
+        BorrowRequestDetails { request, book: RequestedBook { id, title, authors } }
+    }).collect())
+}
+
+const REQUESTED_BOOK: (books::id, books::title, books::author_lf) = (books::id, books::title, books::author_lf);
+
+pub fn get_details(pool: DbPool, user_id: i32, request_id: i32) -> Result<BorrowRequestDetails, DBError> {
+    let conn = &pool.get().unwrap();
+    let row = borrow_requests::table
+        .inner_join(books::table)
+        .filter(borrow_requests::id.eq(request_id))
+        .filter(borrow_requests::owner_id.eq(user_id).or(borrow_requests::requester_id.eq(user_id)))
+        .select((borrow_requests::all_columns, REQUESTED_BOOK))
+        .first::<RequestRow>(conn)?;
+    Ok(with_books(conn, vec![row])?.remove(0))
+}
+
+// Requests made to the owner of the books, most recent first
+pub fn received(pool: DbPool, owner_id: i32) -> Result<Vec<BorrowRequestDetails>, DBError> {
+    let conn = &pool.get().unwrap();
+    let rows = borrow_requests::table
+        .inner_join(books::table)
+        .filter(borrow_requests::owner_id.eq(owner_id))
+        .select((borrow_requests::all_columns, REQUESTED_BOOK))
+        .order((borrow_requests::created_at.desc(), borrow_requests::id.desc()))
+        .load(conn)?;
+    with_books(conn, rows)
+}
+
+// Requests made by the user for the books still visible to them, most recent first
+pub fn sent(pool: DbPool, requester_id: i32) -> Result<Vec<BorrowRequestDetails>, DBError> {
+    let conn = &pool.get().unwrap();
+    let visible_owners = friends::table
+        .filter(friends::friend_id.eq(requester_id))
+        .select(friends::user_id);
+    let rows = borrow_requests::table
+        .inner_join(books::table)
+        .filter(borrow_requests::requester_id.eq(requester_id))
+        .filter(borrow_requests::owner_id.eq_any(visible_owners))
+        .select((borrow_requests::all_columns, REQUESTED_BOOK))
+        .order((borrow_requests::created_at.desc(), borrow_requests::id.desc()))
+        .load(conn)?;
+    with_books(conn, rows)
+}
+
+fn set_state(conn: &MyConnection, request_id: i32, state: BorrowState, loan_id: Option<i32>) -> Result<BorrowRequest, DBError> {
+    diesel::update(borrow_requests::table.filter(borrow_requests::id.eq(request_id)))
+        .set((
+            borrow_requests::state.eq(state.as_str()),
+            borrow_requests::loan_id.eq(loan_id),
+            borrow_requests::updated_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(conn)?;
+    borrow_requests::table.filter(borrow_requests::id.eq(request_id)).first(conn)
+}
+
+// Only the owner answers a request : DBError::NotFound otherwise.
+// The transition is checked by the caller, see borrow::next
+// The loan and the new state of the request are saved together
+pub fn accept(pool: DbPool, owner_id: i32, request_id: i32, requester_login: &str, lent_at: NaiveDateTime, due_at: Option<NaiveDateTime>) -> Result<BorrowRequest, DBError> {
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let request = borrow_requests::table
+            .filter(borrow_requests::id.eq(request_id))
+            .filter(borrow_requests::owner_id.eq(owner_id))
+            .first::<BorrowRequest>(conn)?;
+        let borrower = Borrower::User { id: request.requester_id, login: requester_login.to_string() };
+        let loan = loan_handler::insert(conn, owner_id, request.book_id, borrower, lent_at, due_at, request.message)?;
+        set_state(conn, request_id, BorrowState::Accepted, Some(loan.id))
+    })
+}
+
+pub fn decline(pool: DbPool, owner_id: i32, request_id: i32) -> Result<BorrowRequest, DBError> {
+    let request = get(pool.clone(), owner_id, request_id)?;
+    if request.owner_id != owner_id {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    set_state(conn, request_id, BorrowState::Declined, None)
+}
+
+// The loan of the request is ended, which returns the request
+pub fn give_back(pool: DbPool, owner_id: i32, request_id: i32, returned_at: NaiveDateTime) -> Result<BorrowRequest, DBError> {
+    let request = get(pool.clone(), owner_id, request_id)?;
+    if request.owner_id != owner_id {
+        return Err(DBError::NotFound);
+    }
+    loan_handler::give_back(pool.clone(), owner_id, request.book_id, returned_at, None)?;
+    get(pool, owner_id, request_id)
+}
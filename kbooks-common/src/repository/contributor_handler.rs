@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::schema::{authors, book_contributors};
+use crate::models::{BookContributor, Contributor, Role};
+
+// Replaces the contributors of a book, in the given order
+pub fn set_for_book(pool: DbPool, book_id: i32, contributors: &[(i32, Role)]) -> Result<(), DBError> {
+    let conn = &pool.get().unwrap();
+    replace_for_book(conn, book_id, contributors)
+}
+
+pub(crate) fn replace_for_book(conn: &MyConnection, book_id: i32, contributors: &[(i32, Role)]) -> Result<(), DBError> {
+    let mut rows: Vec<BookContributor> = Vec::new();
+    for (position, (author_id, role)) in contributors.iter().enumerate() {
+        // the same person can only appear once for a given role
+        if rows.iter().any(|row| row.author_id == *author_id && row.role == role.as_str()) {
+            continue;
+        }
+        rows.push(BookContributor {
+            book_id,
+            author_id: *author_id,
+            role: role.as_str().to_string(),
+            position: position as i32,
+        });
+    }
+
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(book_contributors::table.filter(book_contributors::book_id.eq(book_id)))
+            .execute(conn)?;
+        // no batch insert with sqlite
+        for row in &rows {
+            diesel::insert_into(book_contributors::table).values(row).execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn list_for_books(pool: DbPool, book_ids: &[i32]) -> Result<HashMap<i32, Vec<Contributor>>, DBError> {
+    let conn = &pool.get().unwrap();
+    load_for_books(conn, book_ids)
+}
+
+pub(crate) fn load_for_books(conn: &MyConnection, book_ids: &[i32]) -> Result<HashMap<i32, Vec<Contributor>>, DBError> {
+    let rows: Vec<(i32, String, i32, String, String)> = book_contributors::table
+        .inner_join(authors::table)
+        .filter(book_contributors::book_id.eq_any(book_ids))
+        .order((book_contributors::book_id, book_contributors::position))
+        .select((book_contributors::book_id, book_contributors::role, authors::id, authors::name, authors::code))
+        .load(conn)?;
+
+    let mut by_book: HashMap<i32, Vec<Contributor>> = HashMap::new();
+    for (book_id, role, author_id, name, code) in rows {
+        let role = match role.parse::<Role>() {
+            Ok(role) => role,
+            Err(_) => continue,
+        };
+        by_book.entry(book_id).or_insert_with(Vec::new).push(Contributor { author_id, name, code, role });
+    }
+    Ok(by_book)
+}
@@ -0,0 +1,40 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::repository::{author_handler, book_handler};
+use crate::schema::data_migrations;
+
+// Conversions of the existing rows that need the Rust code (author names...) and can not be written in SQL.
+// Each step returns the number of rows changed.
+type Step = fn(&MyConnection) -> Result<usize, DBError>;
+
+// In the order of the SQL migrations they complete
+const STEPS: &[(&str, Step)] = &[
+    ("2026-10-18-100000_normalize_author_names", author_handler::normalize_names),
+    ("2026-10-18-140000_normalize_publication_dates", book_handler::normalize_dates),
+    ("2026-10-18-150000_normalize_language_codes", book_handler::normalize_languages),
+];
+
+// Runs the steps not applied yet, after the SQL migrations : returns their names and the number of rows changed
+pub fn run(pool: DbPool) -> Result<Vec<(&'static str, usize)>, DBError> {
+    let conn = &pool.get().unwrap();
+    let applied: Vec<String> = data_migrations::table.select(data_migrations::name).load(conn)?;
+    let mut report = Vec::new();
+    for (name, step) in STEPS {
+        if applied.iter().any(|applied| applied == name) {
+            continue;
+        }
+        let changed = conn.transaction::<_, DBError, _>(|| {
+            let changed = step(conn)?;
+            diesel::insert_into(data_migrations::table)
+                .values((data_migrations::name.eq(*name), data_migrations::applied_at.eq(Utc::now().naive_utc())))
+                .execute(conn)?;
+            Ok(changed)
+        })?;
+        report.push((*name, changed));
+    }
+    Ok(report)
+}
@@ -0,0 +1,57 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::schema::{friends, users};
+use crate::models::Friend;
+
+// The friends of the user, by login
+pub fn list(pool: DbPool, user_id: i32) -> Result<Vec<Friend>, DBError> {
+    let conn = &pool.get().unwrap();
+    friends::table
+        .inner_join(users::table.on(users::id.eq(friends::friend_id)))
+        .filter(friends::user_id.eq(user_id))
+        .select((users::id, users::login, friends::created_at))
+        .order(users::login)
+        .load::<Friend>(conn)
+}
+
+// The library of the user becomes visible to the friend : a unique violation when it already is
+pub fn add(pool: DbPool, user_id: i32, friend_id: i32) -> Result<(), DBError> {
+    let conn = &pool.get().unwrap();
+    diesel::insert_into(friends::table)
+        .values((
+            friends::user_id.eq(user_id),
+            friends::friend_id.eq(friend_id),
+            friends::created_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn remove(pool: DbPool, user_id: i32, friend_id: i32) -> Result<(), DBError> {
+    let conn = &pool.get().unwrap();
+    let count = diesel::delete(
+        friends::table
+        .filter(friends::user_id.eq(user_id))
+        .filter(friends::friend_id.eq(friend_id))
+    ).execute(conn)?;
+    if count == 0 {
+        return Err(DBError::NotFound);
+    }
+    Ok(())
+}
+
+// A library is visible to its owner and to the owner's friends
+pub(crate) fn can_see(conn: &MyConnection, viewer_id: i32, owner_id: i32) -> Result<bool, DBError> {
+    if viewer_id == owner_id {
+        return Ok(true);
+    }
+    diesel::dsl::select(diesel::dsl::exists(
+        friends::table
+            .filter(friends::user_id.eq(owner_id))
+            .filter(friends::friend_id.eq(viewer_id))
+    )).get_result(conn)
+}
@@ -0,0 +1,83 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+
+use crate::khnum::wiring::{DbPool, MyBackend, MyConnection};
+
+use crate::goals::{self as goal_progress, GoalProgress};
+use crate::repository::stats_handler;
+use crate::schema::goals;
+use crate::models::{Goal, NewGoal};
+
+// Goals of a user, for one year or for all of them, most recent year first
+pub fn list(pool: DbPool, user_id: i32, year: Option<i32>) -> Result<Vec<Goal>, DBError> {
+    let conn = &pool.get().unwrap();
+    let mut query: goals::BoxedQuery<MyBackend> = goals::table
+        .filter(goals::user_id.eq(user_id))
+        .into_boxed();
+    if let Some(year) = year {
+        query = query.filter(goals::year.eq(year));
+    }
+    query.order((goals::year.desc(), goals::id)).load(conn)
+}
+
+// A concurrent creation of the same goal makes the insert fail, see goals_unique_idx
+const MAX_SET_ATTEMPTS: usize = 3;
+
+// A user has one goal per year, kind and language : the target of an existing one is replaced
+pub fn set(pool: DbPool, goal: NewGoal) -> Result<Goal, DBError> {
+    let conn = &pool.get().unwrap();
+    let mut attempt = 1;
+    loop {
+        match conn.transaction(|| update_or_insert(conn, &goal)) {
+            Err(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) if attempt < MAX_SET_ATTEMPTS => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+fn update_or_insert(conn: &MyConnection, goal: &NewGoal) -> Result<Goal, DBError> {
+    let mut same_goal: goals::BoxedQuery<MyBackend> = goals::table
+        .filter(goals::user_id.eq(goal.user_id))
+        .filter(goals::year.eq(goal.year))
+        .filter(goals::kind.eq(&goal.kind))
+        .into_boxed();
+    same_goal = match &goal.language {
+        Some(language) => same_goal.filter(goals::language.eq(language)),
+        None => same_goal.filter(goals::language.is_null()),
+    };
+    if let Some(existing) = same_goal.first::<Goal>(conn).optional()? {
+        diesel::update(goals::table.filter(goals::id.eq(existing.id)))
+            .set(goals::target.eq(goal.target))
+            .execute(conn)?;
+        return goals::table.filter(goals::id.eq(existing.id)).first(conn);
+    }
+
+    #[cfg(not(feature = "test"))]
+    let inserted_goal: Goal = diesel::insert_into(goals::table).values(goal).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(goals::table).values(goal).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_goal: Goal = goals::table.order(goals::id.desc()).first(conn)?;
+
+    Ok(inserted_goal)
+}
+
+pub fn delete(pool: DbPool, user_id: i32, goal_id: i32) -> Result<(), DBError> {
+    let conn = &pool.get().unwrap();
+    let count = diesel::delete(
+        goals::table
+        .filter(goals::id.eq(goal_id))
+        .filter(goals::user_id.eq(user_id))
+    ).execute(conn)?;
+    if count == 0 {
+        return Err(DBError::NotFound);
+    }
+    Ok(())
+}
+
+// Progress of the goals of a user at the given date
+pub fn with_progress(pool: DbPool, user_id: i32, goals: Vec<Goal>, today: NaiveDate) -> Result<Vec<GoalProgress>, DBError> {
+    let readings = stats_handler::finished_readings(pool, user_id, None)?;
+    Ok(goals.into_iter().map(|goal| goal_progress::progress(goal, &readings, today)).collect())
+}
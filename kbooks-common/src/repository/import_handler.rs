@@ -0,0 +1,216 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::{isbn, language, partial_date};
+use crate::import::{ImportReport, ImportReportRow, ImportStatus, ImportedBook, ParsedRow};
+use crate::models::{Author, Book, BookChanges, NewBook, Role};
+use crate::repository::{author_handler, book_handler, contributor_handler, reading_handler, tag_handler};
+use crate::schema::users;
+
+// Saves the parsed books of a user.
+// Books already imported (same id in our exports, same librarything_id, or same ISBN for the rows
+// without id) are updated, or skipped when nothing changed.
+// Each row is saved in its own transaction, a row that can not be saved is reported as skipped.
+pub fn import(pool: DbPool, user_id: i32, rows: Vec<ParsedRow>) -> Result<ImportReport, DBError> {
+    let conn = &pool.get().unwrap();
+    // for the books without language
+    let locale: String = users::table.find(user_id).select(users::language).first(conn)?;
+    let default_language = language::from_locale(&locale).map(|language| language.code).unwrap_or("");
+    let mut report = ImportReport::default();
+    for parsed in rows {
+        let report_row = match parsed.book {
+            Err(message) => ImportReportRow {
+                row: parsed.row,
+                title: String::new(),
+                status: ImportStatus::Skipped,
+                book_id: None,
+                message: Some(message),
+            },
+            Ok(mut book) => {
+                let title = book.title.clone();
+                // the book is saved without its invalid ISBN
+                let message = match isbn::validate(&book.isbn) {
+                    Ok(valid) => { book.isbn = valid; None }
+                    Err(_) if book.isbn.trim().is_empty() => None,
+                    Err(error) => {
+                        let message = format!("isbn {} ignored: {}", book.isbn.trim(), error);
+                        book.isbn = String::new();
+                        Some(message)
+                    }
+                };
+                match conn.transaction(|| save(conn, user_id, default_language, book)) {
+                    Ok((status, book_id)) => ImportReportRow { row: parsed.row, title, status, book_id: Some(book_id), message },
+                    // such as a concurrent creation of the same author : the other rows are still imported
+                    Err(error) => ImportReportRow {
+                        row: parsed.row,
+                        title,
+                        status: ImportStatus::Skipped,
+                        book_id: None,
+                        message: Some(format!("not saved: {}", error)),
+                    },
+                }
+            }
+        };
+        report.rows.push(report_row);
+    }
+    Ok(report)
+}
+
+fn contributors(conn: &MyConnection, author: &Author, book: &ImportedBook) -> Result<Vec<(i32, Role)>, DBError> {
+    let mut contributors = vec![(author.id, Role::Author)];
+    for (name, role) in &book.contributors {
+        let contributor = author_handler::find_or_create(conn, name)?;
+        // stored once per person and role
+        if !contributors.contains(&(contributor.id, *role)) {
+            contributors.push((contributor.id, *role));
+        }
+    }
+    Ok(contributors)
+}
+
+fn is_unchanged(existing: &Book, new: &NewBook) -> bool {
+    existing.title == new.title
+        && existing.author_lf == new.author_lf
+        && existing.author_code == new.author_code
+        && existing.isbn == new.isbn
+        && existing.publicationdate == new.publicationdate
+        && existing.rating == new.rating
+        && existing.language_main == new.language_main
+        && existing.language_secondary == new.language_secondary
+        && existing.language_original == new.language_original
+        && existing.review == new.review
+        && existing.dateacquired_stamp == new.dateacquired_stamp
+        && existing.started_stamp == new.started_stamp
+        && existing.finished_stamp == new.finished_stamp
+        && existing.author_id == new.author_id
+        && existing.page_count == new.page_count
+}
+
+fn save(conn: &MyConnection, user_id: i32, default_language: &str, book: ImportedBook) -> Result<(ImportStatus, i32), DBError> {
+    let exported = match book.kbooks_id {
+        Some(book_id) => book_handler::find_exported_book(conn, user_id, book_id, book.created_at)?,
+        None => None,
+    };
+    let existing = match (exported, &book.librarything_id) {
+        (Some(exported), _) => Some(exported),
+        (None, Some(librarything_id)) => book_handler::find_librarything_book(conn, user_id, librarything_id)?,
+        // Goodreads exports have no LibraryThing id
+        (None, None) if !book.isbn.is_empty() => book_handler::find_isbn_book(conn, user_id, &book.isbn)?,
+        (None, None) => None,
+    };
+    let (existing_contributors, existing_tags) = match &existing {
+        Some(existing) => (
+            contributor_handler::load_for_books(conn, &[existing.id])?
+                .remove(&existing.id).unwrap_or_else(Vec::new)
+                .into_iter().map(|contributor| (contributor.author_id, contributor.role)).collect(),
+            tag_handler::load_for_books(conn, &[existing.id])?
+                .remove(&existing.id).unwrap_or_else(Vec::new),
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let author = author_handler::find_or_create(conn, &book.author)?;
+    let mut contributors = contributors(conn, &author, &book)?;
+    if book.authors_only {
+        for contributor in existing_contributors.iter().filter(|(_, role)| *role != Role::Author) {
+            contributors.push(*contributor);
+        }
+    }
+    let mut tags: Vec<String> = if book.shelves { existing_tags.clone() } else { Vec::new() };
+    for tag in book.tags.iter().map(|tag| tag_handler::normalize(tag)) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    // the columns missing from the export keep the stored values, an empty column clears them
+    let language_main = match (book.language_main.as_ref().map(|language| language::normalize_or_keep(language)), &existing) {
+        (Some(language), _) if !language.is_empty() => language,
+        (None, Some(existing)) => existing.language_main.clone(),
+        _ => default_language.to_string(),
+    };
+    let language_original = match (book.language_original.as_ref().map(|language| language::normalize_or_keep(language)), &existing) {
+        (Some(language), _) if !language.is_empty() => language,
+        (None, Some(existing)) => existing.language_original.clone(),
+        _ => language_main.clone(),
+    };
+    let language_secondary = match (&book.language_secondary, &existing) {
+        (Some(secondary), _) => secondary.as_ref().map(|secondary| language::normalize_or_keep(secondary)),
+        (None, Some(existing)) => existing.language_secondary.clone(),
+        (None, None) => None,
+    };
+    let started_stamp = match (book.started_stamp, &existing) {
+        (Some(started), _) => started,
+        (None, Some(existing)) => existing.started_stamp,
+        (None, None) => None,
+    };
+    let new_book = NewBook {
+        user_id,
+        librarything_id: book.librarything_id.clone(),
+        title: book.title,
+        author_lf: author.name,
+        author_code: author.code,
+        isbn: book.isbn,
+        publicationdate: partial_date::canonical(&book.publicationdate),
+        rating: book.rating,
+        language_main,
+        language_secondary,
+        language_original,
+        review: book.review,
+        cover: "".to_string(),
+        created_at: book.created_at.unwrap_or_else(|| Utc::now().naive_utc()),
+        dateacquired_stamp: book.dateacquired_stamp,
+        started_stamp,
+        finished_stamp: book.finished_stamp,
+        author_id: Some(author.id),
+        page_count: book.page_count,
+    };
+
+    match existing {
+        None => {
+            let inserted = book_handler::insert(conn, &new_book)?;
+            contributor_handler::replace_for_book(conn, inserted.id, &contributors)?;
+            tag_handler::replace_for_book(conn, user_id, inserted.id, &tags)?;
+            reading_handler::record(conn, user_id, inserted.id, started_stamp, book.finished_stamp)?;
+            Ok((ImportStatus::Created, inserted.id))
+        }
+        Some(existing) => {
+            let mut existing_tags = existing_tags;
+            existing_tags.sort();
+            let mut new_tags = tags.clone();
+            new_tags.sort();
+
+            if is_unchanged(&existing, &new_book) && existing_contributors == contributors && existing_tags == new_tags {
+                return Ok((ImportStatus::Skipped, existing.id));
+            }
+
+            // every column of the export is written : an empty field clears the stored value
+            let changes = BookChanges {
+                title: Some(new_book.title),
+                author_lf: Some(new_book.author_lf),
+                author_code: Some(new_book.author_code),
+                isbn: Some(new_book.isbn),
+                publicationdate: Some(new_book.publicationdate),
+                rating: Some(new_book.rating),
+                language_main: Some(new_book.language_main),
+                language_secondary: Some(new_book.language_secondary),
+                language_original: Some(new_book.language_original),
+                review: Some(new_book.review),
+                dateacquired_stamp: Some(new_book.dateacquired_stamp),
+                started_stamp: Some(new_book.started_stamp),
+                finished_stamp: Some(new_book.finished_stamp),
+                author_id: Some(new_book.author_id),
+                page_count: Some(new_book.page_count),
+                ..BookChanges::default()
+            };
+            book_handler::apply_changes(conn, user_id, existing.id, &changes)?;
+            contributor_handler::replace_for_book(conn, existing.id, &contributors)?;
+            tag_handler::replace_for_book(conn, user_id, existing.id, &tags)?;
+            reading_handler::record(conn, user_id, existing.id, started_stamp, book.finished_stamp)?;
+            Ok((ImportStatus::Updated, existing.id))
+        }
+    }
+}
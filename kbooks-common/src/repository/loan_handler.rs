@@ -0,0 +1,112 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::repository::book_handler;
+use crate::schema::{books, borrow_requests, loans};
+use crate::models::{Book, BorrowState, LentBook, Loan, NewLoan};
+
+// Someone the books are lent to : a registered user or anybody else
+#[derive(Clone, Debug, PartialEq)]
+pub enum Borrower {
+    User { id: i32, login: String },
+    Name(String),
+}
+
+// Loans of a book, oldest first
+pub fn list_for_book(pool: DbPool, user_id: i32, book_id: i32) -> Result<Vec<Loan>, DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    loans::table
+        .filter(loans::book_id.eq(book_id))
+        .order((loans::lent_at, loans::id))
+        .load::<Loan>(conn)
+}
+
+// The loan in progress, if any
+pub fn current(pool: DbPool, user_id: i32, book_id: i32) -> Result<Option<Loan>, DBError> {
+    let loans = list_for_book(pool, user_id, book_id)?;
+    Ok(loans.into_iter().find(|loan| loan.returned_at.is_none()))
+}
+
+pub fn lend(pool: DbPool, user_id: i32, book_id: i32, borrower: Borrower, lent_at: NaiveDateTime, due_at: Option<NaiveDateTime>, notes: Option<String>) -> Result<Loan, DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    insert(conn, user_id, book_id, borrower, lent_at, due_at, notes)
+}
+
+// A unique violation when the book is already lent, see loans_in_progress_idx
+pub(crate) fn insert(conn: &MyConnection, user_id: i32, book_id: i32, borrower: Borrower, lent_at: NaiveDateTime, due_at: Option<NaiveDateTime>, notes: Option<String>) -> Result<Loan, DBError> {
+    let (borrower_name, borrower_id) = match borrower {
+        Borrower::User { id, login } => (login, Some(id)),
+        Borrower::Name(name) => (name, None),
+    };
+    let loan = NewLoan {
+        book_id,
+        user_id,
+        borrower_name,
+        borrower_id,
+        lent_at,
+        due_at,
+        returned_at: None,
+        notes,
+        created_at: Utc::now().naive_utc(),
+    };
+    #[cfg(not(feature = "test"))]
+    let inserted_loan: Loan = diesel::insert_into(loans::table).values(&loan).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(loans::table).values(&loan).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_loan: Loan = loans::table.order(loans::id.desc()).first(conn)?;
+
+    Ok(inserted_loan)
+}
+
+// Ends the loan in progress : DBError::NotFound when the book is not lent.
+// The notes are added to the ones written when lending, and the borrow request of the loan is returned.
+pub fn give_back(pool: DbPool, user_id: i32, book_id: i32, returned_at: NaiveDateTime, notes: Option<String>) -> Result<Loan, DBError> {
+    let loan = current(pool.clone(), user_id, book_id)?.ok_or(DBError::NotFound)?;
+    let loan_id = loan.id;
+    let notes = match (loan.notes, notes) {
+        (Some(previous), Some(notes)) => Some(format!("{}\n{}", previous, notes)),
+        (previous, notes) => notes.or(previous),
+    };
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::update(loans::table.filter(loans::id.eq(loan_id)))
+            .set((loans::returned_at.eq(returned_at), loans::notes.eq(notes)))
+            .execute(conn)?;
+        diesel::update(borrow_requests::table.filter(borrow_requests::loan_id.eq(loan_id)))
+            .set((
+                borrow_requests::state.eq(BorrowState::Returned.as_str()),
+                borrow_requests::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+        loans::table.filter(loans::id.eq(loan_id)).first(conn)
+    })
+}
+
+// Books currently lent by the user, the longest lent first
+pub fn lent(pool: DbPool, user_id: i32) -> Result<Vec<LentBook>, DBError> {
+    let conn = &pool.get().unwrap();
+    let rows: Vec<(Loan, Book)> = loans::table
+        .inner_join(books::table)
+        .filter(loans::user_id.eq(user_id))
+        .filter(loans::returned_at.is_null())
+        .order((loans::lent_at, loans::id))
+        .load(conn)?;
+    Ok(rows.into_iter().map(|(loan, book)| LentBook { loan, book }).collect())
+}
+
+// Lent books which should have been returned before the given date
+pub fn overdue(pool: DbPool, user_id: i32, now: NaiveDateTime) -> Result<Vec<LentBook>, DBError> {
+    let lent = lent(pool, user_id)?;
+    Ok(lent.into_iter().filter(|lent| lent.loan.due_at.map(|due| due < now).unwrap_or(false)).collect())
+}
+
@@ -0,0 +1,71 @@
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::khnum::errors::ServiceError;
+use crate::khnum::wiring::DbPool;
+
+use crate::{isbn, language};
+use crate::metadata::BookMetadata;
+use crate::repository::{author_handler, book_handler, contributor_handler};
+use crate::models::{Author, Book, BookChanges, NewBook, Role};
+
+// Language code found by the provider : the codes which are not a language ("mul", "und") are left out
+fn metadata_language(raw: &str) -> String {
+    language::lookup(raw).map(|language| language.code.to_string()).unwrap_or_default()
+}
+
+// Adds a book to the library from the metadata found for its ISBN, with its contributors, all or nothing.
+// store_cover is given the id of the new book and returns the key of its stored cover, if any.
+// A book with the same ISBN already in the library is not added again.
+pub fn create_book<F>(pool: DbPool, user_id: i32, metadata: &BookMetadata, store_cover: F) -> Result<Book, ServiceError>
+    where F: FnOnce(i32) -> Option<String>
+{
+    let language_main = metadata_language(&metadata.language_main);
+    let language_original = metadata_language(&metadata.language_original);
+    let isbn = isbn::normalize(&metadata.isbn);
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, ServiceError, _>(|| {
+        if !isbn.is_empty() && book_handler::find_isbn_book(conn, user_id, &isbn)?.is_some() {
+            return Err(ServiceError::BadRequest("isbn: the book is already in the library".to_string()));
+        }
+        let mut authors = Vec::new();
+        for name in metadata.authors.iter().filter(|name| !name.trim().is_empty()) {
+            let author = author_handler::find_or_create(conn, name)?;
+            if !authors.iter().any(|known: &Author| known.id == author.id) {
+                authors.push(author);
+            }
+        }
+        let primary = authors.first();
+        let book = NewBook {
+            user_id,
+            librarything_id: None,
+            title: metadata.title.clone(),
+            author_lf: primary.map(|author| author.name.clone()).unwrap_or_default(),
+            author_code: primary.map(|author| author.code.clone()).unwrap_or_default(),
+            isbn: isbn.clone(),
+            publicationdate: metadata.publicationdate.clone(),
+            rating: None,
+            language_main,
+            language_secondary: None,
+            language_original,
+            review: None,
+            cover: "".to_string(),
+            created_at: Utc::now().naive_utc(),
+            dateacquired_stamp: None,
+            started_stamp: None,
+            finished_stamp: None,
+            author_id: primary.map(|author| author.id),
+            page_count: metadata.page_count,
+        };
+        let book = book_handler::insert(conn, &book)?;
+        let contributors: Vec<(i32, Role)> = authors.iter().map(|author| (author.id, Role::Author)).collect();
+        contributor_handler::replace_for_book(conn, book.id, &contributors)?;
+        match store_cover(book.id) {
+            Some(key) => {
+                let changes = BookChanges { cover: Some(key), ..BookChanges::default() };
+                Ok(book_handler::apply_changes(conn, user_id, book.id, &changes)?)
+            }
+            None => Ok(book),
+        }
+    })
+}
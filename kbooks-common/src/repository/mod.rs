@@ -1 +1,15 @@
+pub mod author_handler;
 pub mod book_handler;
+pub mod borrow_handler;
+pub mod contributor_handler;
+pub mod data_migration_handler;
+pub mod friend_handler;
+pub mod goal_handler;
+pub mod import_handler;
+pub mod loan_handler;
+pub mod metadata_handler;
+pub mod reading_handler;
+pub mod shelf_handler;
+pub mod stats_handler;
+pub mod tag_handler;
+pub mod wish_handler;
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::repository::book_handler;
+use crate::schema::{books, reading_progress, readings};
+use crate::models::{NewProgress, NewReading, Progress, Reading, ReadingDetails, ReadingStatus};
+
+// The status of a book is the one of its last reading, the reading in progress if any
+pub fn status(last: Option<&Reading>) -> ReadingStatus {
+    match last {
+        None => ReadingStatus::ToRead,
+        Some(reading) if reading.abandoned => ReadingStatus::Abandoned,
+        Some(reading) if reading.finished_at.is_some() => ReadingStatus::Read,
+        Some(_) => ReadingStatus::Reading,
+    }
+}
+
+// Readings may be recorded afterwards : they are ordered by their dates, not by their creation,
+// the reading in progress coming last whatever its start
+fn chronological(mut readings: Vec<Reading>) -> Vec<Reading> {
+    readings.sort_by_key(|reading| (
+        reading.finished_at.is_none() && !reading.abandoned,
+        reading.started_at.or(reading.finished_at).unwrap_or(reading.created_at),
+        reading.id,
+    ));
+    readings
+}
+
+fn load(conn: &MyConnection, book_id: i32) -> Result<Vec<Reading>, DBError> {
+    let readings = readings::table
+        .filter(readings::book_id.eq(book_id))
+        .load::<Reading>(conn)?;
+    Ok(chronological(readings))
+}
+
+// The reading in progress, one at most (readings_in_progress_idx)
+fn open_reading(conn: &MyConnection, book_id: i32) -> Result<Option<Reading>, DBError> {
+    readings::table
+        .filter(readings::book_id.eq(book_id))
+        .filter(readings::finished_at.is_null())
+        .filter(readings::abandoned.eq(false))
+        .first::<Reading>(conn)
+        .optional()
+}
+
+// Copies the dates on the book, which is filtered and exported with them : the start of the reading in progress
+// or else of the last reading, and the last finish, kept while the book is read again
+fn sync_book(conn: &MyConnection, book_id: i32) -> Result<ReadingStatus, DBError> {
+    let open = open_reading(conn, book_id)?;
+    let readings = load(conn, book_id)?;
+    let last = open.as_ref().or_else(|| readings.last());
+    let status = status(last);
+    let started = last.and_then(|reading| reading.started_at);
+    let finished = readings.iter()
+        .filter(|reading| !reading.abandoned)
+        .filter_map(|reading| reading.finished_at)
+        .max();
+    diesel::update(books::table.filter(books::id.eq(book_id)))
+        .set((
+            books::started_stamp.eq(started),
+            books::finished_stamp.eq(finished),
+            books::reading_status.eq(status.as_str()),
+        ))
+        .execute(conn)?;
+    Ok(status)
+}
+
+fn insert(conn: &MyConnection, reading: &NewReading) -> Result<Reading, DBError> {
+    #[cfg(not(feature = "test"))]
+    let inserted_reading: Reading = diesel::insert_into(readings::table).values(reading).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(readings::table).values(reading).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_reading: Reading = readings::table.order(readings::id.desc()).first(conn)?;
+
+    Ok(inserted_reading)
+}
+
+fn new_reading(user_id: i32, book_id: i32, started_at: Option<NaiveDateTime>, finished_at: Option<NaiveDateTime>) -> NewReading {
+    NewReading {
+        book_id,
+        user_id,
+        started_at,
+        finished_at,
+        abandoned: false,
+        notes: None,
+        created_at: Utc::now().naive_utc(),
+    }
+}
+
+// Readings of a book, oldest first, the reading in progress last
+pub fn list_for_book(pool: DbPool, user_id: i32, book_id: i32) -> Result<Vec<Reading>, DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    load(conn, book_id)
+}
+
+// The reading in progress, if any
+pub fn current(pool: DbPool, user_id: i32, book_id: i32) -> Result<Option<Reading>, DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    open_reading(conn, book_id)
+}
+
+// A second reading in progress is refused by the readings_in_progress_idx index : UniqueViolation
+pub fn start(pool: DbPool, user_id: i32, book_id: i32, started_at: NaiveDateTime, notes: Option<String>) -> Result<Reading, DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let reading = insert(conn, &NewReading { notes, ..new_reading(user_id, book_id, Some(started_at), None) })?;
+        sync_book(conn, book_id)?;
+        Ok(reading)
+    })
+}
+
+// Ends the reading in progress, or records a reading whose start is unknown
+pub fn finish(pool: DbPool, user_id: i32, book_id: i32, finished_at: NaiveDateTime, notes: Option<String>) -> Result<Reading, DBError> {
+    let in_progress = current(pool.clone(), user_id, book_id)?;
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let reading = match in_progress {
+            Some(reading) => close(conn, reading, finished_at, false, notes)?,
+            None => insert(conn, &NewReading { notes, ..new_reading(user_id, book_id, None, Some(finished_at)) })?,
+        };
+        sync_book(conn, book_id)?;
+        Ok(reading)
+    })
+}
+
+// Only a reading in progress can be abandoned : DBError::NotFound otherwise
+pub fn abandon(pool: DbPool, user_id: i32, book_id: i32, abandoned_at: NaiveDateTime, notes: Option<String>) -> Result<Reading, DBError> {
+    let in_progress = current(pool.clone(), user_id, book_id)?.ok_or(DBError::NotFound)?;
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let reading = close(conn, in_progress, abandoned_at, true, notes)?;
+        sync_book(conn, book_id)?;
+        Ok(reading)
+    })
+}
+
+// The notes given when closing a reading are added to the ones written when it started
+fn close(conn: &MyConnection, reading: Reading, finished_at: NaiveDateTime, abandoned: bool, notes: Option<String>) -> Result<Reading, DBError> {
+    let notes = match (reading.notes, notes) {
+        (Some(previous), Some(notes)) => Some(format!("{}\n{}", previous, notes)),
+        (previous, notes) => notes.or(previous),
+    };
+    let target = readings::table.filter(readings::id.eq(reading.id));
+    diesel::update(target)
+        .set((
+            readings::finished_at.eq(finished_at),
+            readings::abandoned.eq(abandoned),
+            readings::notes.eq(notes),
+        ))
+        .execute(conn)?;
+    readings::table.filter(readings::id.eq(reading.id)).first(conn)
+}
+
+pub fn delete(pool: DbPool, user_id: i32, book_id: i32, reading_id: i32) -> Result<(), DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        // foreign keys are not enforced by sqlite
+        diesel::delete(reading_progress::table.filter(reading_progress::reading_id.eq(reading_id)))
+            .execute(conn)?;
+        let count = diesel::delete(
+            readings::table
+            .filter(readings::id.eq(reading_id))
+            .filter(readings::book_id.eq(book_id))
+        ).execute(conn)?;
+        if count == 0 {
+            return Err(DBError::NotFound);
+        }
+        sync_book(conn, book_id)?;
+        Ok(())
+    })
+}
+
+// Imports only know the dates of one reading : it is added unless already recorded,
+// a reading with the same start being completed with the finish date.
+pub fn record_imported(pool: DbPool, user_id: i32, book_id: i32, started_at: Option<NaiveDateTime>, finished_at: Option<NaiveDateTime>) -> Result<(), DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    record(conn, user_id, book_id, started_at, finished_at)
+}
+
+// The book is expected to belong to the user
+pub(crate) fn record(conn: &MyConnection, user_id: i32, book_id: i32, started_at: Option<NaiveDateTime>, finished_at: Option<NaiveDateTime>) -> Result<(), DBError> {
+    if started_at.is_none() && finished_at.is_none() {
+        return Ok(());
+    }
+    conn.transaction::<_, DBError, _>(|| {
+        let readings = load(conn, book_id)?;
+        let already_recorded = readings.iter().any(|reading| reading.started_at == started_at && reading.finished_at == finished_at);
+        let same_start = readings.iter().find(|reading| started_at.is_some() && reading.started_at == started_at);
+        // a book has one reading in progress at most
+        let in_progress = readings.iter().find(|reading| finished_at.is_none() && reading.finished_at.is_none());
+        if !already_recorded {
+            match same_start.or(in_progress) {
+                Some(reading) => {
+                    diesel::update(readings::table.filter(readings::id.eq(reading.id)))
+                        .set((readings::started_at.eq(started_at), readings::finished_at.eq(finished_at)))
+                        .execute(conn)?;
+                }
+                None => {
+                    insert(conn, &new_reading(user_id, book_id, started_at, finished_at))?;
+                }
+            }
+        }
+        // the book update of the import may have overwritten the dates of the last reading
+        sync_book(conn, book_id)?;
+        Ok(())
+    })
+}
+
+// ---------------- Progress -------------
+
+// Percentage read at a page, rounded down
+pub fn percent(page: i32, page_count: i32) -> i32 {
+    if page_count <= 0 {
+        return 0;
+    }
+    (page.max(0) as i64 * 100 / page_count as i64).min(100) as i32
+}
+
+// Records the progress of the reading in progress : DBError::NotFound when there is none
+pub fn add_progress(pool: DbPool, user_id: i32, book_id: i32, page: Option<i32>, percent: i32, recorded_at: NaiveDateTime) -> Result<Progress, DBError> {
+    let in_progress = current(pool.clone(), user_id, book_id)?.ok_or(DBError::NotFound)?;
+    let conn = &pool.get().unwrap();
+    let progress = NewProgress { reading_id: in_progress.id, page, percent, recorded_at };
+    #[cfg(not(feature = "test"))]
+    let inserted_progress: Progress = diesel::insert_into(reading_progress::table).values(&progress).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(reading_progress::table).values(&progress).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_progress: Progress = reading_progress::table.order(reading_progress::id.desc()).first(conn)?;
+
+    Ok(inserted_progress)
+}
+
+// Adds the progress entries of each reading
+pub fn with_progress(pool: DbPool, readings: Vec<Reading>) -> Result<Vec<ReadingDetails>, DBError> {
+    let conn = &pool.get().unwrap();
+    let reading_ids: Vec<i32> = readings.iter().map(|reading| reading.id).collect();
+    let entries: Vec<Progress> = reading_progress::table
+        .filter(reading_progress::reading_id.eq_any(&reading_ids))
+        .order((reading_progress::recorded_at, reading_progress::id))
+        .load(conn)?;
+
+    let mut by_reading: HashMap<i32, Vec<Progress>> = HashMap::new();
+    for progress in entries {
+        by_reading.entry(progress.reading_id).or_insert_with(Vec::new).push(progress);
+    }
+    Ok(readings.into_iter().map(|reading| ReadingDetails {
+        progress: by_reading.remove(&reading.id).unwrap_or_else(Vec::new),
+        reading,
+    }).collect())
+}
+
+// Last percentage recorded for the books being read
+pub fn current_progress(pool: DbPool, book_ids: &[i32]) -> Result<HashMap<i32, i32>, DBError> {
+    let conn = &pool.get().unwrap();
+    let rows: Vec<(i32, i32)> = reading_progress::table
+        .inner_join(readings::table.inner_join(books::table))
+        .filter(books::id.eq_any(book_ids))
+        .filter(books::reading_status.eq(ReadingStatus::Reading.as_str()))
+        // the reading in progress, see readings_in_progress_idx
+        .filter(readings::finished_at.is_null())
+        .order((reading_progress::recorded_at, reading_progress::id))
+        .select((books::id, reading_progress::percent))
+        .load(conn)?;
+    // the last row of each book wins
+    Ok(rows.into_iter().collect())
+}
@@ -0,0 +1,191 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::book_filter::Expr;
+use crate::repository::book_handler::{self, BookFilters};
+use crate::schema::{books, shelf_books, shelves};
+use crate::models::{Book, NewShelf, Shelf, ShelfBook, ShelfCount};
+
+// Returns DBError::NotFound when the shelf does not belong to the user
+fn owned(conn: &MyConnection, user_id: i32, shelf_id: i32) -> Result<Shelf, DBError> {
+    shelves::table
+        .filter(shelves::id.eq(shelf_id))
+        .filter(shelves::user_id.eq(user_id))
+        .first::<Shelf>(conn)
+}
+
+// A smart shelf is created with a query, its books being the ones matching it
+pub fn create(pool: DbPool, user_id: i32, name: &str, description: &str, query: Option<&Expr>) -> Result<Shelf, DBError> {
+    let conn = &pool.get().unwrap();
+    let shelf = NewShelf {
+        user_id,
+        name: name.to_string(),
+        description: description.to_string(),
+        created_at: Utc::now().naive_utc(),
+        query: query.map(Expr::to_string),
+    };
+    #[cfg(not(feature = "test"))]
+    let inserted_shelf: Shelf = diesel::insert_into(shelves::table).values(&shelf).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(shelves::table).values(&shelf).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_shelf: Shelf = shelves::table.order(shelves::id.desc()).first(conn)?;
+
+    Ok(inserted_shelf)
+}
+
+pub fn get(pool: DbPool, user_id: i32, shelf_id: i32) -> Result<Shelf, DBError> {
+    let conn = &pool.get().unwrap();
+    owned(conn, user_id, shelf_id)
+}
+
+pub fn find_by_name(pool: DbPool, user_id: i32, name: &str) -> Result<Option<Shelf>, DBError> {
+    let conn = &pool.get().unwrap();
+    shelves::table
+        .filter(shelves::user_id.eq(user_id))
+        .filter(shelves::name.eq(name))
+        .first::<Shelf>(conn)
+        .optional()
+}
+
+// The user shelves by name, with their number of books
+pub fn list(pool: DbPool, user_id: i32) -> Result<Vec<ShelfCount>, DBError> {
+    let rows: Vec<(Shelf, Option<i32>)> = shelves::table
+        .left_join(shelf_books::table)
+        .filter(shelves::user_id.eq(user_id))
+        .select((shelves::all_columns, shelf_books::book_id.nullable()))
+        .order((shelves::name, shelves::id))
+        .load(&pool.get().unwrap())?;
+
+    let mut counts: Vec<ShelfCount> = Vec::new();
+    for (shelf, book_id) in rows {
+        let is_new = counts.last().map(|last| last.shelf.id != shelf.id).unwrap_or(true);
+        if is_new {
+            counts.push(ShelfCount { shelf, count: 0 });
+        }
+        if book_id.is_some() {
+            counts.last_mut().unwrap().count += 1;
+        }
+    }
+    // smart shelves are evaluated
+    for count in counts.iter_mut().filter(|count| count.shelf.query.is_some()) {
+        count.count = book_handler::count_matching(pool.clone(), user_id, &shelf_filters(count.shelf.id))?;
+    }
+    Ok(counts)
+}
+
+// The books placed on a shelf are kept when it becomes a smart shelf, and found again without its query
+pub fn update(pool: DbPool, user_id: i32, shelf_id: i32, name: &str, description: &str, query: Option<&Expr>) -> Result<Shelf, DBError> {
+    let conn = &pool.get().unwrap();
+    owned(conn, user_id, shelf_id)?;
+    diesel::update(shelves::table.filter(shelves::id.eq(shelf_id)))
+        .set((
+            shelves::name.eq(name),
+            shelves::description.eq(description),
+            shelves::query.eq(query.map(Expr::to_string)),
+        ))
+        .execute(conn)?;
+    owned(conn, user_id, shelf_id)
+}
+
+// The books are kept, only their place on the shelf is removed
+pub fn delete(pool: DbPool, user_id: i32, shelf_id: i32) -> Result<(), DBError> {
+    let conn = &pool.get().unwrap();
+    owned(conn, user_id, shelf_id)?;
+    conn.transaction::<_, DBError, _>(|| {
+        // foreign keys are not enforced by sqlite
+        diesel::delete(shelf_books::table.filter(shelf_books::shelf_id.eq(shelf_id))).execute(conn)?;
+        diesel::delete(shelves::table.filter(shelves::id.eq(shelf_id))).execute(conn)?;
+        Ok(())
+    })
+}
+
+// ---------------- Books of a shelf -------------
+
+fn book_ids(conn: &MyConnection, shelf_id: i32) -> Result<Vec<i32>, DBError> {
+    shelf_books::table
+        .filter(shelf_books::shelf_id.eq(shelf_id))
+        .order(shelf_books::position)
+        .select(shelf_books::book_id)
+        .load(conn)
+}
+
+// Rewrites the positions of all the books of the shelf
+fn arrange(conn: &MyConnection, shelf_id: i32, book_ids: &[i32]) -> Result<(), DBError> {
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(shelf_books::table.filter(shelf_books::shelf_id.eq(shelf_id))).execute(conn)?;
+        for (position, book_id) in book_ids.iter().enumerate() {
+            let shelf_book = ShelfBook { shelf_id, book_id: *book_id, position: position as i32 };
+            diesel::insert_into(shelf_books::table).values(&shelf_book).execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+fn shelf_filters(shelf_id: i32) -> BookFilters {
+    BookFilters { shelf: Some(shelf_id), ..BookFilters::default() }
+}
+
+// Books of the shelf in their manual order, or the books matching a smart shelf by title
+pub fn books(pool: DbPool, user_id: i32, shelf_id: i32) -> Result<Vec<Book>, DBError> {
+    let shelf = get(pool.clone(), user_id, shelf_id)?;
+    if shelf.query.is_some() {
+        return book_handler::list_matching(pool, user_id, &shelf_filters(shelf_id));
+    }
+    let conn = &pool.get().unwrap();
+    shelf_books::table
+        .inner_join(books::table)
+        .filter(shelf_books::shelf_id.eq(shelf_id))
+        .order(shelf_books::position)
+        .select(books::all_columns)
+        .load::<Book>(conn)
+}
+
+// Puts the book at the given position, at the end when not given.
+// A book already on the shelf is moved.
+pub fn add_book(pool: DbPool, user_id: i32, shelf_id: i32, book_id: i32, position: Option<usize>) -> Result<(), DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    owned(conn, user_id, shelf_id)?;
+    let mut ids = book_ids(conn, shelf_id)?;
+    let current = ids.iter().position(|id| *id == book_id);
+    match (current, position) {
+        (Some(_), None) => return Ok(()),
+        (Some(current), Some(_)) => { ids.remove(current); }
+        (None, _) => (),
+    }
+    let position = position.unwrap_or(ids.len()).min(ids.len());
+    ids.insert(position, book_id);
+    arrange(conn, shelf_id, &ids)
+}
+
+// Returns DBError::NotFound when the book is not on the shelf
+pub fn remove_book(pool: DbPool, user_id: i32, shelf_id: i32, book_id: i32) -> Result<(), DBError> {
+    let conn = &pool.get().unwrap();
+    owned(conn, user_id, shelf_id)?;
+    let mut ids = book_ids(conn, shelf_id)?;
+    let current = ids.iter().position(|id| *id == book_id).ok_or(DBError::NotFound)?;
+    ids.remove(current);
+    arrange(conn, shelf_id, &ids)
+}
+
+// The given books come first in this order, the other books of the shelf follow in their current order.
+// Books which are not on the shelf are ignored.
+pub fn reorder(pool: DbPool, user_id: i32, shelf_id: i32, ordered: &[i32]) -> Result<Vec<i32>, DBError> {
+    let conn = &pool.get().unwrap();
+    owned(conn, user_id, shelf_id)?;
+    let current = book_ids(conn, shelf_id)?;
+    let mut ids: Vec<i32> = Vec::new();
+    for book_id in ordered.iter().chain(current.iter()) {
+        if current.contains(book_id) && !ids.contains(book_id) {
+            ids.push(*book_id);
+        }
+    }
+    arrange(conn, shelf_id, &ids)?;
+    Ok(ids)
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use diesel::query_dsl::GroupByDsl;
+use diesel::result::Error as DBError;
+use diesel::sql_types::{Nullable, Timestamp};
+
+use crate::khnum::wiring::{DbPool, MyBackend, MyConnection};
+
+use crate::schema::{books, readings};
+use crate::stats::{self, FinishedReading, Stats};
+
+type Row = (i32, Option<NaiveDateTime>, Option<i32>, Option<i32>, String, String, String, String, Option<NaiveDateTime>);
+
+// First finish of each book read by the user, over all the years
+fn first_finishes(conn: &MyConnection, user_id: i32) -> Result<HashMap<i32, NaiveDateTime>, DBError> {
+    // diesel 1.x refuses to mix min with the grouped column
+    let rows: Vec<(i32, Option<NaiveDateTime>)> = readings::table
+        .filter(readings::user_id.eq(user_id))
+        .filter(readings::finished_at.is_not_null())
+        .filter(readings::abandoned.eq(false))
+        .group_by(readings::book_id)
+        .select((readings::book_id, diesel::dsl::sql::<Nullable<Timestamp>>("MIN(readings.finished_at)")))
+        .load(conn)?;
+    Ok(rows.into_iter().filter_map(|(book_id, first)| first.map(|first| (book_id, first))).collect())
+}
+
+// Readings finished by the user during the given year, or during all of them, abandoned ones excluded
+pub fn finished_readings(pool: DbPool, user_id: i32, year: Option<i32>) -> Result<Vec<FinishedReading>, DBError> {
+    let conn = &pool.get().unwrap();
+    let mut query = readings::table
+        .inner_join(books::table)
+        .filter(readings::user_id.eq(user_id))
+        .filter(readings::finished_at.is_not_null())
+        .filter(readings::abandoned.eq(false))
+        .select((
+            books::id,
+            readings::finished_at,
+            books::page_count,
+            books::rating,
+            books::language_main,
+            books::language_original,
+            books::author_code,
+            books::author_lf,
+            books::dateacquired_stamp,
+        ))
+        .into_boxed::<MyBackend>();
+    if let Some(year) = year {
+        let start = NaiveDate::from_ymd_opt(year, 1, 1);
+        let end = start.and_then(|_| NaiveDate::from_ymd_opt(year + 1, 1, 1));
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) => (start, end),
+            // no reading can be dated out of the chrono range
+            _ => return Ok(Vec::new()),
+        };
+        query = query
+            .filter(readings::finished_at.ge(start.and_hms(0, 0, 0)))
+            .filter(readings::finished_at.lt(end.and_hms(0, 0, 0)));
+    }
+    let rows: Vec<Row> = query.load(conn)?;
+    let first_finishes = first_finishes(conn, user_id)?;
+
+    Ok(rows.into_iter().filter_map(|row| {
+        let (book_id, finished_at, page_count, rating, language_main, language_original, author_code, author_lf, dateacquired_stamp) = row;
+        finished_at.map(|finished_at| FinishedReading {
+            book_id, finished_at, page_count, rating, language_main, language_original, author_code, author_lf, dateacquired_stamp,
+            first: first_finishes.get(&book_id) == Some(&finished_at),
+        })
+    }).collect())
+}
+
+// Statistics of the user readings, for one year or for all of them
+pub fn stats(pool: DbPool, user_id: i32, year: Option<i32>) -> Result<Stats, DBError> {
+    let finished = finished_readings(pool, user_id, year)?;
+    Ok(stats::compute(finished, year))
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+
+use crate::khnum::wiring::{DbPool, MyConnection};
+
+use crate::repository::book_handler;
+use crate::schema::{book_tags, tags};
+use crate::models::{BookTag, NewTag, Tag, TagCount};
+
+// Tags are trimmed and lowercased so that "Poetry " and "poetry" are the same tag
+pub fn normalize(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+// Splits a comma separated list of tags, as found in LibraryThing exports
+pub fn split(names: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for name in names.split(',').map(normalize).filter(|name| !name.is_empty()) {
+        if !tags.contains(&name) {
+            tags.push(name);
+        }
+    }
+    tags
+}
+
+// A concurrent creation of the same tag makes the insert fail
+const MAX_CREATE_ATTEMPTS: usize = 3;
+
+fn get_or_create(conn: &MyConnection, user_id: i32, name: &str) -> Result<Tag, DBError> {
+    let mut attempt = 1;
+    loop {
+        // a savepoint in the transaction of the caller, so that the failed insert can be tried again
+        match conn.transaction(|| find_or_insert(conn, user_id, name)) {
+            Err(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) if attempt < MAX_CREATE_ATTEMPTS => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+fn find_or_insert(conn: &MyConnection, user_id: i32, name: &str) -> Result<Tag, DBError> {
+    let existing = tags::table
+        .filter(tags::user_id.eq(user_id))
+        .filter(tags::name.eq(name))
+        .first::<Tag>(conn)
+        .optional()?;
+    if let Some(tag) = existing {
+        return Ok(tag);
+    }
+
+    let tag = NewTag { user_id, name: name.to_string(), created_at: Utc::now().naive_utc() };
+    #[cfg(not(feature = "test"))]
+    let inserted_tag: Tag = diesel::insert_into(tags::table).values(&tag).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(tags::table).values(&tag).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_tag: Tag = tags::table.order(tags::id.desc()).first(conn)?;
+
+    Ok(inserted_tag)
+}
+
+fn link(conn: &MyConnection, book_id: i32, tag_id: i32) -> Result<(), DBError> {
+    let already_linked: bool = diesel::dsl::select(diesel::dsl::exists(
+            book_tags::table
+            .filter(book_tags::book_id.eq(book_id))
+            .filter(book_tags::tag_id.eq(tag_id))
+    )).get_result(conn)?;
+    if !already_linked {
+        diesel::insert_into(book_tags::table).values(&BookTag { book_id, tag_id }).execute(conn)?;
+    }
+    Ok(())
+}
+
+pub fn add_to_book(pool: DbPool, user_id: i32, book_id: i32, name: &str) -> Result<Tag, DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let tag = get_or_create(conn, user_id, &normalize(name))?;
+        link(conn, book_id, tag.id)?;
+        Ok(tag)
+    })
+}
+
+pub fn remove_from_book(pool: DbPool, user_id: i32, book_id: i32, name: &str) -> Result<(), DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    let tag_ids = tags::table
+        .select(tags::id)
+        .filter(tags::user_id.eq(user_id))
+        .filter(tags::name.eq(normalize(name)));
+    diesel::delete(
+        book_tags::table
+        .filter(book_tags::book_id.eq(book_id))
+        .filter(book_tags::tag_id.eq_any(tag_ids))
+    ).execute(conn)?;
+    Ok(())
+}
+
+// Replaces all the tags of a book
+pub fn set_for_book(pool: DbPool, user_id: i32, book_id: i32, names: &[String]) -> Result<(), DBError> {
+    if !book_handler::is_owner(pool.clone(), user_id, book_id)? {
+        return Err(DBError::NotFound);
+    }
+    let conn = &pool.get().unwrap();
+    replace_for_book(conn, user_id, book_id, names)
+}
+
+// The book is expected to belong to the user
+pub(crate) fn replace_for_book(conn: &MyConnection, user_id: i32, book_id: i32, names: &[String]) -> Result<(), DBError> {
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(book_tags::table.filter(book_tags::book_id.eq(book_id))).execute(conn)?;
+        for name in names.iter().map(|name| normalize(name)).filter(|name| !name.is_empty()) {
+            let tag = get_or_create(conn, user_id, &name)?;
+            link(conn, book_id, tag.id)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn list_for_books(pool: DbPool, book_ids: &[i32]) -> Result<HashMap<i32, Vec<String>>, DBError> {
+    let conn = &pool.get().unwrap();
+    load_for_books(conn, book_ids)
+}
+
+pub(crate) fn load_for_books(conn: &MyConnection, book_ids: &[i32]) -> Result<HashMap<i32, Vec<String>>, DBError> {
+    let rows: Vec<(i32, String)> = book_tags::table
+        .inner_join(tags::table)
+        .filter(book_tags::book_id.eq_any(book_ids))
+        .order((book_tags::book_id, tags::name))
+        .select((book_tags::book_id, tags::name))
+        .load(conn)?;
+
+    let mut by_book: HashMap<i32, Vec<String>> = HashMap::new();
+    for (book_id, name) in rows {
+        by_book.entry(book_id).or_insert_with(Vec::new).push(name);
+    }
+    Ok(by_book)
+}
+
+// The user tags vocabulary with the number of books for each tag
+pub fn cloud(pool: DbPool, user_id: i32) -> Result<Vec<TagCount>, DBError> {
+    let conn = &pool.get().unwrap();
+    let rows: Vec<(String, Option<i32>)> = tags::table
+        .left_join(book_tags::table)
+        .filter(tags::user_id.eq(user_id))
+        .select((tags::name, book_tags::book_id.nullable()))
+        .order(tags::name)
+        .load(conn)?;
+
+    let mut cloud: Vec<TagCount> = Vec::new();
+    for (name, book_id) in rows {
+        let is_new = cloud.last().map(|last| last.name != name).unwrap_or(true);
+        if is_new {
+            cloud.push(TagCount { name, count: 0 });
+        }
+        if book_id.is_some() {
+            cloud.last_mut().unwrap().count += 1;
+        }
+    }
+    Ok(cloud)
+}
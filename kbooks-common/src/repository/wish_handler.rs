@@ -0,0 +1,117 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+
+use crate::khnum::wiring::DbPool;
+
+use crate::isbn;
+use crate::repository::{author_handler, book_handler, contributor_handler};
+use crate::schema::wishes;
+use crate::models::{Book, NewBook, NewWish, Role, Wish};
+
+pub fn add(pool: DbPool, wish: NewWish) -> Result<Wish, DBError> {
+    let conn = &pool.get().unwrap();
+    #[cfg(not(feature = "test"))]
+    let inserted_wish: Wish = diesel::insert_into(wishes::table).values(&wish).get_result(conn)?;
+    #[cfg(feature = "test")]
+    diesel::insert_into(wishes::table).values(&wish).execute(conn)?;
+    #[cfg(feature = "test")]
+    let inserted_wish: Wish = wishes::table.order(wishes::id.desc()).first(conn)?;
+
+    Ok(inserted_wish)
+}
+
+// The wishlist of a user, most wanted first
+pub fn list(pool: DbPool, user_id: i32) -> Result<Vec<Wish>, DBError> {
+    let conn = &pool.get().unwrap();
+    wishes::table
+        .filter(wishes::user_id.eq(user_id))
+        .order((wishes::priority, wishes::created_at, wishes::id))
+        .load::<Wish>(conn)
+}
+
+// Returns DBError::NotFound when the wish does not belong to the user
+pub fn get(pool: DbPool, user_id: i32, wish_id: i32) -> Result<Wish, DBError> {
+    let conn = &pool.get().unwrap();
+    wishes::table
+        .filter(wishes::id.eq(wish_id))
+        .filter(wishes::user_id.eq(user_id))
+        .first::<Wish>(conn)
+}
+
+// Replaces the wish data, its owner and creation date excepted
+pub fn update(pool: DbPool, user_id: i32, wish_id: i32, wish: &NewWish) -> Result<Wish, DBError> {
+    let count = {
+        let conn = &pool.get().unwrap();
+        diesel::update(wishes::table.filter(wishes::id.eq(wish_id)).filter(wishes::user_id.eq(user_id)))
+            .set((
+                wishes::title.eq(&wish.title),
+                wishes::author.eq(&wish.author),
+                wishes::isbn.eq(&wish.isbn),
+                wishes::priority.eq(wish.priority),
+                wishes::notes.eq(&wish.notes),
+                wishes::target_price.eq(wish.target_price),
+                wishes::where_to_buy.eq(&wish.where_to_buy),
+            ))
+            .execute(conn)?
+    };
+    if count == 0 {
+        return Err(DBError::NotFound);
+    }
+    get(pool, user_id, wish_id)
+}
+
+pub fn delete(pool: DbPool, user_id: i32, wish_id: i32) -> Result<(), DBError> {
+    let conn = &pool.get().unwrap();
+    let count = diesel::delete(
+        wishes::table
+        .filter(wishes::id.eq(wish_id))
+        .filter(wishes::user_id.eq(user_id))
+    ).execute(conn)?;
+    if count == 0 {
+        return Err(DBError::NotFound);
+    }
+    Ok(())
+}
+
+// Moves the wish to the library : the book is created, acquired at the given date, and the wish removed, all or nothing.
+// A wish without author gives a book without author.
+pub fn acquire(pool: DbPool, user_id: i32, wish_id: i32, acquired_at: NaiveDateTime) -> Result<Book, DBError> {
+    let conn = &pool.get().unwrap();
+    conn.transaction::<_, DBError, _>(|| {
+        let wish = wishes::table
+            .filter(wishes::id.eq(wish_id))
+            .filter(wishes::user_id.eq(user_id))
+            .first::<Wish>(conn)?;
+        let author = match wish.author.trim() {
+            "" => None,
+            name => Some(author_handler::find_or_create(conn, name)?),
+        };
+        let book = NewBook {
+            user_id,
+            librarything_id: None,
+            title: wish.title,
+            author_lf: author.as_ref().map(|author| author.name.clone()).unwrap_or_default(),
+            author_code: author.as_ref().map(|author| author.code.clone()).unwrap_or_default(),
+            isbn: isbn::normalize(&wish.isbn),
+            publicationdate: String::new(),
+            rating: None,
+            language_main: String::new(),
+            language_secondary: None,
+            language_original: String::new(),
+            review: None,
+            cover: "".to_string(),
+            created_at: Utc::now().naive_utc(),
+            dateacquired_stamp: Some(acquired_at),
+            started_stamp: None,
+            finished_stamp: None,
+            author_id: author.as_ref().map(|author| author.id),
+            page_count: None,
+        };
+        let book = book_handler::insert(conn, &book)?;
+        let contributors: Vec<(i32, Role)> = author.iter().map(|author| (author.id, Role::Author)).collect();
+        contributor_handler::replace_for_book(conn, book.id, &contributors)?;
+        diesel::delete(wishes::table.filter(wishes::id.eq(wish.id))).execute(conn)?;
+        Ok(book)
+    })
+}
@@ -1,3 +1,28 @@
+table! {
+    authors (id) {
+        id -> Int4,
+        name -> Text,
+        code -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    book_contributors (book_id, author_id, role) {
+        book_id -> Int4,
+        author_id -> Int4,
+        role -> Text,
+        position -> Int4,
+    }
+}
+
+table! {
+    book_tags (book_id, tag_id) {
+        book_id -> Int4,
+        tag_id -> Int4,
+    }
+}
+
 table! {
     books (id) {
         id -> Int4,
@@ -18,6 +43,117 @@ table! {
         dateacquired_stamp -> Nullable<Timestamp>,
         started_stamp -> Nullable<Timestamp>,
         finished_stamp -> Nullable<Timestamp>,
+        author_id -> Nullable<Int4>,
+        publication_sort -> Nullable<Int4>,
+        reading_status -> Text,
+        page_count -> Nullable<Int4>,
+    }
+}
+
+table! {
+    borrow_requests (id) {
+        id -> Int4,
+        book_id -> Int4,
+        owner_id -> Int4,
+        requester_id -> Int4,
+        state -> Text,
+        message -> Nullable<Text>,
+        loan_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    data_migrations (name) {
+        name -> Text,
+        applied_at -> Timestamp,
+    }
+}
+
+table! {
+    friends (user_id, friend_id) {
+        user_id -> Int4,
+        friend_id -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    goals (id) {
+        id -> Int4,
+        user_id -> Int4,
+        year -> Int4,
+        kind -> Text,
+        target -> Int4,
+        language -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    loans (id) {
+        id -> Int4,
+        book_id -> Int4,
+        user_id -> Int4,
+        borrower_name -> Text,
+        borrower_id -> Nullable<Int4>,
+        lent_at -> Timestamp,
+        due_at -> Nullable<Timestamp>,
+        returned_at -> Nullable<Timestamp>,
+        notes -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    reading_progress (id) {
+        id -> Int4,
+        reading_id -> Int4,
+        page -> Nullable<Int4>,
+        percent -> Int4,
+        recorded_at -> Timestamp,
+    }
+}
+
+table! {
+    readings (id) {
+        id -> Int4,
+        book_id -> Int4,
+        user_id -> Int4,
+        started_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+        abandoned -> Bool,
+        notes -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    shelf_books (shelf_id, book_id) {
+        shelf_id -> Int4,
+        book_id -> Int4,
+        position -> Int4,
+    }
+}
+
+table! {
+    shelves (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Text,
+        description -> Text,
+        created_at -> Timestamp,
+        query -> Nullable<Text>,
+    }
+}
+
+table! {
+    tags (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -32,7 +168,54 @@ table! {
     }
 }
 
+table! {
+    wishes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        title -> Text,
+        author -> Text,
+        isbn -> Text,
+        priority -> Int4,
+        notes -> Nullable<Text>,
+        target_price -> Nullable<Int4>,
+        where_to_buy -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+joinable!(book_contributors -> authors (author_id));
+joinable!(book_contributors -> books (book_id));
+joinable!(book_tags -> books (book_id));
+joinable!(book_tags -> tags (tag_id));
+joinable!(books -> authors (author_id));
+joinable!(borrow_requests -> books (book_id));
+joinable!(borrow_requests -> loans (loan_id));
+joinable!(goals -> users (user_id));
+joinable!(loans -> books (book_id));
+joinable!(reading_progress -> readings (reading_id));
+joinable!(readings -> books (book_id));
+joinable!(readings -> users (user_id));
+joinable!(shelf_books -> books (book_id));
+joinable!(shelf_books -> shelves (shelf_id));
+joinable!(shelves -> users (user_id));
+joinable!(tags -> users (user_id));
+joinable!(wishes -> users (user_id));
+
 allow_tables_to_appear_in_same_query!(
+    authors,
+    book_contributors,
+    book_tags,
     books,
+    borrow_requests,
+    data_migrations,
+    friends,
+    goals,
+    loans,
+    reading_progress,
+    readings,
+    shelf_books,
+    shelves,
+    tags,
     users,
+    wishes,
 );
@@ -0,0 +1,255 @@
+// Reading statistics, computed from the finished readings of a user.
+// Rereadings count as books read again, abandoned readings are ignored.
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveDateTime};
+
+const TOP_AUTHORS: usize = 10;
+
+// A finished reading with the book data used by the statistics
+#[derive(Clone, Debug, PartialEq)]
+pub struct FinishedReading {
+    pub book_id: i32,
+    pub finished_at: NaiveDateTime,
+    pub page_count: Option<i32>,
+    pub rating: Option<i32>,
+    pub language_main: String,
+    pub language_original: String,
+    pub author_code: String,
+    pub author_lf: String,
+    pub dateacquired_stamp: Option<NaiveDateTime>,
+    // first finished reading of the book, over all the years
+    pub first: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeriodCount {
+    pub year: i32,
+    // none for the yearly counts
+    pub month: Option<u32>,
+    pub books: i64,
+    // books without a page count are not counted
+    pub pages: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LanguageCount {
+    pub code: String,
+    pub count: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LanguageStats {
+    // language the books were read in
+    pub main: Vec<LanguageCount>,
+    // language they were written in
+    pub original: Vec<LanguageCount>,
+    // books read in another language than the original one
+    pub translated: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuthorCount {
+    pub code: String,
+    pub name: String,
+    pub books: i64,
+}
+
+// Days between the acquisition of a book and the end of its first reading
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AcquisitionDelay {
+    pub books: i64,
+    pub average_days: f64,
+    pub median_days: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub year: Option<i32>,
+    pub books_read: i64,
+    pub pages_read: i64,
+    pub by_year: Vec<PeriodCount>,
+    pub by_month: Vec<PeriodCount>,
+    // of the rated books read
+    pub average_rating: Option<f64>,
+    pub languages: LanguageStats,
+    // most read first
+    pub top_authors: Vec<AuthorCount>,
+    pub acquisition_delay: Option<AcquisitionDelay>,
+}
+
+fn pages(reading: &FinishedReading) -> i64 {
+    reading.page_count.unwrap_or(0) as i64
+}
+
+fn periods(readings: &[FinishedReading], with_month: bool) -> Vec<PeriodCount> {
+    let mut counts: HashMap<(i32, Option<u32>), (i64, i64)> = HashMap::new();
+    for reading in readings {
+        let month = if with_month { Some(reading.finished_at.month()) } else { None };
+        let count = counts.entry((reading.finished_at.year(), month)).or_insert((0, 0));
+        count.0 += 1;
+        count.1 += pages(reading);
+    }
+    let mut periods: Vec<PeriodCount> = counts.into_iter()
+        .map(|((year, month), (books, pages))| PeriodCount { year, month, books, pages })
+        .collect();
+    periods.sort_by_key(|period| (period.year, period.month));
+    periods
+}
+
+// Most used first, then by code
+fn language_counts<'a>(codes: impl Iterator<Item = &'a String>) -> Vec<LanguageCount> {
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    for code in codes.filter(|code| !code.is_empty()) {
+        *counts.entry(code).or_insert(0) += 1;
+    }
+    let mut counts: Vec<LanguageCount> = counts.into_iter()
+        .map(|(code, count)| LanguageCount { code: code.to_string(), count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then(a.code.cmp(&b.code)));
+    counts
+}
+
+fn languages(readings: &[FinishedReading]) -> LanguageStats {
+    LanguageStats {
+        main: language_counts(readings.iter().map(|reading| &reading.language_main)),
+        original: language_counts(readings.iter().map(|reading| &reading.language_original)),
+        translated: readings.iter()
+            .filter(|reading| !reading.language_original.is_empty() && reading.language_original != reading.language_main)
+            .count() as i64,
+    }
+}
+
+fn top_authors(readings: &[FinishedReading]) -> Vec<AuthorCount> {
+    let mut counts: HashMap<&str, AuthorCount> = HashMap::new();
+    for reading in readings.iter().filter(|reading| !reading.author_code.is_empty()) {
+        counts.entry(&reading.author_code)
+            .or_insert_with(|| AuthorCount { code: reading.author_code.clone(), name: reading.author_lf.clone(), books: 0 })
+            .books += 1;
+    }
+    let mut authors: Vec<AuthorCount> = counts.into_iter().map(|(_code, author)| author).collect();
+    authors.sort_by(|a, b| b.books.cmp(&a.books).then(a.name.cmp(&b.name)));
+    authors.truncate(TOP_AUTHORS);
+    authors
+}
+
+// Books rated several times count once
+fn average_rating(readings: &[FinishedReading]) -> Option<f64> {
+    let mut ratings: HashMap<i32, i32> = HashMap::new();
+    for reading in readings {
+        if let Some(rating) = reading.rating {
+            ratings.insert(reading.book_id, rating);
+        }
+    }
+    if ratings.is_empty() {
+        return None;
+    }
+    Some(ratings.values().sum::<i32>() as f64 / ratings.len() as f64)
+}
+
+// Of the books first read among the given readings : a book read again is counted with its first reading only
+fn acquisition_delay(readings: &[FinishedReading]) -> Option<AcquisitionDelay> {
+    let first_finish: HashMap<i32, &FinishedReading> = readings.iter()
+        .filter(|reading| reading.first)
+        .map(|reading| (reading.book_id, reading))
+        .collect();
+    let mut days: Vec<i64> = first_finish.values()
+        .filter_map(|reading| reading.dateacquired_stamp.map(|acquired| (reading.finished_at - acquired).num_days()))
+        .filter(|days| *days >= 0)
+        .collect();
+    if days.is_empty() {
+        return None;
+    }
+    days.sort();
+    Some(AcquisitionDelay {
+        books: days.len() as i64,
+        average_days: days.iter().sum::<i64>() as f64 / days.len() as f64,
+        median_days: days[days.len() / 2],
+    })
+}
+
+// Statistics of the readings finished during the given year, or of all of them :
+// the readings are already filtered, see stats_handler::finished_readings
+pub fn compute(readings: Vec<FinishedReading>, year: Option<i32>) -> Stats {
+    Stats {
+        year,
+        books_read: readings.len() as i64,
+        pages_read: readings.iter().map(pages).sum(),
+        by_year: periods(&readings, false),
+        by_month: periods(&readings, true),
+        average_rating: average_rating(&readings),
+        languages: languages(&readings),
+        top_authors: top_authors(&readings),
+        acquisition_delay: acquisition_delay(&readings),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn reading(book_id: i32, finished: (i32, u32, u32), author: &str, languages: (&str, &str)) -> FinishedReading {
+        FinishedReading {
+            book_id,
+            finished_at: NaiveDate::from_ymd(finished.0, finished.1, finished.2).and_hms(0, 0, 0),
+            page_count: Some(100 * book_id),
+            rating: Some(book_id),
+            language_main: languages.0.to_string(),
+            language_original: languages.1.to_string(),
+            author_code: author.to_lowercase(),
+            author_lf: author.to_string(),
+            dateacquired_stamp: Some(NaiveDate::from_ymd(2018, 12, 1).and_hms(0, 0, 0)),
+            first: true,
+        }
+    }
+
+    fn readings() -> Vec<FinishedReading> {
+        vec![
+            reading(1, (2019, 1, 10), "Perec", ("fr", "fr")),
+            reading(2, (2019, 1, 20), "Calvino", ("fr", "it")),
+            reading(3, (2019, 3, 1), "Perec", ("fr", "fr")),
+            // read again
+            FinishedReading { first: false, ..reading(1, (2020, 2, 1), "Perec", ("en", "fr")) },
+        ]
+    }
+
+    #[test]
+    fn counts() {
+        let stats = compute(readings(), None);
+        assert_eq!(stats.books_read, 4);
+        assert_eq!(stats.pages_read, 700);
+        assert_eq!(stats.by_year, vec![
+            PeriodCount { year: 2019, month: None, books: 3, pages: 600 },
+            PeriodCount { year: 2020, month: None, books: 1, pages: 100 },
+        ]);
+        assert_eq!(stats.by_month[0], PeriodCount { year: 2019, month: Some(1), books: 2, pages: 300 });
+        assert_eq!(stats.by_month.len(), 3);
+        assert_eq!(stats.average_rating, Some(2.0));
+        assert_eq!(stats.top_authors[0], AuthorCount { code: String::from("perec"), name: String::from("Perec"), books: 3 });
+    }
+
+    #[test]
+    fn languages_and_delays() {
+        let stats = compute(readings(), None);
+        assert_eq!(stats.languages.main[0], LanguageCount { code: String::from("fr"), count: 3 });
+        assert_eq!(stats.languages.original[0], LanguageCount { code: String::from("fr"), count: 3 });
+        assert_eq!(stats.languages.translated, 2);
+        // first readings only : 40, 50 and 90 days
+        let delay = stats.acquisition_delay.unwrap();
+        assert_eq!((delay.books, delay.median_days), (3, 50));
+        assert_eq!(delay.average_days, 60.0);
+    }
+
+    #[test]
+    fn one_year() {
+        let readings: Vec<FinishedReading> = readings().into_iter().filter(|reading| reading.finished_at.year() == 2020).collect();
+        let stats = compute(readings, Some(2020));
+        assert_eq!(stats.books_read, 1);
+        assert_eq!(stats.year, Some(2020));
+        assert_eq!(stats.languages.translated, 1);
+        // only read again this year
+        assert_eq!(stats.acquisition_delay, None);
+        assert_eq!(compute(Vec::new(), Some(2000)), Stats { year: Some(2000), ..Stats::default() });
+    }
+}
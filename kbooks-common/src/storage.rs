@@ -0,0 +1,98 @@
+// Where the files sent by the users, such as the book covers, are kept.
+// A storage maps keys to contents, the local filesystem being the first backend.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub trait Storage: Send + Sync {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()>;
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+    // Removing a missing key is not an error
+    fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+// Keys are file names, they can not reach outside the storage directory
+fn check_key(key: &str) -> io::Result<()> {
+    let valid = !key.is_empty()
+        && !key.starts_with('.')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid storage key {}", key)))
+    }
+}
+
+// Files of a local directory, created when needed
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new<P: Into<PathBuf>>(root: P) -> LocalStorage {
+        LocalStorage { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+impl Storage for LocalStorage {
+    // The file is renamed once written, so that it is never served incomplete
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(key)?;
+        fs::create_dir_all(&self.root)?;
+        let partial = self.root.join(format!(".{}.part", key));
+        fs::write(&partial, data)?;
+        fs::rename(&partial, &path)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(key)?)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage(name: &str) -> LocalStorage {
+        let root = std::env::temp_dir().join(format!("kbooks-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        LocalStorage::new(root)
+    }
+
+    #[test]
+    fn local_storage() {
+        let storage = storage("local");
+        storage.put("1-cover.jpg", b"jpeg").unwrap();
+        assert_eq!(storage.get("1-cover.jpg").unwrap(), b"jpeg".to_vec());
+        storage.put("1-cover.jpg", b"other").unwrap();
+        assert_eq!(storage.get("1-cover.jpg").unwrap(), b"other".to_vec());
+        storage.delete("1-cover.jpg").unwrap();
+        assert!(storage.get("1-cover.jpg").is_err());
+        storage.delete("1-cover.jpg").unwrap();
+        fs::remove_dir_all(storage.root()).unwrap();
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let storage = storage("invalid");
+        for key in &["", "../cover.jpg", "covers/1.jpg", ".hidden", "a b"] {
+            assert_eq!(storage.put(key, b"data").unwrap_err().kind(), io::ErrorKind::InvalidInput, "{}", key);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- unaccent() is only STABLE, an IMMUTABLE wrapper is needed to build the index
+CREATE OR REPLACE FUNCTION books_unaccent(text) RETURNS text AS
+$$ SELECT public.unaccent('public.unaccent', $1) $$
+LANGUAGE sql IMMUTABLE PARALLEL SAFE STRICT;
+
+CREATE INDEX books_search_idx ON books USING GIN (
+  to_tsvector('simple', books_unaccent(title || ' ' || author_lf || ' ' || isbn || ' ' || coalesce(review, '')))
+);
@@ -0,0 +1,31 @@
+CREATE TABLE authors (
+  id SERIAL NOT NULL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  code TEXT NOT NULL UNIQUE,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE books ADD COLUMN author_id INTEGER REFERENCES authors(id);
+
+-- Existing books : one author by name, keeping the current codes.
+-- A code shared by several names is kept by the first one, the others get the first free "<code>-<n>".
+CREATE TEMPORARY TABLE legacy_authors AS
+  SELECT author_lf AS name, MIN(author_code) AS code,
+    ROW_NUMBER() OVER (PARTITION BY MIN(author_code) ORDER BY author_lf) AS n
+  FROM books GROUP BY author_lf;
+INSERT INTO authors (name, code) SELECT name, code FROM legacy_authors WHERE n = 1;
+WITH RECURSIVE suffixes(k) AS (
+    SELECT 2 UNION ALL SELECT k + 1 FROM suffixes WHERE k <= (SELECT COUNT(*) FROM legacy_authors)
+  ),
+  free_codes AS (
+    SELECT shared.code, shared.code || '-' || k AS free_code,
+      ROW_NUMBER() OVER (PARTITION BY shared.code ORDER BY k) AS n
+    FROM (SELECT DISTINCT code FROM legacy_authors WHERE n > 1) AS shared, suffixes
+    WHERE shared.code || '-' || k NOT IN (SELECT code FROM authors)
+  )
+INSERT INTO authors (name, code)
+  SELECT legacy_authors.name, free_codes.free_code
+  FROM legacy_authors JOIN free_codes ON free_codes.code = legacy_authors.code AND free_codes.n = legacy_authors.n - 1;
+DROP TABLE legacy_authors;
+UPDATE books SET author_id = (SELECT id FROM authors WHERE authors.name = books.author_lf);
+UPDATE books SET author_code = (SELECT code FROM authors WHERE authors.id = books.author_id);
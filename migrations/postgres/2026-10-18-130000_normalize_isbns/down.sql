@@ -0,0 +1,2 @@
+-- The original formatting is lost
+SELECT 1;
@@ -0,0 +1,2 @@
+-- ISBNs are stored without hyphens nor spaces
+UPDATE books SET isbn = upper(replace(replace(isbn, '-', ''), ' ', ''));
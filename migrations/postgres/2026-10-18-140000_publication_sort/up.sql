@@ -0,0 +1,25 @@
+-- Integer form of the publication dates, to sort them (see kbooks-common partial_date) :
+-- year * 10000 + month * 100 + day, missing parts being 0
+ALTER TABLE books ADD COLUMN publication_sort INTEGER;
+
+-- Common formats to their canonical form, the other ones are converted by the data migrations run at startup
+-- (kbooks-common data_migration_handler)
+UPDATE books SET publicationdate = trim(publicationdate);
+UPDATE books SET publicationdate =
+    split_part(publicationdate, '/', 3) || '-' || lpad(split_part(publicationdate, '/', 2), 2, '0') || '-' || lpad(split_part(publicationdate, '/', 1), 2, '0')
+  WHERE publicationdate ~ '^[0-9]{1,2}/[0-9]{1,2}/[0-9]{4}$';
+UPDATE books SET publicationdate =
+    split_part(publicationdate, '/', 2) || '-' || lpad(split_part(publicationdate, '/', 1), 2, '0')
+  WHERE publicationdate ~ '^[0-9]{1,2}/[0-9]{4}$';
+UPDATE books SET publicationdate = replace(publicationdate, '/', '-')
+  WHERE publicationdate ~ '^[0-9]{4}/[0-9]{2}(/[0-9]{2})?$';
+
+UPDATE books SET publication_sort = CAST(publicationdate AS INTEGER) * 10000
+  WHERE publicationdate ~ '^[0-9]{4}$' AND publicationdate <> '0000';
+UPDATE books SET publication_sort = CAST(substr(publicationdate, 1, 4) AS INTEGER) * 10000
+    + CAST(substr(publicationdate, 6, 2) AS INTEGER) * 100
+  WHERE publicationdate ~ '^[0-9]{4}-(0[1-9]|1[0-2])$';
+UPDATE books SET publication_sort = CAST(substr(publicationdate, 1, 4) AS INTEGER) * 10000
+    + CAST(substr(publicationdate, 6, 2) AS INTEGER) * 100
+    + CAST(substr(publicationdate, 9, 2) AS INTEGER)
+  WHERE publicationdate ~ '^[0-9]{4}-(0[1-9]|1[0-2])-(0[1-9]|[12][0-9]|3[01])$';
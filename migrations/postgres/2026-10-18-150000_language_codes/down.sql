@@ -0,0 +1,2 @@
+-- The original case is lost
+SELECT 1;
@@ -0,0 +1,2 @@
+ALTER TABLE books DROP COLUMN reading_status;
+DROP TABLE readings;
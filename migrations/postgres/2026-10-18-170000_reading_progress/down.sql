@@ -0,0 +1,2 @@
+DROP TABLE reading_progress;
+ALTER TABLE books DROP COLUMN page_count;
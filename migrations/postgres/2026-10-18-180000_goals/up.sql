@@ -0,0 +1,13 @@
+-- Yearly reading goals : a number of books or of pages (kind), possibly read in one language
+CREATE TABLE goals (
+  id SERIAL NOT NULL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  year INTEGER NOT NULL,
+  kind TEXT NOT NULL,
+  target INTEGER NOT NULL,
+  language TEXT,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+-- A user has one goal per year, kind and language.
+-- The goals for all the languages have no language, hence the coalesce.
+CREATE UNIQUE INDEX goals_unique_idx ON goals (user_id, year, kind, coalesce(language, ''));
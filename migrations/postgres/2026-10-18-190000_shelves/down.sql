@@ -0,0 +1,2 @@
+DROP TABLE shelf_books;
+DROP TABLE shelves;
@@ -0,0 +1,17 @@
+-- Named collections of books, ordered by hand
+CREATE TABLE shelves (
+  id SERIAL NOT NULL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  name TEXT NOT NULL,
+  description TEXT NOT NULL DEFAULT '',
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (user_id, name)
+);
+
+CREATE TABLE shelf_books (
+  shelf_id INTEGER NOT NULL REFERENCES shelves(id) ON DELETE CASCADE,
+  book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  PRIMARY KEY (shelf_id, book_id)
+);
+CREATE INDEX shelf_books_book_idx ON shelf_books (book_id);
@@ -0,0 +1,16 @@
+-- Books the user wants to buy : once acquired, they become books of the library
+CREATE TABLE wishes (
+  id SERIAL NOT NULL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  title TEXT NOT NULL,
+  author TEXT NOT NULL DEFAULT '',
+  isbn TEXT NOT NULL DEFAULT '',
+  -- from 1 (most wanted) to 5
+  priority INTEGER NOT NULL DEFAULT 3,
+  notes TEXT,
+  -- in cents
+  target_price INTEGER,
+  where_to_buy TEXT,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX wishes_user_idx ON wishes (user_id);
@@ -0,0 +1,18 @@
+-- Lending history of the books : a loan is in progress until returned_at is set
+CREATE TABLE loans (
+  id SERIAL NOT NULL PRIMARY KEY,
+  book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  -- name given by the lender, or login of a registered borrower
+  borrower_name TEXT NOT NULL,
+  borrower_id INTEGER REFERENCES users(id),
+  lent_at TIMESTAMP NOT NULL,
+  due_at TIMESTAMP,
+  returned_at TIMESTAMP,
+  notes TEXT,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX loans_book_idx ON loans (book_id);
+CREATE INDEX loans_user_idx ON loans (user_id);
+-- A book is lent to one borrower at a time
+CREATE UNIQUE INDEX loans_in_progress_idx ON loans (book_id) WHERE returned_at IS NULL;
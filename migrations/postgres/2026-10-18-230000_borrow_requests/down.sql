@@ -0,0 +1,2 @@
+DROP TABLE friends;
+DROP TABLE borrow_requests;
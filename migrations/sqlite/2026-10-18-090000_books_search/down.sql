@@ -0,0 +1,4 @@
+DROP TRIGGER books_fts_update;
+DROP TRIGGER books_fts_delete;
+DROP TRIGGER books_fts_insert;
+DROP TABLE books_fts;
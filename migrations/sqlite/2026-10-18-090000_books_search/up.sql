@@ -0,0 +1,29 @@
+CREATE VIRTUAL TABLE books_fts USING fts5(
+  title,
+  author_lf,
+  isbn,
+  review,
+  content='books',
+  content_rowid='id',
+  tokenize='unicode61 remove_diacritics 1'
+);
+
+INSERT INTO books_fts(rowid, title, author_lf, isbn, review)
+  SELECT id, title, author_lf, isbn, review FROM books;
+
+CREATE TRIGGER books_fts_insert AFTER INSERT ON books BEGIN
+  INSERT INTO books_fts(rowid, title, author_lf, isbn, review)
+    VALUES (new.id, new.title, new.author_lf, new.isbn, new.review);
+END;
+
+CREATE TRIGGER books_fts_delete AFTER DELETE ON books BEGIN
+  INSERT INTO books_fts(books_fts, rowid, title, author_lf, isbn, review)
+    VALUES ('delete', old.id, old.title, old.author_lf, old.isbn, old.review);
+END;
+
+CREATE TRIGGER books_fts_update AFTER UPDATE ON books BEGIN
+  INSERT INTO books_fts(books_fts, rowid, title, author_lf, isbn, review)
+    VALUES ('delete', old.id, old.title, old.author_lf, old.isbn, old.review);
+  INSERT INTO books_fts(rowid, title, author_lf, isbn, review)
+    VALUES (new.id, new.title, new.author_lf, new.isbn, new.review);
+END;
@@ -0,0 +1,11 @@
+CREATE TABLE book_contributors (
+  book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+  author_id INTEGER NOT NULL REFERENCES authors(id),
+  role TEXT NOT NULL,
+  position INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (book_id, author_id, role)
+);
+
+-- The primary author of existing books
+INSERT INTO book_contributors (book_id, author_id, role, position)
+  SELECT id, author_id, 'author', 0 FROM books WHERE author_id IS NOT NULL;
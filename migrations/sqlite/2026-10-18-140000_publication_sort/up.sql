@@ -0,0 +1,27 @@
+-- Integer form of the publication dates, to sort them (see kbooks-common partial_date) :
+-- year * 10000 + month * 100 + day, missing parts being 0
+ALTER TABLE books ADD COLUMN publication_sort INTEGER;
+
+-- Common formats to their canonical form, the other ones are converted by the data migrations run at startup
+-- (kbooks-common data_migration_handler)
+UPDATE books SET publicationdate = trim(publicationdate);
+UPDATE books SET publicationdate = substr(publicationdate, 7, 4) || '-' || substr(publicationdate, 4, 2) || '-' || substr(publicationdate, 1, 2)
+  WHERE publicationdate GLOB '[0-9][0-9]/[0-9][0-9]/[0-9][0-9][0-9][0-9]';
+UPDATE books SET publicationdate = substr(publicationdate, 4, 4) || '-' || substr(publicationdate, 1, 2)
+  WHERE publicationdate GLOB '[0-9][0-9]/[0-9][0-9][0-9][0-9]';
+UPDATE books SET publicationdate = replace(publicationdate, '/', '-')
+  WHERE publicationdate GLOB '[0-9][0-9][0-9][0-9]/[0-9][0-9]'
+     OR publicationdate GLOB '[0-9][0-9][0-9][0-9]/[0-9][0-9]/[0-9][0-9]';
+
+UPDATE books SET publication_sort = CAST(publicationdate AS INTEGER) * 10000
+  WHERE publicationdate GLOB '[0-9][0-9][0-9][0-9]' AND publicationdate <> '0000';
+UPDATE books SET publication_sort = CAST(substr(publicationdate, 1, 4) AS INTEGER) * 10000
+    + CAST(substr(publicationdate, 6, 2) AS INTEGER) * 100
+  WHERE publicationdate GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]'
+    AND CAST(substr(publicationdate, 6, 2) AS INTEGER) BETWEEN 1 AND 12;
+UPDATE books SET publication_sort = CAST(substr(publicationdate, 1, 4) AS INTEGER) * 10000
+    + CAST(substr(publicationdate, 6, 2) AS INTEGER) * 100
+    + CAST(substr(publicationdate, 9, 2) AS INTEGER)
+  WHERE publicationdate GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]'
+    AND CAST(substr(publicationdate, 6, 2) AS INTEGER) BETWEEN 1 AND 12
+    AND CAST(substr(publicationdate, 9, 2) AS INTEGER) BETWEEN 1 AND 31;
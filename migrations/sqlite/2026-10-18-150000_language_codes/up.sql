@@ -0,0 +1,5 @@
+-- ISO 639-1 codes are stored in lowercase, the names and other codes are converted by the data migrations run
+-- at startup (kbooks-common data_migration_handler)
+UPDATE books SET language_main = lower(trim(language_main)) WHERE length(trim(language_main)) = 2;
+UPDATE books SET language_secondary = lower(trim(language_secondary)) WHERE length(trim(language_secondary)) = 2;
+UPDATE books SET language_original = lower(trim(language_original)) WHERE length(trim(language_original)) = 2;
@@ -0,0 +1,24 @@
+-- Every reading of a book : a book can be read several times, or abandoned
+CREATE TABLE readings (
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  started_at DATETIME,
+  finished_at DATETIME,
+  abandoned BOOLEAN NOT NULL DEFAULT 0,
+  notes TEXT,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX readings_book_idx ON readings (book_id);
+-- A book has one reading in progress at most
+CREATE UNIQUE INDEX readings_in_progress_idx ON readings (book_id) WHERE finished_at IS NULL;
+
+-- Status of the last reading, kept by kbooks-common reading_handler : to-read, reading, read or abandoned
+ALTER TABLE books ADD COLUMN reading_status TEXT NOT NULL DEFAULT 'to-read';
+
+-- The dates recorded on the books become their first reading
+INSERT INTO readings (book_id, user_id, started_at, finished_at, abandoned, created_at)
+  SELECT id, user_id, started_stamp, finished_stamp, 0, created_at FROM books
+  WHERE started_stamp IS NOT NULL OR finished_stamp IS NOT NULL;
+UPDATE books SET reading_status = 'read' WHERE finished_stamp IS NOT NULL;
+UPDATE books SET reading_status = 'reading' WHERE started_stamp IS NOT NULL AND finished_stamp IS NULL;
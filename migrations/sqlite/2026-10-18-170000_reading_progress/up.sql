@@ -0,0 +1,11 @@
+ALTER TABLE books ADD COLUMN page_count INTEGER;
+
+-- Progress of a reading over time : the page reached when known, and the percentage read
+CREATE TABLE reading_progress (
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  reading_id INTEGER NOT NULL REFERENCES readings(id) ON DELETE CASCADE,
+  page INTEGER,
+  percent INTEGER NOT NULL,
+  recorded_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX reading_progress_reading_idx ON reading_progress (reading_id);
@@ -0,0 +1 @@
+ALTER TABLE shelves DROP COLUMN query;
@@ -0,0 +1,2 @@
+-- Filter expression of a smart shelf, whose books are the ones matching it
+ALTER TABLE shelves ADD COLUMN query TEXT;
@@ -0,0 +1,18 @@
+-- Lending history of the books : a loan is in progress until returned_at is set
+CREATE TABLE loans (
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  -- name given by the lender, or login of a registered borrower
+  borrower_name TEXT NOT NULL,
+  borrower_id INTEGER REFERENCES users(id),
+  lent_at DATETIME NOT NULL,
+  due_at DATETIME,
+  returned_at DATETIME,
+  notes TEXT,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX loans_book_idx ON loans (book_id);
+CREATE INDEX loans_user_idx ON loans (user_id);
+-- A book is lent to one borrower at a time
+CREATE UNIQUE INDEX loans_in_progress_idx ON loans (book_id) WHERE returned_at IS NULL;
@@ -0,0 +1,25 @@
+-- Requests to borrow the book of another user : pending, then accepted or declined.
+-- An accepted request is returned with its loan.
+CREATE TABLE borrow_requests (
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+  owner_id INTEGER NOT NULL REFERENCES users(id),
+  requester_id INTEGER NOT NULL REFERENCES users(id),
+  state TEXT NOT NULL DEFAULT 'pending',
+  message TEXT,
+  -- the loan created when the request is accepted
+  loan_id INTEGER REFERENCES loans(id) ON DELETE SET NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX borrow_requests_owner_idx ON borrow_requests (owner_id);
+CREATE INDEX borrow_requests_requester_idx ON borrow_requests (requester_id);
+
+-- A library is visible to the friends chosen by its owner (user_id), who can ask to borrow its books
+CREATE TABLE friends (
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  friend_id INTEGER NOT NULL REFERENCES users(id),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (user_id, friend_id)
+);
+CREATE INDEX friends_friend_idx ON friends (friend_id);
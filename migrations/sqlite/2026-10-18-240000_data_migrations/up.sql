@@ -0,0 +1,6 @@
+-- Conversions of existing rows written in Rust (see kbooks-common repository/data_migration_handler),
+-- run once when the API or the CLI starts
+CREATE TABLE data_migrations (
+  name TEXT NOT NULL PRIMARY KEY,
+  applied_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);